@@ -1,6 +1,7 @@
 #[cfg(windows)]
 fn main() {
     windows::build! {
+        Windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS},
         Windows::Win32::System::Power::EXECUTION_STATE,
         Windows::Win32::System::Power::SetThreadExecutionState,
         Windows::Win32::UI::KeyboardAndMouseInput::GetDoubleClickTime,
@@ -0,0 +1,70 @@
+use crate::readback::ReadbackBuffer;
+use anyhow::{anyhow, Result};
+
+/// Offscreen target to read back a rendered frame,
+/// the swap chain texture can't be the source of a copy
+pub struct WindowCapture {
+    format: wgpu::TextureFormat,
+    size: wgpu::Extent3d,
+    texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    buffer: ReadbackBuffer,
+}
+
+impl WindowCapture {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let size = wgpu::Extent3d {
+            width: width.max(1),
+            height: height.max(1),
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Capture Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT | wgpu::TextureUsage::COPY_SRC,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let buffer = ReadbackBuffer::new(device, "Capture Buffer", size);
+
+        Self {
+            format,
+            size,
+            texture,
+            view,
+            buffer,
+        }
+    }
+
+    /// Copy the rendered texture to the readback buffer after drawing to the view
+    pub fn copy_to_buffer(&self, encoder: &mut wgpu::CommandEncoder) {
+        self.buffer.copy_from(encoder, &self.texture);
+    }
+
+    /// Map the buffer after the copy is submitted, the rows are unpadded and converted to RGBA
+    pub fn read(self, device: &wgpu::Device) -> Result<image::RgbaImage> {
+        let mut pixels = self.buffer.read(device)?;
+
+        match self.format {
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => {
+                for pixel in pixels.chunks_exact_mut(4) {
+                    pixel.swap(0, 2);
+                }
+            }
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => {}
+            format => return Err(anyhow!("unsupported frame format: {:?}", format)),
+        }
+
+        image::RgbaImage::from_raw(self.size.width, self.size.height, pixels)
+            .ok_or_else(|| anyhow!("invalid frame size."))
+    }
+}
@@ -0,0 +1,133 @@
+#[cfg(not(feature = "color_management"))]
+use anyhow::anyhow;
+use anyhow::Result;
+use std::io::Read;
+
+const JPEG_SOI: [u8; 2] = [0xff, 0xd8];
+const JPEG_ICC_ID: &[u8] = b"ICC_PROFILE\0";
+const PNG_SIGNATURE_TAIL: [u8; 6] = [b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+const MAX_PNG_PROFILE_NAME: usize = 80;
+
+/// Read the embedded ICC profile of a JPEG or PNG image, None if the image has no profile
+pub fn read_icc_profile<R: Read>(reader: &mut R) -> Option<Vec<u8>> {
+    let mut head = [0; 2];
+    reader.read_exact(&mut head).ok()?;
+    if head == JPEG_SOI {
+        return read_jpeg_profile(reader);
+    }
+
+    let mut tail = [0; 6];
+    reader.read_exact(&mut tail).ok()?;
+    if head == [0x89, b'P'] && tail == PNG_SIGNATURE_TAIL {
+        return read_png_profile(reader);
+    }
+    None
+}
+
+/// Join the 'ICC_PROFILE' APP2 segments in the sequence order
+fn read_jpeg_profile<R: Read>(reader: &mut R) -> Option<Vec<u8>> {
+    let mut chunks: Vec<(u8, Vec<u8>)> = Vec::new();
+    loop {
+        let mut marker = [0; 2];
+        reader.read_exact(&mut marker).ok()?;
+        if marker[0] != 0xff {
+            return None;
+        }
+        // The segments end at the start of scan or the end of image
+        if marker[1] == 0xda || marker[1] == 0xd9 {
+            break;
+        }
+
+        let mut length = [0; 2];
+        reader.read_exact(&mut length).ok()?;
+        let length = u16::from_be_bytes(length) as u64;
+        if length < 2 {
+            return None;
+        }
+        let mut data = Vec::new();
+        reader
+            .by_ref()
+            .take(length - 2)
+            .read_to_end(&mut data)
+            .ok()?;
+
+        // APP2: 'ICC_PROFILE\0', sequence number, number of chunks, profile data
+        if marker[1] == 0xe2 && data.starts_with(JPEG_ICC_ID) && data.len() > JPEG_ICC_ID.len() + 2
+        {
+            let sequence = data[JPEG_ICC_ID.len()];
+            chunks.push((sequence, data[JPEG_ICC_ID.len() + 2..].to_vec()));
+        }
+    }
+
+    if chunks.is_empty() {
+        return None;
+    }
+    chunks.sort_by_key(|(sequence, _)| *sequence);
+    Some(chunks.into_iter().flat_map(|(_, data)| data).collect())
+}
+
+/// Decompress the 'iCCP' chunk, it must appear before the first 'IDAT'
+fn read_png_profile<R: Read>(reader: &mut R) -> Option<Vec<u8>> {
+    loop {
+        let mut header = [0; 8];
+        reader.read_exact(&mut header).ok()?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as u64;
+        match &header[4..] {
+            b"iCCP" => {
+                let mut data = Vec::new();
+                reader.by_ref().take(length).read_to_end(&mut data).ok()?;
+                // Profile name, null separator, compression method, compressed profile
+                let name_end = data
+                    .iter()
+                    .take(MAX_PNG_PROFILE_NAME)
+                    .position(|v| *v == 0)?;
+                return inflate_zlib(data.get(name_end + 2..)?);
+            }
+            b"IDAT" | b"IEND" => return None,
+            _ => {
+                // Skip the chunk data and CRC
+                let skip = length + 4;
+                let skipped =
+                    std::io::copy(&mut reader.by_ref().take(skip), &mut std::io::sink()).ok()?;
+                if skipped != skip {
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "color_management")]
+fn inflate_zlib(data: &[u8]) -> Option<Vec<u8>> {
+    miniz_oxide::inflate::decompress_to_vec_zlib(data).ok()
+}
+
+#[cfg(not(feature = "color_management"))]
+fn inflate_zlib(_data: &[u8]) -> Option<Vec<u8>> {
+    None
+}
+
+/// Convert the pixels from the embedded profile to sRGB
+#[cfg(feature = "color_management")]
+pub fn convert_to_srgb(image: &mut image::RgbaImage, profile: &[u8]) -> Result<()> {
+    use lcms2::{Intent, PixelFormat, Profile, Transform};
+
+    let input = Profile::new_icc(profile)?;
+    let output = Profile::new_srgb();
+    let transform: Transform<[u8; 4], [u8; 4]> = Transform::new(
+        &input,
+        PixelFormat::RGBA_8,
+        &output,
+        PixelFormat::RGBA_8,
+        Intent::Perceptual,
+    )?;
+    let pixels: &mut [[u8; 4]] = bytemuck::cast_slice_mut(&mut **image);
+    transform.transform_in_place(pixels);
+
+    Ok(())
+}
+
+#[cfg(not(feature = "color_management"))]
+pub fn convert_to_srgb(_image: &mut image::RgbaImage, _profile: &[u8]) -> Result<()> {
+    Err(anyhow!("color management is not supported in this build."))
+}
@@ -3,6 +3,7 @@ mod bindings {
 }
 
 use bindings::Windows::Win32::{
+    System::Console::{AttachConsole, ATTACH_PARENT_PROCESS},
     System::Power::{
         SetThreadExecutionState, ES_CONTINUOUS, ES_DISPLAY_REQUIRED, ES_SYSTEM_REQUIRED,
     },
@@ -19,3 +20,10 @@ pub fn stop_screensaver() {
 pub fn get_double_click_time_ms() -> u32 {
     unsafe { GetDoubleClickTime() }
 }
+
+/// Attach to the console of the parent process to print, the release build has no console
+pub fn attach_parent_console() {
+    unsafe {
+        let _attached = AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
@@ -1,9 +1,20 @@
-use anyhow::Result;
+use crate::transition;
+use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 pub const CONF_FILE_EXTENSION: &str = "sldshow";
+/// Largest `cache_extent`, each step keeps two more decoded images in memory
+const MAX_CACHE_EXTENT: usize = 100;
+/// Longest `gap_ms`, the slideshow would look stopped
+const MAX_GAP_MS: u32 = 60_000;
+/// Sample counts of the image quad, wgpu only guarantees 4 and doesn't report the others
+pub const SUPPORTED_MSAA_SAMPLES: [u32; 2] = [1, 4];
+const GPU_BACKEND_NAMES: [&str; 5] = ["vulkan", "dx12", "dx11", "metal", "gl"];
 
 #[derive(Debug, Serialize, Deserialize, Default, Clone)]
 #[serde(default)]
@@ -12,6 +23,7 @@ pub struct Config {
     pub viewer: Viewer,
     pub transition: Transition,
     pub style: Style,
+    pub keybindings: HashMap<String, KeyNames>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -20,11 +32,18 @@ pub struct Window {
     pub width: u32,
     pub height: u32,
     pub fullscreen: bool,
+    pub fullscreen_span: bool,
     pub always_on_top: bool,
     pub titlebar: bool,
     pub resizable: bool,
     pub monitor_index: usize,
     pub cursor_auto_hide: bool,
+    pub restore_geometry: bool,
+    pub lock_aspect: bool,
+    pub vsync: VsyncMode,
+    /// 'vulkan', 'dx12', 'dx11', 'metal' or 'gl', the primary backends if None
+    pub gpu_backend: Option<String>,
+    pub gpu_power: GpuPower,
 }
 
 impl Default for Window {
@@ -33,11 +52,17 @@ impl Default for Window {
             width: 1280,
             height: 720,
             fullscreen: false,
+            fullscreen_span: false,
             always_on_top: false,
             titlebar: false,
             resizable: false,
             monitor_index: 0,
             cursor_auto_hide: false,
+            restore_geometry: false,
+            lock_aspect: false,
+            vsync: VsyncMode::Fifo,
+            gpu_backend: None,
+            gpu_power: GpuPower::Low,
         }
     }
 }
@@ -47,12 +72,64 @@ impl Default for Window {
 pub struct Viewer {
     pub image_paths: Vec<String>,
     pub timer: u32,
+    pub manual: bool,
     pub scan_subfolders: bool,
+    /// Show the folder name when the next image is in another folder
+    pub announce_folders: bool,
+    pub max_depth: Option<usize>,
+    pub follow_symlinks: bool,
+    pub tiff_pages: bool,
+    pub favorites_dir: Option<String>,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub sort: SortOrder,
+    pub natural_sort: bool,
+    pub case_insensitive_sort: bool,
     pub shuffle: bool,
+    pub shuffle_seed: Option<u64>,
+    pub reshuffle_each_loop: bool,
     pub pause_at_last: bool,
+    pub wrap: bool,
+    pub wait_for_load: bool,
+    pub texture_target: TextureTarget,
+    pub pause_on_activity: bool,
+    pub loop_count: u32,
+    pub on_finish: Finish,
+    pub idle_exit_secs: Option<u32>,
+    pub advance_sound: Option<PathBuf>,
+    pub advance_sound_volume: f32,
     pub resize_filter: ResizeFilterType,
+    pub gpu_resize: bool,
+    pub downscale_decode: bool,
+    pub color_management: bool,
+    /// Rotate and flip the images by the EXIF orientation tag
+    pub use_exif_orientation: bool,
     pub stop_screensaver: bool,
     pub cache_extent: usize,
+    /// Columns and rows of the contact sheet, shows that many images at once
+    pub grid: Option<[u32; 2]>,
+    /// Threads decoding the images to preload, 0 for the number of CPU cores
+    pub decode_threads: usize,
+    pub cache_max_bytes: Option<usize>,
+    /// Keep the resized images on disk to skip decoding them on the next launches
+    pub disk_cache: bool,
+    pub disk_cache_max_mb: u64,
+    pub preload_bias: PreloadBias,
+    pub fit_mode: FitMode,
+    pub no_upscale: bool,
+    pub watch_folder: bool,
+    /// Decode the current image again if its file was modified while the window was inactive
+    pub reload_on_focus: bool,
+    /// Lock the window in fullscreen and ignore the quit inputs, Ctrl+Shift+Q still quits
+    pub kiosk: bool,
+    pub ken_burns: bool,
+    pub ken_burns_intensity: f32,
+    pub pixel_shift: bool,
+    pub pixel_shift_blank: bool,
+    pub resume: bool,
+    pub reopen_last: bool,
+    pub control_port: Option<u16>,
+    pub control_address: String,
 }
 
 impl Default for Viewer {
@@ -60,12 +137,57 @@ impl Default for Viewer {
         Self {
             image_paths: Vec::new(),
             timer: 10,
+            manual: false,
             scan_subfolders: false,
+            announce_folders: false,
+            max_depth: None,
+            follow_symlinks: true,
+            tiff_pages: false,
+            favorites_dir: None,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            sort: SortOrder::Name,
+            natural_sort: true,
+            case_insensitive_sort: false,
             shuffle: false,
+            shuffle_seed: None,
+            reshuffle_each_loop: false,
             pause_at_last: false,
+            wrap: true,
+            wait_for_load: false,
+            texture_target: TextureTarget::Window,
+            pause_on_activity: false,
+            loop_count: 0,
+            on_finish: Finish::Quit,
+            idle_exit_secs: None,
+            advance_sound: None,
+            advance_sound_volume: 1.0,
             resize_filter: ResizeFilterType::Linear,
+            gpu_resize: false,
+            downscale_decode: false,
+            color_management: false,
+            use_exif_orientation: true,
             stop_screensaver: false,
             cache_extent: 3,
+            grid: None,
+            decode_threads: 0,
+            cache_max_bytes: None,
+            disk_cache: false,
+            disk_cache_max_mb: 1024,
+            preload_bias: PreloadBias::Balanced,
+            fit_mode: FitMode::Fit,
+            no_upscale: false,
+            watch_folder: false,
+            reload_on_focus: false,
+            kiosk: false,
+            ken_burns: false,
+            ken_burns_intensity: 0.15,
+            pixel_shift: false,
+            pixel_shift_blank: false,
+            resume: false,
+            reopen_last: false,
+            control_port: None,
+            control_address: "127.0.0.1".to_string(),
         }
     }
 }
@@ -73,17 +195,32 @@ impl Default for Viewer {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(default)]
 pub struct Transition {
+    pub enabled: bool,
     pub time: f32,
     pub fps: f32,
+    /// Frame rate below which a transition frame counts as slow, 0 to disable
+    pub min_fps: f32,
+    pub slow_frame: SlowFrame,
     pub random: bool,
+    pub effect: String,
+    /// Background shown between the slideshow images in milliseconds
+    pub gap_ms: u32,
+    /// External WGSL file replacing the built-in transition shader
+    pub shader_path: Option<PathBuf>,
 }
 
 impl Default for Transition {
     fn default() -> Self {
         Self {
+            enabled: true,
             time: 0.5,
             fps: 30.0,
+            min_fps: 0.0,
+            slow_frame: SlowFrame::Finish,
             random: false,
+            effect: "fade".to_string(),
+            gap_ms: 0,
+            shader_path: None,
         }
     }
 }
@@ -92,22 +229,125 @@ impl Default for Transition {
 #[serde(default)]
 pub struct Style {
     pub bg_color: [u8; 4],
+    pub bg_pattern: BgPattern,
+    pub letterbox_color: Option<[u8; 4]>,
+    pub border_px: u32,
+    pub border_color: [u8; 4],
     pub text_color: [u8; 4],
     pub show_image_path: bool,
+    pub show_image_details: bool,
+    pub show_progress_bar: bool,
+    pub show_caption: bool,
     pub font_name: Option<String>,
+    /// Font of the OSD messages, `font_name` if None
+    pub font_name_osd: Option<String>,
+    /// Font of the image path, `font_name` if None
+    pub font_name_path: Option<String>,
+    /// Fallback fonts of the glyphs missing in the fonts above, e.g. CJK and emoji
+    pub font_names: Vec<String>,
     pub font_size_osd: f32,
     pub font_size_image_path: f32,
+    pub osd_duration_secs: f32,
+    pub osd_position: Corner,
+    pub msaa_samples: u32,
+    pub dim_schedule: Option<DimSchedule>,
 }
 
 impl Default for Style {
     fn default() -> Self {
         Self {
             bg_color: [0, 0, 0, 255],
+            bg_pattern: BgPattern::Solid,
+            letterbox_color: None,
+            border_px: 0,
+            border_color: [255, 255, 255, 255],
             text_color: [255, 255, 255, 255],
             show_image_path: false,
+            show_image_details: false,
+            show_progress_bar: false,
+            show_caption: false,
             font_name: None,
+            font_name_osd: None,
+            font_name_path: None,
+            font_names: vec![],
             font_size_osd: 18.0,
             font_size_image_path: 12.0,
+            osd_duration_secs: 3.0,
+            osd_position: Corner::TopRight,
+            msaa_samples: 1,
+            dim_schedule: None,
+        }
+    }
+}
+
+/// Local time window the image is dimmed in, e.g. '22:00' to '06:00' over midnight
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DimSchedule {
+    /// 'HH:MM'
+    pub start: String,
+    /// 'HH:MM', the end minute is not dimmed
+    pub end: String,
+    /// Brightness multiplier in 0.0-1.0
+    pub factor: f32,
+}
+
+impl DimSchedule {
+    /// Get the brightness multiplier at the minute of the day (0-1439)
+    pub fn factor_at(&self, minute: u32) -> f32 {
+        let (start, end) = match (parse_time(&self.start), parse_time(&self.end)) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return 1.0,
+        };
+        let active = if start <= end {
+            (start..end).contains(&minute)
+        } else {
+            // Wraps around midnight
+            minute >= start || minute < end
+        };
+        if active {
+            self.factor
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Parse 'HH:MM' into the minute of the day
+fn parse_time(s: &str) -> Option<u32> {
+    let (hour, minute) = s.trim().split_once(':')?;
+    let (hour, minute) = (hour.parse::<u32>().ok()?, minute.parse::<u32>().ok()?);
+    if hour < 24 && minute < 60 {
+        Some(hour * 60 + minute)
+    } else {
+        None
+    }
+}
+
+/// A key name or a list of key names of an action,
+/// or mouse button numbers of `mouse_back` and `mouse_forward`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum KeyNames {
+    One(String),
+    Many(Vec<String>),
+    Button(u16),
+    Buttons(Vec<u16>),
+}
+
+impl KeyNames {
+    pub fn to_vec(&self) -> Vec<&str> {
+        match self {
+            KeyNames::One(v) => vec![v.as_str()],
+            KeyNames::Many(v) => v.iter().map(|v| v.as_str()).collect(),
+            KeyNames::Button(_) | KeyNames::Buttons(_) => vec![],
+        }
+    }
+
+    pub fn buttons(&self) -> Vec<u16> {
+        match self {
+            KeyNames::Button(v) => vec![*v],
+            KeyNames::Buttons(v) => v.clone(),
+            KeyNames::One(_) | KeyNames::Many(_) => vec![],
         }
     }
 }
@@ -121,9 +361,390 @@ pub enum ResizeFilterType {
     Lanczos3,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    Name,
+    NameDesc,
+    ModifiedTime,
+    ModifiedTimeDesc,
+    Size,
+    /// EXIF DateTimeOriginal, the modified time for the files without it
+    CaptureTime,
+    Random,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Finish {
+    Quit,
+    Pause,
+    Loop,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum FitMode {
+    Fit,
+    Fill,
+    Stretch,
+    Actual,
+    /// Fit the window width and scroll the tall images vertically
+    Width,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum BgPattern {
+    Solid,
+    Checker,
+}
+
+/// Handling of the transition frames slower than `min_fps`
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum SlowFrame {
+    /// Jump to the end of the transition, keeps the duration
+    Finish,
+    /// Advance by one `min_fps` frame at most, keeps the motion smooth but takes longer
+    Stretch,
+}
+
+/// Presentation of the rendered frames
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum VsyncMode {
+    /// Wait for the vertical blank, no tearing
+    Fifo,
+    /// Replace the queued frame with the newest one, lower latency without tearing
+    Mailbox,
+    /// Present right away, may tear
+    Immediate,
+}
+
+/// GPU preference of the adapter request
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum GpuPower {
+    /// Integrated GPU on the laptops
+    Low,
+    /// Discrete GPU on the laptops
+    High,
+}
+
+/// Size of the texture the images are resized to
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum TextureTarget {
+    /// The initial window size
+    Window,
+    /// The resolution of the monitor, sharper in fullscreen but uses more memory
+    Monitor,
+    /// The current window size, the images are resized again after the window is resized
+    FollowWindow,
+}
+
+/// Direction the cache prefers to preload and keep
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum PreloadBias {
+    /// Alternate the next and previous images
+    Balanced,
+    /// Preload the next images first, evict the previous images first
+    Forward,
+    /// Preload the previous images first, evict the next images first
+    Backward,
+}
+
+/// Position of the OSD message in the window
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Center,
+}
+
+impl FitMode {
+    pub fn next(self) -> Self {
+        match self {
+            FitMode::Fit => FitMode::Fill,
+            FitMode::Fill => FitMode::Stretch,
+            FitMode::Stretch => FitMode::Actual,
+            FitMode::Actual => FitMode::Width,
+            FitMode::Width => FitMode::Fit,
+        }
+    }
+}
+
+impl Config {
+    /// Reset the out-of-range values to the defaults, return a warning for each of them
+    pub fn validate(&mut self) -> Vec<String> {
+        let default = Config::default();
+        let mut warnings = Vec::new();
+        let mut check = |name: &str, valid: bool, expected: &str| {
+            if !valid {
+                warnings.push(format!(
+                    "Invalid config value '{}', expected {}, using the default",
+                    name, expected
+                ));
+            }
+            valid
+        };
+
+        if !check("window.width", self.window.width > 0, "> 0") {
+            self.window.width = default.window.width;
+        }
+        if !check("window.height", self.window.height > 0, "> 0") {
+            self.window.height = default.window.height;
+        }
+        if !check(
+            "viewer.cache_extent",
+            self.viewer.cache_extent <= MAX_CACHE_EXTENT,
+            &format!("<= {}", MAX_CACHE_EXTENT),
+        ) {
+            self.viewer.cache_extent = default.viewer.cache_extent;
+        }
+        if let Some([columns, rows]) = self.viewer.grid {
+            if !check(
+                "viewer.grid",
+                columns > 0 && rows > 0,
+                "[columns, rows] > 0",
+            ) {
+                self.viewer.grid = None;
+            }
+        }
+        if !check(
+            "viewer.advance_sound_volume",
+            self.viewer.advance_sound_volume >= 0.0,
+            ">= 0",
+        ) {
+            self.viewer.advance_sound_volume = default.viewer.advance_sound_volume;
+        }
+        if !check(
+            "viewer.ken_burns_intensity",
+            (0.0..=1.0).contains(&self.viewer.ken_burns_intensity),
+            "0.0 to 1.0",
+        ) {
+            self.viewer.ken_burns_intensity = default.viewer.ken_burns_intensity;
+        }
+        if let Some(name) = &self.window.gpu_backend {
+            if !check(
+                "window.gpu_backend",
+                GPU_BACKEND_NAMES
+                    .iter()
+                    .any(|v| v.eq_ignore_ascii_case(name)),
+                &format!("one of {:?}", GPU_BACKEND_NAMES),
+            ) {
+                self.window.gpu_backend = None;
+            }
+        }
+        if !check(
+            "viewer.cache_max_bytes",
+            self.viewer.cache_max_bytes != Some(0),
+            "> 0",
+        ) {
+            self.viewer.cache_max_bytes = default.viewer.cache_max_bytes;
+        }
+        if !check(
+            "viewer.disk_cache_max_mb",
+            self.viewer.disk_cache_max_mb > 0,
+            "> 0",
+        ) {
+            self.viewer.disk_cache_max_mb = default.viewer.disk_cache_max_mb;
+        }
+        if !check(
+            "viewer.control_address",
+            self.viewer.control_address.parse::<IpAddr>().is_ok(),
+            "an IP address",
+        ) {
+            self.viewer.control_address = default.viewer.control_address.clone();
+        }
+        if !check(
+            "transition.time",
+            self.transition.time >= 0.0 && self.transition.time.is_finite(),
+            ">= 0",
+        ) {
+            self.transition.time = default.transition.time;
+        }
+        if !check(
+            "transition.fps",
+            self.transition.fps > 0.0 && self.transition.fps.is_finite(),
+            "> 0",
+        ) {
+            self.transition.fps = default.transition.fps;
+        }
+        if !check(
+            "transition.min_fps",
+            self.transition.min_fps >= 0.0 && self.transition.min_fps.is_finite(),
+            ">= 0",
+        ) {
+            self.transition.min_fps = default.transition.min_fps;
+        }
+        if !check(
+            "transition.gap_ms",
+            self.transition.gap_ms <= MAX_GAP_MS,
+            &format!("<= {}", MAX_GAP_MS),
+        ) {
+            self.transition.gap_ms = default.transition.gap_ms;
+        }
+        let effect = &self.transition.effect;
+        if !check(
+            "transition.effect",
+            transition::effect_mode(effect).is_some()
+                || effect.eq_ignore_ascii_case(transition::RANDOM_EFFECT_NAME),
+            "'random' or an effect name",
+        ) {
+            self.transition.effect = default.transition.effect.clone();
+        }
+        if !check("style.font_size_osd", self.style.font_size_osd > 0.0, "> 0") {
+            self.style.font_size_osd = default.style.font_size_osd;
+        }
+        if !check(
+            "style.font_size_image_path",
+            self.style.font_size_image_path > 0.0,
+            "> 0",
+        ) {
+            self.style.font_size_image_path = default.style.font_size_image_path;
+        }
+        if !check(
+            "style.osd_duration_secs",
+            self.style.osd_duration_secs >= 0.0 && self.style.osd_duration_secs.is_finite(),
+            ">= 0",
+        ) {
+            self.style.osd_duration_secs = default.style.osd_duration_secs;
+        }
+        if !check(
+            "style.msaa_samples",
+            SUPPORTED_MSAA_SAMPLES.contains(&self.style.msaa_samples),
+            &format!("one of {:?}", SUPPORTED_MSAA_SAMPLES),
+        ) {
+            self.style.msaa_samples = default.style.msaa_samples;
+        }
+        if let Some(schedule) = &self.style.dim_schedule {
+            let valid = parse_time(&schedule.start).is_some()
+                && parse_time(&schedule.end).is_some()
+                && (0.0..=1.0).contains(&schedule.factor);
+            if !check(
+                "style.dim_schedule",
+                valid,
+                "'HH:MM' times and a factor of 0.0 to 1.0",
+            ) {
+                self.style.dim_schedule = None;
+            }
+        }
+
+        warnings
+    }
+}
+
+/// Config values from the command-line arguments, they take priority over the config file
+#[derive(Debug, Default, Clone)]
+pub struct ArgOverrides {
+    pub fullscreen: Option<bool>,
+    pub timer: Option<u32>,
+    pub shuffle: Option<bool>,
+    pub monitor_index: Option<usize>,
+    pub image_paths: Vec<String>,
+    /// Print the config errors and exit
+    pub check_config: bool,
+}
+
+impl ArgOverrides {
+    /// Parse the arguments after the program name,
+    /// e.g. `[config.sldshow] [--fullscreen] [--timer <secs>] [--shuffle] [--monitor <index>] [--check-config] [paths...]`
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Self {
+        let mut overrides = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) if arg.starts_with("--") => (name, Some(value.to_owned())),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = || inline_value.clone().or_else(|| args.next());
+
+            let result = match name {
+                "--fullscreen" => {
+                    overrides.fullscreen = Some(true);
+                    Ok(())
+                }
+                "--shuffle" => {
+                    overrides.shuffle = Some(true);
+                    Ok(())
+                }
+                "--check-config" => {
+                    overrides.check_config = true;
+                    Ok(())
+                }
+                "--timer" => parse_arg_value(name, value()).map(|v| overrides.timer = Some(v)),
+                "--monitor" => {
+                    parse_arg_value(name, value()).map(|v| overrides.monitor_index = Some(v))
+                }
+                // Handled by `get_arg_shuffle_seed`
+                "--shuffle-seed" => {
+                    value();
+                    Ok(())
+                }
+                _ if name.starts_with("--") => Err(anyhow!("unknown argument '{}'", name)),
+                _ => {
+                    let path = Path::new(&arg);
+                    let is_conf_file = path.extension().map_or(false, |v| v == CONF_FILE_EXTENSION)
+                        && path.is_file();
+                    if !is_conf_file {
+                        overrides.image_paths.push(absolute_path_string(&arg));
+                    }
+                    Ok(())
+                }
+            };
+
+            if let Err(err) = result {
+                log::error!("{}", err);
+            }
+        }
+
+        overrides
+    }
+
+    /// Apply the arguments on top of the config
+    pub fn apply(&self, conf: &mut Config) {
+        if let Some(fullscreen) = self.fullscreen {
+            conf.window.fullscreen = fullscreen;
+        }
+        if let Some(timer) = self.timer {
+            conf.viewer.timer = timer;
+        }
+        if let Some(shuffle) = self.shuffle {
+            conf.viewer.shuffle = shuffle;
+        }
+        if let Some(monitor_index) = self.monitor_index {
+            conf.window.monitor_index = monitor_index;
+        }
+        if !self.image_paths.is_empty() {
+            conf.viewer.image_paths = self.image_paths.clone();
+        }
+    }
+}
+
+fn parse_arg_value<T: FromStr>(name: &str, value: Option<String>) -> Result<T>
+where
+    T::Err: std::fmt::Display,
+{
+    let value = value.ok_or_else(|| anyhow!("missing value for '{}'", name))?;
+    value
+        .parse()
+        .map_err(|err| anyhow!("invalid value '{}' for '{}': {}", value, name, err))
+}
+
+/// Resolve the relative path against the current directory, the working directory
+/// changes to the config file location later
+fn absolute_path_string(arg: &str) -> String {
+    let path = Path::new(arg);
+    if path.is_relative() && !arg.contains("://") {
+        if let Ok(current_dir) = std::env::current_dir() {
+            return current_dir.join(path).to_string_lossy().into_owned();
+        }
+    }
+    arg.to_owned()
+}
+
 pub fn get_config(path: &Path) -> Result<Config> {
-    let config_data = &fs::read_to_string(path)?;
-    let config: Config = toml::from_str(config_data)?;
+    let config_data =
+        &fs::read_to_string(path).map_err(|err| anyhow!("{}: {}", path.display(), err))?;
+    // The TOML error includes the line and column
+    let config: Config =
+        toml::from_str(config_data).map_err(|err| anyhow!("{}: {}", path.display(), err))?;
 
     Ok(config)
 }
@@ -0,0 +1,118 @@
+use crate::image_loader::{is_remote_path, DecodeOptions};
+use crate::multi_image;
+use anyhow::Result;
+use filetime::FileTime;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::UNIX_EPOCH;
+
+const ENTRY_EXTENSION: &str = "png";
+
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Resized images kept on disk across launches, keyed by the source path, its modified time
+/// and the decode options, the least recently used entries are removed over the size cap
+pub struct DiskCache {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+impl DiskCache {
+    pub fn new(dir: PathBuf, max_bytes: u64) -> Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir, max_bytes })
+    }
+
+    /// Get the default cache folder ('<cache dir>/sldshow/images')
+    pub fn default_dir() -> Option<PathBuf> {
+        Some(dirs::cache_dir()?.join("sldshow").join("images"))
+    }
+
+    /// Read the cached image, None if it's not cached or the source file was modified
+    pub fn load(&self, path: &Path, options: &DecodeOptions) -> Option<image::RgbaImage> {
+        let entry_path = self.entry_path(path, options)?;
+        let image = image::open(&entry_path).ok()?.to_rgba8();
+        // The modified time of the entry is its last use for the eviction
+        filetime::set_file_mtime(&entry_path, FileTime::now()).ok();
+        Some(image)
+    }
+
+    /// Write the resized image, then remove the old entries over the size cap
+    pub fn store(
+        &self,
+        path: &Path,
+        options: &DecodeOptions,
+        image: &image::RgbaImage,
+    ) -> Result<()> {
+        let entry_path = match self.entry_path(path, options) {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+
+        // Rename after writing, the other decode threads never read a partial file
+        let temp_id = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let temp_path = entry_path.with_extension(format!(
+            "{}.{}-{}.tmp",
+            ENTRY_EXTENSION,
+            std::process::id(),
+            temp_id
+        ));
+        image.save_with_format(&temp_path, image::ImageFormat::Png)?;
+        fs::rename(&temp_path, &entry_path)?;
+
+        self.evict()
+    }
+
+    /// Remove the least recently used entries until the total size fits the cap
+    fn evict(&self) -> Result<()> {
+        let mut entries = Vec::new();
+        let mut total_bytes = 0;
+        for entry in fs::read_dir(&self.dir)?.flatten() {
+            let path = entry.path();
+            if path.extension().map_or(true, |v| v != ENTRY_EXTENSION) {
+                continue;
+            }
+            if let Ok(metadata) = entry.metadata() {
+                total_bytes += metadata.len();
+                let used = metadata.modified().unwrap_or(UNIX_EPOCH);
+                entries.push((used, metadata.len(), path));
+            }
+        }
+
+        entries.sort_by_key(|(used, _, _)| *used);
+        for (_, size, path) in entries {
+            if total_bytes <= self.max_bytes {
+                break;
+            }
+            // Another thread may have removed it already
+            if fs::remove_file(&path).is_ok() {
+                total_bytes = total_bytes.saturating_sub(size);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Entry file of the image, None for the remote images and the missing files
+    fn entry_path(&self, path: &Path, options: &DecodeOptions) -> Option<PathBuf> {
+        if is_remote_path(path) {
+            return None;
+        }
+        let metadata = fs::metadata(multi_image::file_path(path)).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+
+        let mut hasher = DefaultHasher::new();
+        path.hash(&mut hasher);
+        modified.hash(&mut hasher);
+        metadata.len().hash(&mut hasher);
+        // The filter type has no Hash, the debug string covers all options
+        format!("{:?}", options).hash(&mut hasher);
+        Some(
+            self.dir
+                .join(format!("{:016x}.{}", hasher.finish(), ENTRY_EXTENSION)),
+        )
+    }
+}
@@ -0,0 +1,78 @@
+use crate::utils::unique_path;
+use anyhow::{anyhow, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const FAVORITES_FILE_EXTENSION: &str = "favorites";
+
+/// Marked image paths of all the folder sets, one path per line in the file
+#[derive(Debug, Clone)]
+pub struct Favorites {
+    file_path: PathBuf,
+    pub paths: HashSet<PathBuf>,
+}
+
+impl Favorites {
+    pub fn load(file_path: PathBuf) -> Self {
+        let paths = match fs::read_to_string(&file_path) {
+            Ok(text) => text
+                .lines()
+                .filter(|v| !v.is_empty())
+                .map(PathBuf::from)
+                .collect(),
+            Err(_) => HashSet::new(),
+        };
+
+        Self { file_path, paths }
+    }
+
+    /// Replace the marks of the scanned paths, the marks of the other folders are kept
+    pub fn update(&mut self, scanned_paths: &[PathBuf], favorites: Vec<PathBuf>) {
+        for path in scanned_paths {
+            self.paths.remove(path);
+        }
+        self.paths.extend(favorites);
+    }
+
+    /// Write the sorted paths, nothing is written until the first mark
+    pub fn save(&self) -> Result<()> {
+        if self.paths.is_empty() && !self.file_path.exists() {
+            return Ok(());
+        }
+
+        let mut lines: Vec<_> = self.paths.iter().map(|p| p.to_string_lossy()).collect();
+        lines.sort();
+        fs::write(&self.file_path, lines.join("\n"))?;
+
+        Ok(())
+    }
+}
+
+/// Copy the files into the directory, a number is appended to the existing names.
+/// Return the number of the copied files
+pub fn copy_to_dir(paths: &[PathBuf], dir: &Path) -> Result<usize> {
+    if paths.is_empty() {
+        return Err(anyhow!("no favorites to copy"));
+    }
+    fs::create_dir_all(dir)?;
+
+    let mut count = 0;
+    for path in paths {
+        let stem = path
+            .file_stem()
+            .map(|v| v.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let ext = path
+            .extension()
+            .map(|v| v.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let dest = unique_path(dir, &stem, &ext);
+        match fs::copy(path, &dest) {
+            Ok(_) => count += 1,
+            Err(err) => log::error!("{}: {}", path.display(), err),
+        }
+    }
+
+    Ok(count)
+}
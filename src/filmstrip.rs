@@ -0,0 +1,303 @@
+use crate::image_loader::THUMBNAIL_SIZE;
+use crate::texture;
+use anyhow::Result;
+use wgpu::util::DeviceExt;
+
+/// Most thumbnails in the strip, odd to center the current image
+pub const MAX_SLOTS: usize = 31;
+const ATLAS_COLUMNS: u32 = 8;
+const ATLAS_ROWS: u32 = 4;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct FilmstripVertex {
+    position: [f32; 2],
+    tex_coords: [f32; 2],
+    opacity: f32,
+}
+
+impl FilmstripVertex {
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<FilmstripVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32,
+                },
+            ],
+        }
+    }
+}
+
+/// Draws the thumbnails of the filmstrip as textured quads,
+/// the thumbnails are kept in the slots of an atlas texture
+pub struct FilmstripRenderer {
+    render_pipeline: wgpu::RenderPipeline,
+    atlas: texture::Texture,
+    bind_group: wgpu::BindGroup,
+    /// Image index and thumbnail size of each slot, None for the empty slots
+    slots: [Option<(usize, (u32, u32))>; MAX_SLOTS],
+}
+
+impl FilmstripRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        render_format: wgpu::TextureFormat,
+    ) -> Result<Self> {
+        let atlas = texture::Texture::from_image(
+            device,
+            queue,
+            &image::RgbaImage::new(THUMBNAIL_SIZE * ATLAS_COLUMNS, THUMBNAIL_SIZE * ATLAS_ROWS),
+            Some("Filmstrip Atlas"),
+        )?;
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler {
+                        comparison: false,
+                        filtering: true,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("Filmstrip Bind Group Layout"),
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&atlas.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&atlas.sampler),
+                },
+            ],
+            label: Some("Filmstrip Bind Group"),
+        });
+
+        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some("Filmstrip Shader"),
+            flags: wgpu::ShaderFlags::all(),
+            source: wgpu::ShaderSource::Wgsl(include_str!("filmstrip.wgsl").into()),
+        });
+
+        let render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Filmstrip Render Pipeline Layout"),
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Filmstrip Render Pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "main",
+                buffers: &[FilmstripVertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "main",
+                targets: &[wgpu::ColorTargetState {
+                    format: render_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrite::ALL,
+                }],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                clamp_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+        });
+
+        Ok(Self {
+            render_pipeline,
+            atlas,
+            bind_group,
+            slots: [None; MAX_SLOTS],
+        })
+    }
+
+    /// Write the thumbnail of the image into the slot, skipped if the slot already has it
+    pub fn write_slot(
+        &mut self,
+        queue: &wgpu::Queue,
+        slot: usize,
+        index: Option<usize>,
+        thumbnail: Option<&image::RgbaImage>,
+    ) {
+        if slot >= MAX_SLOTS {
+            return;
+        }
+        let (index, thumbnail) = match (index, thumbnail) {
+            (Some(index), Some(thumbnail))
+                if thumbnail.width() <= THUMBNAIL_SIZE && thumbnail.height() <= THUMBNAIL_SIZE =>
+            {
+                (index, thumbnail)
+            }
+            _ => {
+                self.slots[slot] = None;
+                return;
+            }
+        };
+        let dimensions = thumbnail.dimensions();
+        if self.slots[slot] == Some((index, dimensions)) {
+            return;
+        }
+
+        let slot_index = slot as u32;
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.atlas.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: (slot_index % ATLAS_COLUMNS) * THUMBNAIL_SIZE,
+                    y: (slot_index / ATLAS_COLUMNS) * THUMBNAIL_SIZE,
+                    z: 0,
+                },
+            },
+            thumbnail,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(4 * dimensions.0),
+                rows_per_image: std::num::NonZeroU32::new(dimensions.1),
+            },
+            wgpu::Extent3d {
+                width: dimensions.0,
+                height: dimensions.1,
+                depth_or_array_layers: 1,
+            },
+        );
+        self.slots[slot] = Some((index, dimensions));
+    }
+
+    /// Draw the thumbnails fitted in the square cells `(slot, x, y, size)` in window pixels
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        target_width: u32,
+        target_height: u32,
+        cells: &[(usize, f32, f32, f32)],
+        opacity: f32,
+    ) {
+        // Window pixels to the normalized device coordinates
+        let to_ndc = |x: f32, y: f32| {
+            [
+                (x / target_width as f32) * 2.0 - 1.0,
+                1.0 - (y / target_height as f32) * 2.0,
+            ]
+        };
+        let atlas_width = (THUMBNAIL_SIZE * ATLAS_COLUMNS) as f32;
+        let atlas_height = (THUMBNAIL_SIZE * ATLAS_ROWS) as f32;
+
+        let mut vertices: Vec<FilmstripVertex> = Vec::with_capacity(cells.len() * 6);
+        for &(slot, x, y, size) in cells {
+            let (width, height) = match self.slots.get(slot).copied().flatten() {
+                Some((_, dimensions)) => dimensions,
+                None => continue,
+            };
+            let scale = size / width.max(height).max(1) as f32;
+            let (quad_width, quad_height) = (width as f32 * scale, height as f32 * scale);
+            let quad_x = x + (size - quad_width) / 2.0;
+            let quad_y = y + (size - quad_height) / 2.0;
+            let left_top = to_ndc(quad_x, quad_y);
+            let right_bottom = to_ndc(quad_x + quad_width, quad_y + quad_height);
+
+            let slot_index = slot as u32;
+            let u0 = ((slot_index % ATLAS_COLUMNS) * THUMBNAIL_SIZE) as f32 / atlas_width;
+            let v0 = ((slot_index / ATLAS_COLUMNS) * THUMBNAIL_SIZE) as f32 / atlas_height;
+            let u1 = u0 + width as f32 / atlas_width;
+            let v1 = v0 + height as f32 / atlas_height;
+
+            let corners = [
+                ([left_top[0], left_top[1]], [u0, v0]),
+                ([left_top[0], right_bottom[1]], [u0, v1]),
+                ([right_bottom[0], left_top[1]], [u1, v0]),
+                ([right_bottom[0], left_top[1]], [u1, v0]),
+                ([left_top[0], right_bottom[1]], [u0, v1]),
+                ([right_bottom[0], right_bottom[1]], [u1, v1]),
+            ];
+            vertices.extend(
+                corners
+                    .iter()
+                    .map(|(position, tex_coords)| FilmstripVertex {
+                        position: *position,
+                        tex_coords: *tex_coords,
+                        opacity,
+                    }),
+            );
+        }
+        if vertices.is_empty() {
+            return;
+        }
+
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Filmstrip Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsage::VERTEX,
+        });
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Filmstrip Render Pass"),
+            color_attachments: &[wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.draw(0..vertices.len() as u32, 0..1);
+    }
+}
@@ -0,0 +1,125 @@
+use crate::keybindings::Action;
+use crate::logger::ResultLogging;
+use crate::CustomEvent;
+use gilrs::{Axis, Button, EventType, Gilrs};
+use std::time::{Duration, Instant};
+use winit::event_loop::EventLoopProxy;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(16);
+const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+const REPEAT_DELAY: Duration = Duration::from_millis(400);
+const REPEAT_INTERVAL: Duration = Duration::from_millis(150);
+const STICK_DEADZONE: f32 = 0.5;
+
+/// Limit the navigation rate while a direction is held
+struct RepeatLimiter {
+    action: Option<Action>,
+    next_time: Instant,
+}
+
+impl RepeatLimiter {
+    /// Return true when the held action should run now
+    fn ready(&mut self, action: Option<Action>) -> bool {
+        let now = Instant::now();
+        if action != self.action {
+            self.action = action;
+            self.next_time = now + REPEAT_DELAY;
+            return action.is_some();
+        }
+        if action.is_some() && now >= self.next_time {
+            self.next_time = now + REPEAT_INTERVAL;
+            return true;
+        }
+        false
+    }
+}
+
+/// Poll the gamepads and send the actions to the event loop,
+/// nothing is sent while no gamepad is connected
+pub fn spawn_gamepad_thread(proxy: EventLoopProxy<CustomEvent>) {
+    std::thread::spawn(move || {
+        let mut gilrs = match Gilrs::new() {
+            Ok(v) => v,
+            Err(err) => {
+                log::warn!("gamepad not available: {}", err);
+                return;
+            }
+        };
+        let mut limiter = RepeatLimiter {
+            action: None,
+            next_time: Instant::now(),
+        };
+
+        loop {
+            while let Some(event) = gilrs.next_event() {
+                match event.event {
+                    EventType::ButtonPressed(button, _) => {
+                        if let Some(action) = button_action(button) {
+                            proxy.send_event(CustomEvent::Action(action)).log_err();
+                        }
+                    }
+                    EventType::Connected => {
+                        log::info!("gamepad connected: {}", gilrs.gamepad(event.id).name())
+                    }
+                    EventType::Disconnected => log::info!("gamepad disconnected"),
+                    _ => {}
+                }
+            }
+
+            if gilrs.gamepads().next().is_none() {
+                limiter.ready(None);
+                std::thread::sleep(IDLE_POLL_INTERVAL);
+                continue;
+            }
+
+            let action = held_nav_action(&gilrs);
+            if limiter.ready(action) {
+                if let Some(action) = action {
+                    proxy.send_event(CustomEvent::Action(action)).log_err();
+                }
+            }
+
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    });
+}
+
+/// Actions of the buttons that run once per press
+fn button_action(button: Button) -> Option<Action> {
+    match button {
+        Button::South => Some(Action::TogglePause),
+        Button::Start => Some(Action::ToggleFullscreen),
+        _ => None,
+    }
+}
+
+/// Navigation of the D-pad, the bumpers or the left stick held on any gamepad
+fn held_nav_action(gilrs: &Gilrs) -> Option<Action> {
+    for (_, gamepad) in gilrs.gamepads() {
+        if gamepad.is_pressed(Button::RightTrigger) {
+            return Some(Action::Next10);
+        }
+        if gamepad.is_pressed(Button::LeftTrigger) {
+            return Some(Action::Prev10);
+        }
+
+        // Some drivers report the D-pad as axes
+        let x = gamepad.value(Axis::DPadX) + gamepad.value(Axis::LeftStickX);
+        let y = gamepad.value(Axis::DPadY) + gamepad.value(Axis::LeftStickY);
+        if gamepad.is_pressed(Button::DPadRight)
+            || gamepad.is_pressed(Button::DPadDown)
+            || x > STICK_DEADZONE
+            || y < -STICK_DEADZONE
+        {
+            return Some(Action::Next);
+        }
+        if gamepad.is_pressed(Button::DPadLeft)
+            || gamepad.is_pressed(Button::DPadUp)
+            || x < -STICK_DEADZONE
+            || y > STICK_DEADZONE
+        {
+            return Some(Action::Prev);
+        }
+    }
+    None
+}
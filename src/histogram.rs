@@ -0,0 +1,76 @@
+use crate::overlay::Rect;
+
+const BIN_COUNT: usize = 256;
+const BAR_COLORS: [[f32; 4]; 4] = [
+    [1.0, 0.2, 0.2, 0.5],
+    [0.2, 1.0, 0.2, 0.5],
+    [0.3, 0.4, 1.0, 0.5],
+    [1.0, 1.0, 1.0, 0.3],
+];
+const BACKGROUND_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+
+/// Red, green, blue and luma histogram of an image
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    bins: [[u32; BIN_COUNT]; 4],
+    max: u32,
+    /// Computed from a resized image instead of the original pixels
+    pub approximate: bool,
+}
+
+impl Histogram {
+    /// Count the pixels, fully transparent pixels are ignored
+    pub fn new(image: &image::RgbaImage, approximate: bool) -> Self {
+        let mut bins = [[0; BIN_COUNT]; 4];
+        for pixel in image.pixels() {
+            let [r, g, b, a] = pixel.0;
+            if a == 0 {
+                continue;
+            }
+            let luma = (0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32).round() as usize;
+            bins[0][r as usize] += 1;
+            bins[1][g as usize] += 1;
+            bins[2][b as usize] += 1;
+            bins[3][luma.min(BIN_COUNT - 1)] += 1;
+        }
+        let max = bins.iter().flatten().copied().max().unwrap_or(0);
+
+        Self {
+            bins,
+            max,
+            approximate,
+        }
+    }
+
+    /// Bars of all channels in the given area of the window
+    pub fn rects(&self, x: f32, y: f32, width: f32, height: f32) -> Vec<Rect> {
+        let mut rects = vec![Rect {
+            x,
+            y,
+            width,
+            height,
+            color: BACKGROUND_COLOR,
+        }];
+        if self.max == 0 {
+            return rects;
+        }
+
+        let bar_width = width / BIN_COUNT as f32;
+        for (bins, color) in self.bins.iter().zip(BAR_COLORS.iter()) {
+            for (i, &count) in bins.iter().enumerate() {
+                if count == 0 {
+                    continue;
+                }
+                let bar_height = height * count as f32 / self.max as f32;
+                rects.push(Rect {
+                    x: x + bar_width * i as f32,
+                    y: y + height - bar_height,
+                    width: bar_width,
+                    height: bar_height,
+                    color: *color,
+                });
+            }
+        }
+        rects
+    }
+}
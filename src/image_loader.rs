@@ -1,24 +1,369 @@
+use crate::color_profile;
+use crate::config::{FitMode, PreloadBias, SortOrder};
+use crate::disk_cache::DiskCache;
+use crate::gpu_resize::GpuResizer;
+use crate::histogram::Histogram;
+use crate::logger::ResultLogging;
+use crate::multi_image;
+use crate::transition;
 use crate::utils::modulo;
-use crate::SUPPORTED_IMAGE_FORMATS;
+use crate::{CustomEvent, SUPPORTED_IMAGE_FORMATS};
 use anyhow::{anyhow, Result};
+use chrono::{FixedOffset, Local, NaiveDate, TimeZone};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use image::{AnimationDecoder, GenericImageView};
 use rand::prelude::*;
-use std::collections::{HashMap, VecDeque};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::ffi::OsString;
 use std::fs;
+use std::io::{BufRead, BufReader, Cursor, Read, Seek};
 use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 use stopwatch::Stopwatch;
 use winit::dpi::PhysicalSize;
+use winit::event_loop::EventLoopProxy;
 
 const MAX_DEPTH_SCAN: usize = 999;
+const MAX_DEPTH_PLAYLIST: usize = 8;
+const PLAYLIST_EXTENSIONS: [&str; 3] = ["txt", "m3u", "sldlist"];
+pub const THUMBNAIL_SIZE: u32 = 160;
+const ERROR_PLACEHOLDER_BORDER: u32 = 6;
+const ERROR_PLACEHOLDER_BG: image::Rgba<u8> = image::Rgba([64, 16, 16, 255]);
+const ERROR_PLACEHOLDER_FG: image::Rgba<u8> = image::Rgba([224, 64, 64, 255]);
+/// Budget of the animation frames of all cached images
+const MAX_ANIMATION_BYTES: usize = 256 * 1024 * 1024;
+const MIN_FRAME_DELAY: Duration = Duration::from_millis(20);
+const DEFAULT_FRAME_DELAY: Duration = Duration::from_millis(100);
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+const GIF_LOOP_SEARCH_BYTES: u64 = 4096;
+const MAX_CAPTURE_TIME_THREADS: usize = 4;
+const SCAN_FLUSH_INTERVAL: Duration = Duration::from_millis(100);
+#[cfg(feature = "remote")]
+const MAX_DOWNLOAD_BYTES: u64 = 256 * 1024 * 1024;
+/// A stalled server fails the download after these, shown as the load error
+#[cfg(feature = "remote")]
+const DOWNLOAD_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+#[cfg(feature = "remote")]
+const DOWNLOAD_READ_TIMEOUT: Duration = Duration::from_secs(10);
+#[cfg(feature = "remote")]
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Return true if the path is a HTTP/HTTPS URL
+pub fn is_remote_path(path: &Path) -> bool {
+    match path.to_str() {
+        Some(s) => s.starts_with("http://") || s.starts_with("https://"),
+        None => false,
+    }
+}
+
+/// Read the shooting information from the EXIF data, local files only
+pub fn exif_info(path: &Path) -> Option<ExifInfo> {
+    if is_remote_path(path) {
+        return None;
+    }
+
+    let file = fs::File::open(path).ok()?;
+    let exif = exif::Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .ok()?;
+    let get = |tag: exif::Tag| {
+        exif.get_field(tag, exif::In::PRIMARY).map(|field| {
+            field
+                .display_value()
+                .with_unit(&exif)
+                .to_string()
+                .trim_matches('"')
+                .to_owned()
+        })
+    };
+
+    let info = ExifInfo {
+        date_time: get(exif::Tag::DateTimeOriginal),
+        camera_model: get(exif::Tag::Model),
+        lens: get(exif::Tag::LensModel),
+        focal_length: get(exif::Tag::FocalLength),
+        aperture: get(exif::Tag::FNumber),
+        shutter: get(exif::Tag::ExposureTime),
+        iso: get(exif::Tag::PhotographicSensitivity),
+    };
+    if info == ExifInfo::default() {
+        None
+    } else {
+        Some(info)
+    }
+}
+
+/// Read the caption from a sidecar text file ('<path>.txt' or '<stem>.caption')
+pub fn read_caption(path: &Path) -> Option<String> {
+    if is_remote_path(path) {
+        return None;
+    }
+
+    let mut txt_path = path.as_os_str().to_owned();
+    txt_path.push(".txt");
+    let candidates = [PathBuf::from(txt_path), path.with_extension("caption")];
+    candidates.iter().find_map(|p| {
+        let text = fs::read_to_string(p).ok()?;
+        let text = text.trim();
+        if text.is_empty() {
+            None
+        } else {
+            Some(text.replace("\r\n", "\n"))
+        }
+    })
+}
+
+/// Read the EXIF DateTimeOriginal, the time without the offset is taken as local time
+pub fn capture_time(path: &Path) -> Option<SystemTime> {
+    if is_remote_path(path) {
+        return None;
+    }
+
+    let file = fs::File::open(path).ok()?;
+    let exif = exif::Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .ok()?;
+    let field = exif.get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY)?;
+    let dt = match &field.value {
+        exif::Value::Ascii(values) => exif::DateTime::from_ascii(values.first()?).ok()?,
+        _ => return None,
+    };
+
+    let naive = NaiveDate::from_ymd_opt(dt.year as i32, dt.month as u32, dt.day as u32)?
+        .and_hms_opt(dt.hour as u32, dt.minute as u32, dt.second as u32)?;
+    let secs = match dt.offset {
+        Some(minutes) => FixedOffset::east_opt(minutes as i32 * 60)?
+            .from_local_datetime(&naive)
+            .single()?
+            .timestamp(),
+        None => Local.from_local_datetime(&naive).earliest()?.timestamp(),
+    };
+    if secs < 0 {
+        return None;
+    }
+    Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs as u64))
+}
+
+/// Read the capture times in a bounded number of threads,
+/// the progress is called with the number of the files read and the total
+pub fn read_capture_times<F: FnMut(usize, usize)>(
+    paths: Vec<PathBuf>,
+    mut progress: F,
+) -> HashMap<PathBuf, Option<SystemTime>> {
+    let total = paths.len();
+    let thread_count = std::thread::available_parallelism()
+        .map_or(1, |v| v.get())
+        .clamp(1, MAX_CAPTURE_TIME_THREADS);
+    let queue = Arc::new(Mutex::new(paths.into_iter()));
+    let (tx, rx) = mpsc::channel();
+    for _ in 0..thread_count {
+        let queue = queue.clone();
+        let tx = tx.clone();
+        std::thread::spawn(move || loop {
+            let path = match queue.lock().unwrap().next() {
+                Some(v) => v,
+                None => return,
+            };
+            let time = capture_time(&path);
+            if tx.send((path, time)).is_err() {
+                return;
+            }
+        });
+    }
+    drop(tx);
+
+    let mut times = HashMap::with_capacity(total);
+    for (path, time) in rx {
+        times.insert(path, time);
+        progress(times.len(), total);
+    }
+    times
+}
+
+/// Compare the sort keys, the paths without the key go last
+fn compare_sort_keys<K: Ord>(a: &Option<K>, b: &Option<K>, descending: bool) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) if descending => b.cmp(a),
+        (Some(a), Some(b)) => a.cmp(b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Scan the input paths in another thread and report `ScanProgress` and `ScanFinished`.
+/// In name order without shuffle the found paths are appended while scanning
+/// so the first image is shown early, other orders need the complete list
+pub fn spawn_scan(
+    image_loader: Arc<Mutex<ImageLoader>>,
+    input_paths: Vec<PathBuf>,
+    shuffle_seed: Option<u64>,
+    proxy: EventLoopProxy<CustomEvent>,
+) {
+    let (scan_id, incremental, mut scanner) = {
+        let mut loader = image_loader.lock().unwrap();
+        loader.scan_id += 1;
+        loader.scanning = true;
+        let incremental = loader.sort == SortOrder::Name && shuffle_seed.is_none();
+        (loader.scan_id, incremental, loader.scanner())
+    };
+
+    let (tx, rx) = mpsc::channel();
+    scanner.found_tx = Some(tx);
+    let scan_thread = std::thread::spawn(move || {
+        scanner.scan_input_paths(&input_paths);
+        // Close the channel before the collector joins
+        scanner.found_tx = None;
+        scanner
+    });
+
+    std::thread::spawn(move || {
+        let mut pending = Vec::new();
+        let mut found = 0;
+        let mut reported = 0;
+        let mut last_flush = Instant::now();
+        loop {
+            match rx.recv_timeout(SCAN_FLUSH_INTERVAL) {
+                Ok(path) => {
+                    found += 1;
+                    if incremental {
+                        pending.push(path);
+                    }
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+            // Flush the first path at once to show it early
+            if found == reported || (reported > 0 && last_flush.elapsed() < SCAN_FLUSH_INTERVAL) {
+                continue;
+            }
+            last_flush = Instant::now();
+
+            let mut loader = image_loader.lock().unwrap();
+            if loader.scan_id != scan_id {
+                return;
+            }
+            loader.scanned_paths.append(&mut pending);
+            drop(loader);
+
+            reported = found;
+            proxy.send_event(CustomEvent::ScanProgress(found)).log_err();
+        }
+
+        let scanner = scan_thread.join();
+        let mut loader = image_loader.lock().unwrap();
+        if loader.scan_id != scan_id {
+            return;
+        }
+        match scanner {
+            Ok(scanner) => {
+                // Same order as the appended paths in the incremental mode
+                loader.scanned_paths = scanner.scanned_paths;
+                loader.scanned_dirs = scanner.scanned_dirs;
+                loader.durations = scanner.durations;
+                loader.transitions = scanner.transitions;
+                if let Some(seed) = shuffle_seed {
+                    loader.shuffle_paths(seed);
+                }
+            }
+            Err(_) => log::error!("failed to scan the input paths"),
+        }
+        loader.scanning = false;
+        drop(loader);
+
+        proxy.send_event(CustomEvent::ScanFinished).log_err();
+    });
+}
 
 #[derive(Debug, Clone)]
 pub struct ImageCache {
     pub path: Option<PathBuf>,
     pub image: image::RgbaImage,
+    pub animation: Option<Arc<AnimatedImage>>,
+    pub dimensions: Option<(u32, u32)>,
+    pub file_size: Option<u64>,
+    /// Modified time of the file when it was decoded
+    pub modified: Option<SystemTime>,
+    pub exif: Option<ExifInfo>,
     pub emsg: Option<String>,
+    pub caption: Option<String>,
+    /// Computed when the image is first shown with the histogram enabled
+    pub histogram: Option<Arc<Histogram>>,
+}
+
+impl ImageCache {
+    /// RGBA byte size of the image and the animation frames
+    pub fn byte_size(&self) -> usize {
+        self.image.as_raw().len() + self.animation_bytes()
+    }
+
+    /// RGBA byte size of the animation frames
+    pub fn animation_bytes(&self) -> usize {
+        self.animation.as_ref().map_or(0, |animation| {
+            animation
+                .frames
+                .iter()
+                .map(|frame| frame.as_raw().len())
+                .sum()
+        })
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Shooting information of the image
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExifInfo {
+    pub date_time: Option<String>,
+    pub camera_model: Option<String>,
+    pub lens: Option<String>,
+    pub focal_length: Option<String>,
+    pub aperture: Option<String>,
+    pub shutter: Option<String>,
+    pub iso: Option<String>,
+}
+
+impl ExifInfo {
+    /// Labeled lines of the available fields
+    pub fn lines(&self) -> Vec<String> {
+        [
+            ("Date", &self.date_time),
+            ("Camera", &self.camera_model),
+            ("Lens", &self.lens),
+            ("Focal length", &self.focal_length),
+            ("Aperture", &self.aperture),
+            ("Shutter", &self.shutter),
+            ("ISO", &self.iso),
+        ]
+        .iter()
+        .filter_map(|(label, value)| value.as_ref().map(|v| format!("{}: {}", label, v)))
+        .collect()
+    }
+}
+
+/// Decoded frames of an animated image
+#[derive(Debug, Clone)]
+pub struct AnimatedImage {
+    pub frames: Vec<image::RgbaImage>,
+    pub delays: Vec<Duration>,
+    /// Number of times to play the animation, 0 is infinite
+    pub play_count: u32,
+}
+
+/// Parameters used to decode and resize an image
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecodeOptions {
+    pub size: Size2d<u32>,
+    pub filter_type: image::imageops::FilterType,
+    pub fit_mode: FitMode,
+    pub no_upscale: bool,
+    pub downscale_decode: bool,
+    pub color_management: bool,
+    pub exif_orientation: bool,
+    pub rotation: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Size2d<T> {
     pub width: T,
     pub height: T,
@@ -47,22 +392,97 @@ impl From<Size2d<u32>> for PhysicalSize<u32> {
 pub struct ImageLoader {
     pub cache: HashMap<usize, ImageCache>,
     pub preload_queue: VecDeque<usize>,
+    /// Indices being decoded by the decode workers
+    pub loading: HashSet<usize>,
     pub scanned_paths: Vec<PathBuf>,
+    pub scanned_dirs: Vec<PathBuf>,
     pub scan_subfolders: bool,
+    /// Subfolder levels to scan, 0 is the folder's own files
+    pub max_depth: Option<usize>,
+    /// Scan the symbolic links to files and folders
+    pub follow_symlinks: bool,
+    /// Scan each page of the multi-page TIFF files as an image
+    pub tiff_pages: bool,
+    pub sort: SortOrder,
+    /// Compare the numbers in the file names by value, e.g. "img2" < "img10"
+    pub natural_sort: bool,
+    pub case_insensitive_sort: bool,
+    /// Go back to the first image after the last image
+    pub wrap: bool,
+    /// Shuffle the order again when moving past the last image
+    pub reshuffle_each_loop: bool,
+    /// The list is shuffled, the new files are appended
+    pub shuffled: bool,
     pub current_path: Option<PathBuf>,
     pub current_index: usize,
     pub supported_extensions: Vec<OsString>,
+    pub include: GlobSet,
+    pub exclude: GlobSet,
     pub cache_extent: usize,
     pub max_cache_size: usize,
+    /// Images shown at once on a contact sheet page, the navigation moves by pages
+    pub page_size: usize,
+    /// Columns and rows of the contact sheet, the images are decoded at the cell size
+    sheet_grid: (u32, u32),
+    /// Total RGBA size of the cached images, no limit if None
+    pub max_cache_bytes: Option<usize>,
+    pub preload_bias: PreloadBias,
     pub texture_size: Size2d<u32>,
     pub resize_filter: image::imageops::FilterType,
+    pub fit_mode: FitMode,
+    pub no_upscale: bool,
+    pub downscale_decode: bool,
+    /// Convert the images with an embedded ICC profile to sRGB
+    pub color_management: bool,
+    /// Rotate and flip the images by the EXIF orientation tag
+    pub exif_orientation: bool,
+    pub rotations: HashMap<usize, u16>,
+    /// Indices of the images marked as favorite
+    pub favorites: HashSet<usize>,
+    pub thumbnails: HashMap<usize, image::RgbaImage>,
+    pub thumbnail_queue: VecDeque<usize>,
+    pub gpu_resizer: Option<Arc<GpuResizer>>,
+    /// Resized images kept across launches
+    pub disk_cache: Option<Arc<DiskCache>>,
+    /// Display time in seconds from the playlist
+    pub durations: HashMap<PathBuf, u32>,
+    /// Transition effect mode into the image from the playlist
+    pub transitions: HashMap<PathBuf, i32>,
+    /// Parsed capture times, None if the file has no EXIF date
+    pub capture_times: HashMap<PathBuf, Option<SystemTime>>,
+    /// The background scan is still adding paths
+    pub scanning: bool,
+    scan_id: usize,
+    /// Sends each path found by a scanner copy
+    found_tx: Option<mpsc::Sender<PathBuf>>,
+}
+
+/// Paths collected from the input paths
+#[derive(Default)]
+struct ScanResult {
+    paths: Vec<PathBuf>,
+    dirs: Vec<PathBuf>,
+    durations: HashMap<PathBuf, u32>,
+    transitions: HashMap<PathBuf, i32>,
+}
+
+/// Counts of a directory walk to tune the depth limit
+#[derive(Default)]
+struct DirWalk {
+    dirs: usize,
+    files: usize,
+    /// Canonical paths of the scanned directories to break the symlink cycles
+    visited: HashSet<PathBuf>,
 }
 
 impl ImageLoader {
     pub fn new(
         scan_subfolders: bool,
+        sort: SortOrder,
         texture_size: Size2d<u32>,
         resize_filter: image::imageops::FilterType,
+        fit_mode: FitMode,
+        no_upscale: bool,
         cache_extent: usize,
     ) -> Self {
         let supported_extensions: Vec<OsString> = SUPPORTED_IMAGE_FORMATS
@@ -74,43 +494,245 @@ impl ImageLoader {
         ImageLoader {
             cache: HashMap::new(),
             preload_queue: VecDeque::new(),
+            loading: HashSet::new(),
             scanned_paths: Vec::new(),
+            scanned_dirs: Vec::new(),
             scan_subfolders,
+            max_depth: None,
+            follow_symlinks: true,
+            tiff_pages: false,
+            sort,
+            natural_sort: true,
+            case_insensitive_sort: false,
+            wrap: true,
+            reshuffle_each_loop: false,
+            shuffled: false,
             current_path: None,
             current_index: 0,
             supported_extensions,
+            include: GlobSet::empty(),
+            exclude: GlobSet::empty(),
             cache_extent,
             max_cache_size: (cache_extent * 2) + 1,
+            page_size: 1,
+            sheet_grid: (1, 1),
+            max_cache_bytes: None,
+            preload_bias: PreloadBias::Balanced,
             texture_size,
             resize_filter,
+            fit_mode,
+            no_upscale,
+            downscale_decode: false,
+            color_management: false,
+            exif_orientation: true,
+            rotations: HashMap::new(),
+            favorites: HashSet::new(),
+            thumbnails: HashMap::new(),
+            thumbnail_queue: VecDeque::new(),
+            gpu_resizer: None,
+            disk_cache: None,
+            durations: HashMap::new(),
+            transitions: HashMap::new(),
+            capture_times: HashMap::new(),
+            scanning: false,
+            scan_id: 0,
+            found_tx: None,
+        }
+    }
+
+    /// Copy of the scan settings without the images to scan in another thread
+    fn scanner(&self) -> Self {
+        let mut scanner = Self::new(
+            self.scan_subfolders,
+            self.sort,
+            self.texture_size,
+            self.resize_filter,
+            self.fit_mode,
+            self.no_upscale,
+            0,
+        );
+        scanner.max_depth = self.max_depth;
+        scanner.follow_symlinks = self.follow_symlinks;
+        scanner.tiff_pages = self.tiff_pages;
+        scanner.natural_sort = self.natural_sort;
+        scanner.case_insensitive_sort = self.case_insensitive_sort;
+        scanner.supported_extensions = self.supported_extensions.clone();
+        scanner.include = self.include.clone();
+        scanner.exclude = self.exclude.clone();
+        scanner.capture_times = self.capture_times.clone();
+        scanner
+    }
+
+    /// Changed by each scan, the watchers of the previous list stop
+    pub fn scan_id(&self) -> usize {
+        self.scan_id
+    }
+
+    /// Stop applying the background scan, e.g. the paths were replaced by dropped files
+    pub fn cancel_scan(&mut self) {
+        self.scan_id += 1;
+        self.scanning = false;
+    }
+
+    /// Add a scanned path, the scanner copy also reports it to the background scan
+    fn push_found(&self, out: &mut Vec<PathBuf>, path: PathBuf) {
+        for path in self.expand_pages(path) {
+            if let Some(tx) = &self.found_tx {
+                tx.send(path.clone()).log_err();
+            }
+            out.push(path);
         }
     }
 
-    pub fn append_path(&mut self, path: PathBuf) {
-        let mut new_paths = {
-            let mut out: Vec<PathBuf> = vec![];
-            if path.is_dir() {
-                self.scan_recursively(&mut out, &path, 0);
-            } else if path.is_file() && self.is_supported_ext(&path) {
-                out.push(path);
+    /// Split a multi-page TIFF file into the page paths with `tiff_pages`
+    fn expand_pages(&self, path: PathBuf) -> Vec<PathBuf> {
+        let is_tiff = matches!(
+            image::ImageFormat::from_path(&path),
+            Ok(image::ImageFormat::Tiff)
+        );
+        if !self.tiff_pages || !is_tiff || is_remote_path(&path) {
+            return vec![path];
+        }
+
+        match multi_image::tiff_page_count(&path) {
+            Ok(count) if count > 1 => (0..count)
+                .map(|page| multi_image::page_path(&path, page))
+                .collect(),
+            Ok(_) => vec![path],
+            Err(err) => {
+                log::warn!("{}: {}", path.display(), err);
+                vec![path]
             }
-            out
+        }
+    }
+
+    /// Insert a new file at its position in the sort order without changing the current image,
+    /// appended to the shuffled list
+    pub fn insert_path(&mut self, path: PathBuf) -> bool {
+        if !path.is_file()
+            || !self.is_supported_ext(&path)
+            || !self.is_included(&path)
+            || self.scanned_paths.contains(&path)
+        {
+            return false;
+        }
+
+        let pos = if self.shuffled {
+            self.scanned_paths.len()
+        } else {
+            self.scanned_paths
+                .partition_point(|p| self.compare_in_sort_order(p, &path) == Ordering::Less)
         };
-        self.scanned_paths.append(&mut new_paths);
+        self.scanned_paths.insert(pos, path);
+        self.remap_indices(|i| Some(if i >= pos { i + 1 } else { i }));
+        if self.current_index >= pos && self.scanned_paths.len() > 1 {
+            self.current_index += 1;
+        }
+
+        true
     }
 
-    pub fn shuffle_paths(&mut self) {
-        self.scanned_paths.shuffle(&mut rand::thread_rng());
+    /// Remove the path, the current index points to the next image after that
+    pub fn remove_path(&mut self, index: usize) -> Option<PathBuf> {
+        if index >= self.scanned_paths.len() {
+            return None;
+        }
+
+        let path = self.scanned_paths.remove(index);
+        self.remap_indices(|i| match i.cmp(&index) {
+            Ordering::Less => Some(i),
+            Ordering::Equal => None,
+            Ordering::Greater => Some(i - 1),
+        });
+        if self.current_index > index {
+            self.current_index -= 1;
+        }
+        if self.current_index >= self.scanned_paths.len() {
+            self.current_index = 0; // the last image was removed
+        }
+
+        Some(path)
+    }
+
+    /// Move the current file to the trash and remove it from the list
+    pub fn trash_current(&mut self) -> Result<PathBuf> {
+        let index = self.current_index;
+        let path = self
+            .scanned_paths
+            .get(index)
+            .ok_or_else(|| anyhow!("no image to delete."))?;
+        if is_remote_path(path) {
+            return Err(anyhow!("cannot delete a remote image."));
+        }
+
+        trash::delete(path).map_err(|err| anyhow!("{}", err))?;
+
+        self.remove_path(index)
+            .ok_or_else(|| anyhow!("faild to remove the path."))
+    }
+
+    /// Move the index keyed data to the new indices, or drop it if `None`
+    fn remap_indices<F: Fn(usize) -> Option<usize>>(&mut self, remap: F) {
+        self.cache = self
+            .cache
+            .drain()
+            .filter_map(|(k, v)| remap(k).map(|k| (k, v)))
+            .collect();
+        self.rotations = self
+            .rotations
+            .drain()
+            .filter_map(|(k, v)| remap(k).map(|k| (k, v)))
+            .collect();
+        self.thumbnails = self
+            .thumbnails
+            .drain()
+            .filter_map(|(k, v)| remap(k).map(|k| (k, v)))
+            .collect();
+        self.favorites = self.favorites.drain().filter_map(&remap).collect();
+        self.preload_queue.clear();
+        self.thumbnail_queue.clear();
+    }
+
+    /// Shuffle the paths, the same seed gives the same order for the same scanned files
+    pub fn shuffle_paths(&mut self, seed: u64) {
+        self.scanned_paths
+            .shuffle(&mut rand::rngs::StdRng::seed_from_u64(seed));
+        self.shuffled = true;
+    }
+
+    /// Shuffle the paths again for the next loop, the current image keeps its index
+    /// so it's not shown first and the previous images stay reachable in the new order
+    pub fn reshuffle_paths(&mut self) {
+        let len = self.scanned_paths.len();
+        let current = self.current_index;
+        let positions: Vec<usize> = (0..len).filter(|&i| i != current).collect();
+        let mut sources = positions.clone();
+        sources.shuffle(&mut rand::thread_rng());
+
+        // new_indices[old index] = new index
+        let mut new_indices: Vec<usize> = (0..len).collect();
+        let mut paths = self.scanned_paths.clone();
+        for (&position, &source) in positions.iter().zip(&sources) {
+            paths[position] = self.scanned_paths[source].clone();
+            new_indices[source] = position;
+        }
+        self.scanned_paths = paths;
+        self.remap_indices(|i| new_indices.get(i).copied());
+        log::info!("reshuffled {} paths", len);
     }
 
     pub fn limit_cache(&mut self) -> Result<()> {
         let mut cache_count = self.cache.len();
-        while cache_count > self.max_cache_size {
+        let mut cache_bytes = self.cache_bytes();
+        // Keep the current image even if it exceeds the byte budget
+        while cache_count > self.max_cache_size
+            || (cache_count > 1 && self.max_cache_bytes.map_or(false, |max| cache_bytes > max))
+        {
             let max_dist = self
                 .cache
                 .keys()
                 .cloned()
-                .map(|k| self.index_distance(&self.current_index, &k))
+                .map(|k| self.eviction_distance(&k))
                 .max()
                 .ok_or_else(|| anyhow!("cannot get a max distance in cache."))?;
 
@@ -118,10 +740,12 @@ impl ImageLoader {
                 .cache
                 .keys()
                 .cloned()
-                .find(|k| self.index_distance(&self.current_index, k) == max_dist)
+                .find(|k| self.eviction_distance(k) == max_dist)
                 .ok_or_else(|| anyhow!("cannot get a key"))?;
 
-            self.cache.remove(&max_dist_key);
+            if let Some(image_cache) = self.cache.remove(&max_dist_key) {
+                cache_bytes -= image_cache.byte_size();
+            }
             //log::info!("remove_cache: key={}, dist={}", max_dist_key, max_dist);
             cache_count -= 1;
         }
@@ -130,6 +754,107 @@ impl ImageLoader {
         Ok(())
     }
 
+    /// Insert a decoded image, then evict the farthest images over the count and the byte budget
+    pub fn insert_cache(&mut self, index: usize, mut image_cache: ImageCache) -> Result<()> {
+        self.limit_animation_bytes(&index, &mut image_cache);
+        self.cache.insert(index, image_cache);
+        self.limit_cache()
+    }
+
+    /// Keep the animation frames of the cache in the budget, the farther animated images are
+    /// evicted first, then the frames of the new animation over the rest of the budget are skipped
+    fn limit_animation_bytes(&mut self, index: &usize, image_cache: &mut ImageCache) {
+        let new_bytes = image_cache.animation_bytes();
+        if new_bytes == 0 {
+            return;
+        }
+
+        let distance = self.eviction_distance(index);
+        // The replaced entry of the same index is not counted
+        let mut cached_bytes =
+            self.animation_bytes() - self.cache.get(index).map_or(0, ImageCache::animation_bytes);
+        while cached_bytes + new_bytes > MAX_ANIMATION_BYTES {
+            let farthest = self
+                .cache
+                .iter()
+                .filter(|(k, v)| v.animation.is_some() && *k != index)
+                .map(|(k, _)| *k)
+                .max_by_key(|k| self.eviction_distance(k))
+                .filter(|k| self.eviction_distance(k) > distance);
+            match farthest.and_then(|k| self.cache.remove(&k)) {
+                Some(evicted) => cached_bytes -= evicted.animation_bytes(),
+                None => break,
+            }
+        }
+
+        let available = MAX_ANIMATION_BYTES.saturating_sub(cached_bytes);
+        if new_bytes <= available {
+            return;
+        }
+        if let Some(animation) = image_cache.animation.as_mut() {
+            let animation = Arc::make_mut(animation);
+            let mut total_bytes = 0;
+            let count = animation
+                .frames
+                .iter()
+                .take_while(|frame| {
+                    total_bytes += frame.as_raw().len();
+                    total_bytes <= available
+                })
+                .count();
+            log::warn!(
+                "image[{}] animation over the cache budget, truncated at {} frames",
+                index,
+                count
+            );
+            animation.frames.truncate(count);
+            animation.delays.truncate(count);
+        }
+        // Show the first frame as a still image if the animation doesn't fit
+        if image_cache
+            .animation
+            .as_ref()
+            .map_or(false, |animation| animation.frames.len() < 2)
+        {
+            image_cache.animation = None;
+        }
+    }
+
+    /// Return false if the cache is over the byte budget and the image would be evicted first,
+    /// the preload stops there not to decode the images only to drop them
+    pub fn fits_cache_budget(&self, index: &usize) -> bool {
+        match self.max_cache_bytes {
+            Some(max) if self.cache_bytes() >= max => {
+                let distance = self.eviction_distance(index);
+                self.cache
+                    .keys()
+                    .any(|k| self.eviction_distance(k) > distance)
+            }
+            _ => true,
+        }
+    }
+
+    /// Total RGBA byte size of the cached images
+    pub fn cache_bytes(&self) -> usize {
+        self.cache.values().map(ImageCache::byte_size).sum()
+    }
+
+    /// Total RGBA byte size of the animation frames of the cached images
+    fn animation_bytes(&self) -> usize {
+        self.cache.values().map(ImageCache::animation_bytes).sum()
+    }
+
+    /// Distance from the current image weighted by the preload bias, the farthest is evicted first
+    fn eviction_distance(&self, index: &usize) -> usize {
+        let distance = self.index_distance(&self.current_index, index);
+        let len = self.scanned_paths.len().max(1);
+        let ahead = (index + len - self.current_index) % len <= len / 2;
+        match (self.preload_bias, ahead) {
+            (PreloadBias::Forward, false) | (PreloadBias::Backward, true) => distance * 2,
+            _ => distance,
+        }
+    }
+
     fn index_distance(&self, a: &usize, b: &usize) -> usize {
         if a == b {
             return 0;
@@ -147,56 +872,165 @@ impl ImageLoader {
         }
     }
 
-    fn get_next_index(&self, amount: i32) -> Option<usize> {
-        let len = self.scanned_paths.len() as i32;
-        if len <= 1 {
+    /// Show the contact sheet, the cache holds the same number of pages as the images
+    /// without it, the cells are decoded at the cell size so the memory use stays the same
+    pub fn set_contact_sheet(&mut self, columns: u32, rows: u32) {
+        self.sheet_grid = (columns.max(1), rows.max(1));
+        self.page_size = (self.sheet_grid.0 * self.sheet_grid.1) as usize;
+        self.max_cache_size = ((self.cache_extent * 2) + 1) * self.page_size;
+    }
+
+    /// Get the index moved by the amount of pages, None if it can't move without wrapping.
+    /// A page is an image unless the contact sheet is shown
+    pub fn get_next_index(&self, amount: i32) -> Option<usize> {
+        let page_size = self.page_size.max(1);
+        // The last page may be partial
+        let page_count = ((self.scanned_paths.len() + page_size - 1) / page_size) as i32;
+        if page_count <= 1 {
             return None;
         }
 
-        let mut index = self.current_index as i32 + amount;
-        if index < 0 || index >= len {
-            index = modulo(index, len);
+        let mut page = (self.current_index / page_size) as i32 + amount;
+        if page < 0 || page >= page_count {
+            // The end is not known while scanning
+            if !self.wrap || self.scanning {
+                return None;
+            }
+            page = modulo(page, page_count);
         }
 
-        Some(index as usize)
+        Some(page as usize * page_size)
     }
 
-    pub fn next_index(&mut self, amount: i32) {
-        if let Some(index) = self.get_next_index(amount) {
-            self.current_index = index;
+    /// Move the current index, returns false if it stays
+    pub fn next_index(&mut self, amount: i32) -> bool {
+        let wraps_forward = amount > 0
+            && self.current_index + amount as usize * self.page_size.max(1)
+                >= self.scanned_paths.len();
+        if self.reshuffle_each_loop
+            && wraps_forward
+            && !self.scanning
+            && self.get_next_index(amount).is_some()
+        {
+            self.reshuffle_paths();
         }
+
+        match self.get_next_index(amount) {
+            Some(index) => {
+                self.current_index = index;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_first(&self) -> bool {
+        !self.scanned_paths.is_empty() && self.current_index == 0
     }
 
     pub fn is_last(&self) -> bool {
-        self.current_index == self.scanned_paths.len() - 1
+        !self.scanned_paths.is_empty()
+            && self.current_index + self.page_size.max(1) >= self.scanned_paths.len()
     }
 
     fn ensure_cache(&mut self, index: &usize) -> Result<()> {
         if !self.cache.contains_key(index) {
-            let mut emsg = None;
-            let (image, path) = match &self.scanned_paths.get(*index) {
-                Some(path) => {
-                    match Self::open_and_resize_image(
-                        index,
-                        path,
-                        &self.texture_size,
-                        self.resize_filter,
-                    ) {
-                        Ok(image) => (image, Some((**path).clone())),
-                        Err(err) => {
-                            log::error!("{}", err);
-                            emsg = Some(err.to_string());
-                            (image::RgbaImage::new(1, 1), Some((**path).clone()))
+            let path = self.scanned_paths.get(*index).cloned();
+            let options = self.decode_options(index);
+            let image_cache = Self::load_image_cache(
+                index,
+                path,
+                &options,
+                self.gpu_resizer.as_deref(),
+                self.disk_cache.as_deref(),
+            );
+            self.insert_cache(*index, image_cache)?;
+        };
+
+        Ok(())
+    }
+
+    /// Get the current decode parameters for the image
+    pub fn decode_options(&self, index: &usize) -> DecodeOptions {
+        let (columns, rows) = self.sheet_grid;
+        DecodeOptions {
+            size: Size2d {
+                width: (self.texture_size.width / columns).max(1),
+                height: (self.texture_size.height / rows).max(1),
+                scale_factor: self.texture_size.scale_factor,
+            },
+            filter_type: self.resize_filter,
+            fit_mode: self.fit_mode,
+            no_upscale: self.no_upscale,
+            downscale_decode: self.downscale_decode,
+            color_management: self.color_management,
+            exif_orientation: self.exif_orientation,
+            rotation: self.rotation(index),
+        }
+    }
+
+    /// Decode an image into a cache entry, keep the error message on failure
+    pub fn load_image_cache(
+        index: &usize,
+        path: Option<PathBuf>,
+        options: &DecodeOptions,
+        gpu_resizer: Option<&GpuResizer>,
+        disk_cache: Option<&DiskCache>,
+    ) -> ImageCache {
+        let mut emsg = None;
+        let mut animation = None;
+        let image = match &path {
+            Some(path) => {
+                let result = if let Some(play_count) = Self::animation_play_count(path) {
+                    Self::open_and_resize_animation(index, path, options, play_count).map(|anim| {
+                        let first_frame = anim.frames[0].clone();
+                        if anim.frames.len() > 1 {
+                            animation = Some(Arc::new(anim));
                         }
+                        first_frame
+                    })
+                } else {
+                    Self::open_and_resize_image(index, path, options, gpu_resizer, disk_cache)
+                };
+
+                match result {
+                    Ok(image) => image,
+                    Err(err) => {
+                        log::error!("{}", err);
+                        emsg = Some(err.to_string());
+                        Self::error_placeholder(&options.size)
                     }
                 }
-                None => (image::RgbaImage::new(1, 1), None),
-            };
-
-            self.cache.insert(*index, ImageCache { path, image, emsg });
+            }
+            None => image::RgbaImage::new(1, 1),
         };
 
-        Ok(())
+        let local_path = path
+            .as_deref()
+            .filter(|p| !is_remote_path(p))
+            .map(multi_image::file_path);
+        let local_path = local_path.as_deref();
+        let dimensions = local_path
+            .filter(|_| emsg.is_none())
+            .and_then(|p| image::image_dimensions(p).ok());
+        let metadata = local_path.and_then(|p| fs::metadata(p).ok());
+        let file_size = metadata.as_ref().map(|v| v.len());
+        let modified = metadata.as_ref().and_then(|v| v.modified().ok());
+        let exif = local_path.and_then(exif_info);
+        let caption = local_path.and_then(read_caption);
+
+        ImageCache {
+            path,
+            image,
+            animation,
+            dimensions,
+            file_size,
+            modified,
+            exif,
+            emsg,
+            caption,
+            histogram: None,
+        }
     }
 
     pub fn force_reload_cache(&mut self, index: &usize) -> Result<()> {
@@ -204,7 +1038,126 @@ impl ImageLoader {
         self.ensure_cache(index)
     }
 
+    /// Return true if the file of the cached image was modified after decoding it
+    pub fn is_modified(&self, index: &usize) -> bool {
+        let image_cache = match self.cache.get(index) {
+            Some(v) => v,
+            None => return false,
+        };
+        let path = match image_cache.path.as_deref().filter(|p| !is_remote_path(p)) {
+            Some(v) => multi_image::file_path(v),
+            None => return false,
+        };
+        let modified = fs::metadata(path).and_then(|v| v.modified()).ok();
+        modified.is_some() && modified != image_cache.modified
+    }
+
+    /// Change the fit mode and re-decode the current image with it
+    pub fn set_fit_mode(&mut self, fit_mode: FitMode) -> Result<()> {
+        self.fit_mode = fit_mode;
+        self.cache.clear();
+        let index = self.current_index;
+        self.force_reload_cache(&index)
+    }
+
+    /// Get the manual rotation in degrees (0, 90, 180 or 270)
+    pub fn rotation(&self, index: &usize) -> u16 {
+        self.rotations.get(index).copied().unwrap_or(0)
+    }
+
+    /// Mark or unmark the current image as favorite, return true if marked
+    pub fn toggle_favorite(&mut self) -> bool {
+        let index = self.current_index;
+        if self.favorites.remove(&index) {
+            false
+        } else {
+            self.favorites.insert(index);
+            true
+        }
+    }
+
+    /// Favorite paths in the list order
+    pub fn favorite_paths(&self) -> Vec<PathBuf> {
+        let mut indices: Vec<_> = self.favorites.iter().copied().collect();
+        indices.sort_unstable();
+        indices
+            .into_iter()
+            .filter_map(|i| self.scanned_paths.get(i).cloned())
+            .collect()
+    }
+
+    /// Mark the scanned paths saved as favorite
+    pub fn restore_favorites(&mut self, paths: &HashSet<PathBuf>) {
+        self.favorites = self
+            .scanned_paths
+            .iter()
+            .enumerate()
+            .filter(|(_, path)| paths.contains(*path))
+            .map(|(i, _)| i)
+            .collect();
+    }
+
+    /// Rotate the current image clockwise by 90 degrees
+    pub fn rotate_cw(&mut self) -> u16 {
+        self.add_rotation(90)
+    }
+
+    /// Rotate the current image counterclockwise by 90 degrees
+    pub fn rotate_ccw(&mut self) -> u16 {
+        self.add_rotation(270)
+    }
+
+    fn add_rotation(&mut self, degrees: u16) -> u16 {
+        self.thumbnails.remove(&self.current_index);
+        let rotation = self.rotations.entry(self.current_index).or_insert(0);
+        *rotation = (*rotation + degrees) % 360;
+        *rotation
+    }
+
+    /// Queue the thumbnails of the indices which are not generated yet
+    pub fn request_thumbnails<I: IntoIterator<Item = usize>>(&mut self, indices: I) {
+        self.thumbnail_queue = indices
+            .into_iter()
+            .filter(|i| *i < self.scanned_paths.len() && !self.thumbnails.contains_key(i))
+            .collect();
+    }
+
+    /// Clear the thumbnails and the image cache
+    pub fn clear_cache(&mut self) {
+        self.cache.clear();
+        self.thumbnails.clear();
+        self.thumbnail_queue.clear();
+    }
+
+    pub fn load_thumbnail(
+        index: &usize,
+        path: &Path,
+        exif_orientation: bool,
+        rotation: u16,
+    ) -> image::RgbaImage {
+        let options = DecodeOptions {
+            size: Size2d {
+                width: THUMBNAIL_SIZE,
+                height: THUMBNAIL_SIZE,
+                scale_factor: None,
+            },
+            filter_type: image::imageops::FilterType::Triangle,
+            fit_mode: FitMode::Fit,
+            no_upscale: false,
+            downscale_decode: true,
+            color_management: false,
+            exif_orientation,
+            rotation,
+        };
+        Self::open_and_resize_image(index, path, &options, None, None).unwrap_or_else(|err| {
+            log::error!("{}", err);
+            image::RgbaImage::new(1, 1)
+        })
+    }
+
     pub fn get_current(&mut self) -> Result<&ImageCache> {
+        // The first image of the page, also after jumping into a contact sheet page
+        self.current_index -= self.current_index % self.page_size.max(1);
         let index = self.current_index;
         self.ensure_cache(&index)?;
 
@@ -214,21 +1167,103 @@ impl ImageLoader {
             .ok_or_else(|| anyhow!("faild to load an image cache."))?;
 
         // Update preload queue: i+1, i-1, i+2, i-2, ...,
+        // or i+1, i+2, ..., i-1, i-2, ... with the forward bias
         self.preload_queue.clear();
-        for i in 1..=self.cache_extent {
-            let amount = i as i32;
-
+        let extent = self.cache_extent as i32;
+        let amounts: Vec<i32> = match self.preload_bias {
+            PreloadBias::Balanced => (1..=extent).flat_map(|i| vec![i, -i]).collect(),
+            PreloadBias::Forward => (1..=extent).chain((1..=extent).map(|i| -i)).collect(),
+            PreloadBias::Backward => (1..=extent).map(|i| -i).chain(1..=extent).collect(),
+        };
+        let page_size = self.page_size.max(1);
+        let len = self.scanned_paths.len();
+        // The rest of the current page first
+        self.preload_queue
+            .extend((index + 1..index + page_size).take_while(|i| *i < len));
+        for amount in amounts {
             if let Some(idx) = self.get_next_index(amount) {
-                self.preload_queue.push_back(idx);
-            }
-            if let Some(idx) = self.get_next_index(-amount) {
-                self.preload_queue.push_back(idx);
+                self.preload_queue
+                    .extend((idx..idx + page_size).take_while(|i| *i < len));
             }
         }
 
         Ok(image_cache)
     }
 
+    /// Get the images of the current contact sheet page, None for the cells still preloading
+    pub fn get_page(&self) -> Vec<Option<&ImageCache>> {
+        self.page_range().map(|i| self.cache.get(&i)).collect()
+    }
+
+    /// Indices of the current contact sheet page
+    pub fn page_range(&self) -> std::ops::Range<usize> {
+        let start = self.current_index;
+        start..(start + self.page_size.max(1)).min(self.scanned_paths.len())
+    }
+
+    /// Move the image moved by the amount to the front of the preload queue,
+    /// with the rest of its page in the contact sheet
+    pub fn prioritize_preload(&mut self, amount: i32) {
+        let page_size = self.page_size.max(1);
+        let len = self.scanned_paths.len();
+        if let Some(index) = self.get_next_index(amount) {
+            for i in (index..index + page_size).take_while(|i| *i < len).rev() {
+                if !self.cache.contains_key(&i) {
+                    self.preload_queue.retain(|v| *v != i);
+                    self.preload_queue.push_front(i);
+                }
+            }
+        }
+    }
+
+    /// Return true if the image moved by the amount is in the cache,
+    /// also true if there is no image to preload
+    pub fn is_preloaded(&self, amount: i32) -> bool {
+        if self.cache_extent == 0 {
+            return true;
+        }
+        let page_size = self.page_size.max(1);
+        let len = self.scanned_paths.len();
+        match self.get_next_index(amount) {
+            Some(index) => (index..index + page_size)
+                .take_while(|i| *i < len)
+                .all(|i| self.cache.contains_key(&i)),
+            None => true,
+        }
+    }
+
+    /// Set the glob patterns to filter the scanned files, an empty include list allows all files
+    pub fn set_path_filters(&mut self, include: &[String], exclude: &[String]) {
+        self.include = Self::build_glob_set(include);
+        self.exclude = Self::build_glob_set(exclude);
+    }
+
+    fn build_glob_set(patterns: &[String]) -> GlobSet {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            match Glob::new(pattern) {
+                Ok(glob) => {
+                    builder.add(glob);
+                }
+                Err(err) => log::error!("invalid glob pattern '{}': {}", pattern, err),
+            }
+        }
+
+        builder.build().unwrap_or_else(|err| {
+            log::error!("{}", err);
+            GlobSet::empty()
+        })
+    }
+
+    /// Match the include/exclude patterns against the file name and the full path
+    fn is_included(&self, path: &Path) -> bool {
+        let is_match = |set: &GlobSet| {
+            set.is_match(path) || path.file_name().map_or(false, |name| set.is_match(name))
+        };
+
+        (self.include.is_empty() || is_match(&self.include)) && !is_match(&self.exclude)
+    }
+
     fn is_supported_ext(&self, path: &Path) -> bool {
         if let Some(ext) = path.extension() {
             let ext = ext.to_ascii_lowercase();
@@ -243,37 +1278,315 @@ impl ImageLoader {
     }
 
     pub fn scan_input_paths(&mut self, paths: &[PathBuf]) {
-        self.scanned_paths = {
-            let mut out: Vec<PathBuf> = vec![];
-            for path in paths {
-                if path.is_dir() {
-                    self.scan_recursively(&mut out, path, 0);
-                } else if path.is_file() && self.is_supported_ext(path) {
-                    out.push(path.clone());
+        let mut result = ScanResult::default();
+        for path in paths {
+            self.scan_input_path(&mut result, path, 0);
+        }
+        self.scanned_dirs = result.dirs;
+        self.scanned_paths = result.paths;
+        self.durations = result.durations;
+        self.transitions = result.transitions;
+        self.sort_paths();
+    }
+
+    /// Scan an image, a directory or a playlist, return false if the path was skipped
+    fn scan_input_path(&self, result: &mut ScanResult, path: &Path, playlist_depth: usize) -> bool {
+        if is_remote_path(path) {
+            self.push_found(&mut result.paths, path.to_path_buf());
+        } else if path.is_dir() {
+            result.dirs.push(path.to_path_buf());
+            let mut walk = DirWalk::default();
+            self.scan_recursively(&mut result.paths, &mut walk, path, 0);
+            log::info!(
+                "scanned {}: {} directories, {} files",
+                path.display(),
+                walk.dirs,
+                walk.files
+            );
+        } else if Self::is_playlist(path) {
+            self.scan_playlist(result, path, playlist_depth);
+        } else if path.is_file() && self.is_supported_ext(path) && self.is_included(path) {
+            self.push_found(&mut result.paths, path.to_path_buf());
+        } else {
+            return false;
+        }
+
+        true
+    }
+
+    pub fn is_playlist(path: &Path) -> bool {
+        path.is_file()
+            && path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .map_or(false, |ext| {
+                    PLAYLIST_EXTENSIONS
+                        .iter()
+                        .any(|v| v.eq_ignore_ascii_case(ext))
+                })
+    }
+
+    /// Read the playlist in order, each line is an image or a directory path
+    /// relative to the playlist, the lines starting with '#' are comments.
+    /// A line can end with the display time in seconds and the transition effect name,
+    /// e.g. "photo.jpg 8 wipe_left"
+    fn scan_playlist(&self, result: &mut ScanResult, path: &Path, depth: usize) {
+        if depth > MAX_DEPTH_PLAYLIST {
+            log::warn!("playlist nested too deep: {}", path.display());
+            return;
+        }
+
+        let text = match fs::read_to_string(path) {
+            Ok(v) => v,
+            Err(err) => {
+                log::error!("failed to read the playlist {}: {}", path.display(), err);
+                return;
+            }
+        };
+
+        let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let resolve = |line: &str| {
+            let entry = Path::new(line);
+            if entry.is_relative() && !is_remote_path(entry) {
+                base_dir.join(entry)
+            } else {
+                entry.to_path_buf()
+            }
+        };
+
+        for line in text
+            .trim_start_matches('\u{feff}')
+            .lines()
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty() && !v.starts_with('#'))
+        {
+            // The trailing number is the display time and the trailing effect name is
+            // the transition, in any order, unless the whole line is a path
+            let mut entry_line = line;
+            let mut duration = None;
+            let mut mode = None;
+            while !resolve(entry_line).exists() {
+                let (rest, token) = match entry_line.rsplit_once(char::is_whitespace) {
+                    Some(v) => v,
+                    None => break,
+                };
+                if let (None, Ok(secs)) = (duration, token.parse::<u32>()) {
+                    duration = Some(secs);
+                } else if let (None, Some(v)) = (mode, transition::effect_mode(token)) {
+                    mode = Some(v);
+                } else {
+                    break;
+                }
+                entry_line = rest.trim_end();
+            }
+            let entry = resolve(entry_line);
+
+            let first = result.paths.len();
+            if !self.scan_input_path(result, &entry, depth + 1) {
+                log::warn!("skipped playlist entry '{}' in {}", line, path.display());
+            }
+            for image_path in &result.paths[first..] {
+                if let Some(secs) = duration {
+                    result.durations.insert(image_path.clone(), secs);
+                }
+                if let Some(mode) = mode {
+                    result.transitions.insert(image_path.clone(), mode);
                 }
             }
-            out
+        }
+    }
+
+    /// Sort the scanned paths by the sort order,
+    /// the name order is kept from the scan to group the files by folder
+    pub fn sort_paths(&mut self) {
+        match self.sort {
+            // Shuffled by `shuffle_paths` to use the seed
+            SortOrder::Name | SortOrder::Random => {}
+            SortOrder::NameDesc => self.scanned_paths.reverse(),
+            SortOrder::ModifiedTime => {
+                Self::sort_paths_by_metadata(&mut self.scanned_paths, false, |m| m.modified())
+            }
+            SortOrder::ModifiedTimeDesc => {
+                Self::sort_paths_by_metadata(&mut self.scanned_paths, true, |m| m.modified())
+            }
+            SortOrder::Size => {
+                Self::sort_paths_by_metadata(&mut self.scanned_paths, false, |m| Ok(m.len()))
+            }
+            SortOrder::CaptureTime => self.sort_paths_by_capture_time(),
+        }
+    }
+
+    /// Stable sort by the cached capture time across the folders,
+    /// the modified time is used until the capture time is read
+    fn sort_paths_by_capture_time(&mut self) {
+        let capture_times = &self.capture_times;
+        let mut keyed: Vec<_> = self
+            .scanned_paths
+            .drain(..)
+            .map(|path| {
+                let time = capture_times.get(&path).copied().flatten().or_else(|| {
+                    fs::metadata(multi_image::file_path(&path))
+                        .and_then(|m| m.modified())
+                        .ok()
+                });
+                (time, path)
+            })
+            .collect();
+        keyed.sort_by(|(a, _), (b, _)| compare_sort_keys(a, b, false));
+        self.scanned_paths
+            .extend(keyed.into_iter().map(|(_, path)| path));
+    }
+
+    /// Local paths without the cached capture time
+    pub fn uncached_capture_time_paths(&self) -> Vec<PathBuf> {
+        self.scanned_paths
+            .iter()
+            .filter(|p| !is_remote_path(p) && !self.capture_times.contains_key(*p))
+            .cloned()
+            .collect()
+    }
+
+    /// Cache the capture times and sort again, return true if the current image changed.
+    /// The first image stays first, otherwise the current image is kept
+    pub fn apply_capture_times(&mut self, times: HashMap<PathBuf, Option<SystemTime>>) -> bool {
+        self.capture_times.extend(times);
+        if self.sort != SortOrder::CaptureTime {
+            return false;
+        }
+
+        let old_paths = self.scanned_paths.clone();
+        let current_path = old_paths.get(self.current_index).cloned();
+        self.sort_paths();
+
+        let new_indices: HashMap<&PathBuf, usize> = self
+            .scanned_paths
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (p, i))
+            .collect();
+        let remap: Vec<Option<usize>> = old_paths
+            .iter()
+            .map(|p| new_indices.get(p).copied())
+            .collect();
+        self.remap_indices(|i| remap.get(i).copied().flatten());
+
+        if self.current_index == 0 {
+            return self.scanned_paths.first() != current_path.as_ref();
+        }
+        if let Some(index) =
+            current_path.and_then(|p| self.scanned_paths.iter().position(|v| v == &p))
+        {
+            self.current_index = index;
+        }
+        false
+    }
+
+    /// Stable sort by the metadata key, the paths without metadata go last
+    fn sort_paths_by_metadata<K, F>(paths: &mut Vec<PathBuf>, descending: bool, key: F)
+    where
+        K: Ord,
+        F: Fn(&fs::Metadata) -> std::io::Result<K>,
+    {
+        let mut keyed: Vec<_> = paths
+            .drain(..)
+            .map(|path| {
+                let metadata = fs::metadata(multi_image::file_path(&path));
+                (metadata.and_then(|m| key(&m)).ok(), path)
+            })
+            .collect();
+        keyed.sort_by(|(a, _), (b, _)| compare_sort_keys(a, b, descending));
+        paths.extend(keyed.into_iter().map(|(_, path)| path));
+    }
+
+    /// Order of the paths in the sort order, the same as `sort_paths`
+    fn compare_in_sort_order(&self, a: &Path, b: &Path) -> Ordering {
+        let metadata = |p: &Path| fs::metadata(multi_image::file_path(p));
+        let modified = |p: &Path| metadata(p).and_then(|m| m.modified()).ok();
+        let size = |p: &Path| metadata(p).map(|m| m.len()).ok();
+        let capture_time = |p: &Path| {
+            self.capture_times
+                .get(p)
+                .copied()
+                .flatten()
+                .or_else(|| modified(p))
         };
+        match self.sort {
+            SortOrder::Name | SortOrder::Random => self.compare_paths(a, b),
+            SortOrder::NameDesc => self.compare_paths(b, a),
+            SortOrder::ModifiedTime => compare_sort_keys(&modified(a), &modified(b), false),
+            SortOrder::ModifiedTimeDesc => compare_sort_keys(&modified(a), &modified(b), true),
+            SortOrder::Size => compare_sort_keys(&size(a), &size(b), false),
+            SortOrder::CaptureTime => compare_sort_keys(&capture_time(a), &capture_time(b), false),
+        }
+    }
+
+    /// Name order of the paths used by all scans
+    pub fn compare_paths(&self, a: &Path, b: &Path) -> Ordering {
+        if self.case_insensitive_sort {
+            let a_lower = a.to_string_lossy().to_lowercase();
+            let b_lower = b.to_string_lossy().to_lowercase();
+            let ordering = if self.natural_sort {
+                alphanumeric_sort::compare_str(&a_lower, &b_lower)
+            } else {
+                a_lower.cmp(&b_lower)
+            };
+            // Keep the order stable for the names only different in case
+            ordering.then_with(|| a.cmp(b))
+        } else if self.natural_sort {
+            alphanumeric_sort::compare_path(a, b)
+        } else {
+            a.cmp(b)
+        }
     }
 
-    pub fn scan_recursively(&self, out: &mut Vec<PathBuf>, dir: &Path, depth: usize) {
-        if self.scan_subfolders {
-            if depth > MAX_DEPTH_SCAN {
+    fn scan_recursively(
+        &self,
+        out: &mut Vec<PathBuf>,
+        walk: &mut DirWalk,
+        dir: &Path,
+        depth: usize,
+    ) {
+        // MAX_DEPTH_SCAN still guards the deep trees when max_depth is larger
+        let max_depth = if self.scan_subfolders {
+            self.max_depth.unwrap_or(MAX_DEPTH_SCAN).min(MAX_DEPTH_SCAN)
+        } else {
+            0
+        };
+        if depth > max_depth {
+            return;
+        }
+
+        // Skip the directories reached again through a symlink
+        match dir.canonicalize() {
+            Ok(canonical) => {
+                if !walk.visited.insert(canonical) {
+                    log::info!("skip the visited directory: {}", dir.display());
+                    return;
+                }
+            }
+            Err(err) => {
+                log::warn!("{}: {}", dir.display(), err);
                 return;
             }
-        } else if depth > 0 {
-            return;
         }
 
         if let Ok(dir) = fs::read_dir(dir) {
+            walk.dirs += 1;
             let mut paths: Vec<_> = dir.filter_map(|e| e.ok()).map(|e| e.path()).collect();
-            alphanumeric_sort::sort_path_slice(&mut paths);
+            paths.sort_by(|a, b| self.compare_paths(a, b));
 
             for path in paths {
+                if !self.follow_symlinks && path.symlink_metadata().map_or(true, |m| m.is_symlink())
+                {
+                    continue;
+                }
                 if path.is_dir() {
-                    self.scan_recursively(out, &path, depth + 1);
-                } else if path.is_file() && self.is_supported_ext(&path) {
-                    out.push(path);
+                    self.scan_recursively(out, walk, &path, depth + 1);
+                } else if path.is_file() {
+                    walk.files += 1;
+                    if self.is_supported_ext(&path) && self.is_included(&path) {
+                        self.push_found(out, path);
+                    }
                 }
             }
         }
@@ -282,19 +1595,44 @@ impl ImageLoader {
     pub fn open_and_resize_image(
         index: &usize,
         path: &Path,
-        size: &Size2d<u32>,
-        filter_type: image::imageops::FilterType,
+        options: &DecodeOptions,
+        gpu_resizer: Option<&GpuResizer>,
+        disk_cache: Option<&DiskCache>,
     ) -> Result<image::RgbaImage> {
         let mut sw = Stopwatch::new();
 
-        let file = std::fs::File::open(path)?;
+        sw.restart();
+        if let Some(img) = disk_cache.and_then(|cache| cache.load(path, options)) {
+            log::info!("image[{}] disk cache: {} ms", index, sw.elapsed_ms());
+            return Ok(img);
+        }
 
         sw.restart();
-        let mut img = image::open(path)?;
+        let remote_bytes = if is_remote_path(path) {
+            Some(Self::download(path)?)
+        } else {
+            None
+        };
+        let mut img = match &remote_bytes {
+            Some(bytes) => image::io::Reader::new(Cursor::new(bytes))
+                .with_guessed_format()?
+                .decode()?,
+            None => match Self::open_downscaled(index, path, options)? {
+                Some(img) => img,
+                None => Self::open_local(index, path)?,
+            },
+        };
+        // The TIFF file of a page path
+        let file_path = multi_image::file_path(path);
         let time_image_open = sw.elapsed_ms();
 
         sw.restart();
-        if let Some(orientation) = Self::get_exif_orientation(&file) {
+        let orientation = match &remote_bytes {
+            _ if !options.exif_orientation => None,
+            Some(bytes) => Self::get_exif_orientation(&mut Cursor::new(bytes)),
+            None => Self::get_exif_orientation(&mut BufReader::new(fs::File::open(&file_path)?)),
+        };
+        if let Some(orientation) = orientation {
             img = match orientation {
                 1 => img,
                 2 => img.fliph(),
@@ -307,9 +1645,490 @@ impl ImageLoader {
                 _ => img,
             }
         }
-        let time_exif_orientation = sw.elapsed_ms();
+        img = Self::rotate_image(img, options.rotation);
+        let time_orientation = sw.elapsed_ms();
 
         sw.restart();
+        let gpu_resized = gpu_resizer.and_then(|resizer| {
+            Self::resize_image_gpu(resizer, &img, options).unwrap_or_else(|err| {
+                log::warn!("image[{}] GPU resize failed: {}", index, err);
+                None
+            })
+        });
+        let (mut img, resize_device) = match gpu_resized {
+            Some(img) => (img, "gpu"),
+            None => (Self::resize_image(img, options).to_rgba8(), "cpu"),
+        };
+        let time_resize = sw.elapsed_ms();
+
+        // Convert after resizing, the conversion is per pixel
+        if options.color_management {
+            let profile = match &remote_bytes {
+                Some(bytes) => color_profile::read_icc_profile(&mut Cursor::new(bytes)),
+                None => color_profile::read_icc_profile(&mut BufReader::new(fs::File::open(
+                    &file_path,
+                )?)),
+            };
+            if let Some(profile) = profile {
+                color_profile::convert_to_srgb(&mut img, &profile).unwrap_or_else(|err| {
+                    log::warn!("image[{}] color conversion failed: {}", index, err)
+                });
+            }
+        }
+
+        log::info!(
+            "image[{}] open: {} ms, orientation({}): {} ms, resize({}): {} ms",
+            index,
+            time_image_open,
+            if options.exif_orientation {
+                "exif"
+            } else {
+                "manual"
+            },
+            time_orientation,
+            resize_device,
+            time_resize
+        );
+
+        if let Some(cache) = disk_cache {
+            cache
+                .store(path, options, &img)
+                .unwrap_or_else(|err| log::warn!("image[{}] disk cache: {}", index, err));
+        }
+
+        Ok(img)
+    }
+
+    /// Open a local image, the page of a page path or the largest image of an ICO/TIFF file
+    fn open_local(index: &usize, path: &Path) -> Result<image::DynamicImage> {
+        if let Some((file_path, page)) = multi_image::split_page_path(path) {
+            return multi_image::open_tiff_page(&file_path, page);
+        }
+
+        match multi_image::open_largest(path) {
+            Ok(Some(img)) => return Ok(img),
+            Ok(None) => (),
+            Err(err) => log::warn!("image[{}] {}, decode the default image", index, err),
+        }
+        Ok(image::open(path)?)
+    }
+
+    /// Decode all frames of an animated image, stop decoding at the memory limit
+    pub fn open_and_resize_animation(
+        index: &usize,
+        path: &Path,
+        options: &DecodeOptions,
+        play_count: u32,
+    ) -> Result<AnimatedImage> {
+        let sw = Stopwatch::start_new();
+
+        let mut frames = Vec::new();
+        let mut delays = Vec::new();
+        let mut total_bytes = 0;
+        // Resize and keep a frame, false at the memory limit
+        let mut push_frame = |img: image::RgbaImage, delay: Duration| {
+            let img = image::DynamicImage::ImageRgba8(img);
+            let img = Self::rotate_image(img, options.rotation);
+            let img = Self::resize_image(img, options).to_rgba8();
+
+            total_bytes += img.as_raw().len();
+            frames.push(img);
+            delays.push(if delay < MIN_FRAME_DELAY {
+                DEFAULT_FRAME_DELAY
+            } else {
+                delay
+            });
+
+            if total_bytes > MAX_ANIMATION_BYTES {
+                log::warn!(
+                    "image[{}] too many frames, truncated at {} frames",
+                    index,
+                    frames.len()
+                );
+                return false;
+            }
+            true
+        };
+
+        match image::ImageFormat::from_path(path)? {
+            #[cfg(feature = "webp_animation")]
+            image::ImageFormat::WebP => Self::decode_webp_frames(path, &mut push_frame)?,
+            format => {
+                let file = BufReader::new(fs::File::open(path)?);
+                let decoded_frames = match format {
+                    image::ImageFormat::Png => image::codecs::png::PngDecoder::new(file)?
+                        .apng()
+                        .into_frames(),
+                    _ => image::codecs::gif::GifDecoder::new(file)?.into_frames(),
+                };
+                for frame in decoded_frames {
+                    let frame = frame?;
+                    let (numer, denom) = frame.delay().numer_denom_ms();
+                    let delay = Duration::from_millis((numer / denom.max(1)) as u64);
+                    if !push_frame(frame.into_buffer(), delay) {
+                        break;
+                    }
+                }
+            }
+        }
+
+        if frames.is_empty() {
+            return Err(anyhow!("no frames in the animation."));
+        }
+
+        log::info!(
+            "image[{}] animation: {} frames, {} ms",
+            index,
+            frames.len(),
+            sw.elapsed_ms()
+        );
+
+        Ok(AnimatedImage {
+            frames,
+            delays,
+            play_count,
+        })
+    }
+
+    /// Decode the frames of an animated WebP, the frame timestamps are the end times
+    #[cfg(feature = "webp_animation")]
+    fn decode_webp_frames(
+        path: &Path,
+        mut push_frame: impl FnMut(image::RgbaImage, Duration) -> bool,
+    ) -> Result<()> {
+        let bytes = fs::read(path)?;
+        let decoder = webp_animation::Decoder::new(&bytes)
+            .map_err(|err| anyhow!("failed to decode the WebP animation: {:?}", err))?;
+
+        let mut prev_timestamp = 0;
+        for frame in decoder.into_iter() {
+            let (width, height) = frame.dimensions();
+            let img = image::RgbaImage::from_raw(width, height, frame.data().to_vec())
+                .ok_or_else(|| anyhow!("invalid WebP frame size."))?;
+            let delay = Duration::from_millis((frame.timestamp() - prev_timestamp).max(0) as u64);
+            prev_timestamp = frame.timestamp();
+            if !push_frame(img, delay) {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Download a remote image
+    #[cfg(feature = "remote")]
+    pub fn download(path: &Path) -> Result<Vec<u8>> {
+        let url = path
+            .to_str()
+            .ok_or_else(|| anyhow!("invalid url: {}", path.display()))?;
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(DOWNLOAD_CONNECT_TIMEOUT)
+            .timeout_read(DOWNLOAD_READ_TIMEOUT)
+            .timeout(DOWNLOAD_TIMEOUT)
+            .build();
+        let mut bytes = Vec::new();
+        agent
+            .get(url)
+            .call()?
+            .into_reader()
+            .take(MAX_DOWNLOAD_BYTES)
+            .read_to_end(&mut bytes)
+            .map_err(|err| match err.kind() {
+                std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock => {
+                    anyhow!("download timed out: {}", url)
+                }
+                _ => err.into(),
+            })?;
+
+        Ok(bytes)
+    }
+
+    #[cfg(not(feature = "remote"))]
+    pub fn download(path: &Path) -> Result<Vec<u8>> {
+        Err(anyhow!(
+            "cannot open {}, remote images are not supported in this build.",
+            path.display()
+        ))
+    }
+
+    /// Get the play count of the animated formats (GIF, APNG and WebP), None for the still images
+    fn animation_play_count(path: &Path) -> Option<u32> {
+        if is_remote_path(path) {
+            return None;
+        }
+
+        match image::ImageFormat::from_path(path) {
+            Ok(image::ImageFormat::Gif) => {
+                let mut file = fs::File::open(path).ok()?;
+                Some(Self::gif_play_count(&mut file))
+            }
+            Ok(image::ImageFormat::Png) => {
+                let mut file = BufReader::new(fs::File::open(path).ok()?);
+                Self::apng_play_count(&mut file)
+            }
+            #[cfg(feature = "webp_animation")]
+            Ok(image::ImageFormat::WebP) => {
+                let mut file = BufReader::new(fs::File::open(path).ok()?);
+                Self::webp_play_count(&mut file)
+            }
+            _ => None,
+        }
+    }
+
+    /// Get the play count from the 'NETSCAPE2.0' extension, play once without the extension
+    fn gif_play_count<R: Read>(reader: &mut R) -> u32 {
+        const APP_ID: &[u8] = b"NETSCAPE2.0";
+
+        let mut head = Vec::new();
+        reader
+            .by_ref()
+            .take(GIF_LOOP_SEARCH_BYTES)
+            .read_to_end(&mut head)
+            .log_err();
+        head.windows(APP_ID.len() + 4)
+            .find(|w| w.starts_with(APP_ID) && w[APP_ID.len()] == 3 && w[APP_ID.len() + 1] == 1)
+            .map(|w| {
+                // The value is the number of repeats after the first play
+                match u16::from_le_bytes([w[APP_ID.len() + 2], w[APP_ID.len() + 3]]) {
+                    0 => 0,
+                    repeats => repeats as u32 + 1,
+                }
+            })
+            .unwrap_or(1)
+    }
+
+    /// Get the play count from the 'acTL' chunk, None for a still PNG
+    fn apng_play_count<R: Read>(reader: &mut R) -> Option<u32> {
+        let mut signature = [0; 8];
+        reader.read_exact(&mut signature).ok()?;
+        if signature != PNG_SIGNATURE {
+            return None;
+        }
+
+        // 'acTL' must appear before the first 'IDAT'
+        loop {
+            let mut header = [0; 8];
+            reader.read_exact(&mut header).ok()?;
+            let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+            match &header[4..] {
+                b"acTL" => {
+                    let mut data = [0; 8];
+                    reader.read_exact(&mut data).ok()?;
+                    return Some(u32::from_be_bytes([data[4], data[5], data[6], data[7]]));
+                }
+                b"IDAT" | b"IEND" => return None,
+                _ => {
+                    // Skip the chunk data and CRC
+                    let skip = length as u64 + 4;
+                    let skipped =
+                        std::io::copy(&mut reader.by_ref().take(skip), &mut std::io::sink())
+                            .ok()?;
+                    if skipped != skip {
+                        return None;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Get the loop count from the 'ANIM' chunk, None for a still WebP
+    #[cfg(feature = "webp_animation")]
+    fn webp_play_count<R: Read>(reader: &mut R) -> Option<u32> {
+        let mut header = [0; 12];
+        reader.read_exact(&mut header).ok()?;
+        if &header[..4] != b"RIFF" || &header[8..] != b"WEBP" {
+            return None;
+        }
+
+        // 'ANIM' must appear before the image data
+        loop {
+            let mut chunk_header = [0; 8];
+            reader.read_exact(&mut chunk_header).ok()?;
+            let length = u32::from_le_bytes([
+                chunk_header[4],
+                chunk_header[5],
+                chunk_header[6],
+                chunk_header[7],
+            ]);
+            match &chunk_header[..4] {
+                b"ANIM" => {
+                    // The background color and the loop count, 0 is infinite
+                    let mut data = [0; 6];
+                    reader.read_exact(&mut data).ok()?;
+                    return Some(u16::from_le_bytes([data[4], data[5]]) as u32);
+                }
+                b"VP8 " | b"VP8L" | b"ANMF" => return None,
+                _ => {
+                    // Skip the chunk data, padded to an even length
+                    let skip = (length as u64 + 1) & !1;
+                    let skipped =
+                        std::io::copy(&mut reader.by_ref().take(skip), &mut std::io::sink())
+                            .ok()?;
+                    if skipped != skip {
+                        return None;
+                    }
+                }
+            }
+        }
+    }
+
+    fn rotate_image(img: image::DynamicImage, rotation: u16) -> image::DynamicImage {
+        match rotation {
+            90 => img.rotate90(),
+            180 => img.rotate180(),
+            270 => img.rotate270(),
+            _ => img,
+        }
+    }
+
+    fn resize_image(img: image::DynamicImage, options: &DecodeOptions) -> image::DynamicImage {
+        let filter_type = options.filter_type;
+        let (logical_width, logical_height) = Self::logical_size(&options.size);
+        if options.no_upscale && Self::fits_without_resize(img.dimensions(), options) {
+            return img;
+        }
+        match options.fit_mode {
+            FitMode::Fit => img.resize(logical_width, logical_height, filter_type),
+            FitMode::Width => img.resize(logical_width, u32::MAX, filter_type),
+            FitMode::Fill => img.resize_to_fill(logical_width, logical_height, filter_type),
+            FitMode::Stretch => img.resize_exact(logical_width, logical_height, filter_type),
+            FitMode::Actual => img,
+        }
+    }
+
+    /// Resize the image on the GPU, return None to fall back to the CPU resize
+    fn resize_image_gpu(
+        resizer: &GpuResizer,
+        img: &image::DynamicImage,
+        options: &DecodeOptions,
+    ) -> Result<Option<image::RgbaImage>> {
+        let (width, height) = img.dimensions();
+        let (logical_width, logical_height) = Self::logical_size(&options.size);
+        // The images kept at the original size go through the CPU path
+        let (resize_width, resize_height) = match Self::resize_size((width, height), options) {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+
+        if !resizer.is_supported((width, height), (resize_width, resize_height)) {
+            log::info!(
+                "{}x{} exceeds the GPU texture limit, resize on the CPU",
+                width,
+                height
+            );
+            return Ok(None);
+        }
+
+        let resized = resizer.resize(&img.to_rgba8(), resize_width, resize_height)?;
+        if options.fit_mode == FitMode::Fill {
+            let crop_width = logical_width.min(resize_width);
+            let crop_height = logical_height.min(resize_height);
+            let x = (resize_width - crop_width) / 2;
+            let y = (resize_height - crop_height) / 2;
+            return Ok(Some(
+                image::imageops::crop_imm(&resized, x, y, crop_width, crop_height).to_image(),
+            ));
+        }
+
+        Ok(Some(resized))
+    }
+
+    /// Draw a "broken image" box to show the failed images
+    pub fn error_placeholder(size: &Size2d<u32>) -> image::RgbaImage {
+        let (width, height) = Self::logical_size(size);
+        let box_size = (width.min(height) / 3).max(ERROR_PLACEHOLDER_BORDER * 4);
+        let mut img = image::RgbaImage::from_pixel(box_size, box_size, ERROR_PLACEHOLDER_BG);
+
+        let border = ERROR_PLACEHOLDER_BORDER;
+        for (x, y, pixel) in img.enumerate_pixels_mut() {
+            let is_border =
+                x < border || y < border || x >= box_size - border || y >= box_size - border;
+            // Diagonal cross
+            let is_cross =
+                x.abs_diff(y) <= border / 2 || (x + y + 1).abs_diff(box_size) <= border / 2;
+            if is_border || is_cross {
+                *pixel = ERROR_PLACEHOLDER_FG;
+            }
+        }
+
+        img
+    }
+
+    /// Get the size to resize the image to before cropping, None if the image is kept as is
+    fn resize_size((width, height): (u32, u32), options: &DecodeOptions) -> Option<(u32, u32)> {
+        let (logical_width, logical_height) = Self::logical_size(&options.size);
+        if options.no_upscale && Self::fits_without_resize((width, height), options) {
+            return None;
+        }
+
+        let scale = |ratio: f64| {
+            (
+                ((width as f64 * ratio).round() as u32).max(1),
+                ((height as f64 * ratio).round() as u32).max(1),
+            )
+        };
+        let ratio_x = logical_width as f64 / width.max(1) as f64;
+        let ratio_y = logical_height as f64 / height.max(1) as f64;
+        match options.fit_mode {
+            FitMode::Fit => Some(scale(ratio_x.min(ratio_y))),
+            FitMode::Fill => Some(scale(ratio_x.max(ratio_y))),
+            FitMode::Stretch => Some((logical_width, logical_height)),
+            FitMode::Actual => None,
+            FitMode::Width => Some(scale(ratio_x)),
+        }
+    }
+
+    /// Return true if the image is not larger than the texture,
+    /// only the width counts in the fit width mode
+    fn fits_without_resize((width, height): (u32, u32), options: &DecodeOptions) -> bool {
+        let (logical_width, logical_height) = Self::logical_size(&options.size);
+        width <= logical_width && (options.fit_mode == FitMode::Width || height <= logical_height)
+    }
+
+    /// Decode a huge JPEG at a reduced DCT scale to bound the memory usage,
+    /// None if the fast path is not available
+    fn open_downscaled(
+        index: &usize,
+        path: &Path,
+        options: &DecodeOptions,
+    ) -> Result<Option<image::DynamicImage>> {
+        if !options.downscale_decode
+            || !matches!(
+                image::ImageFormat::from_path(path),
+                Ok(image::ImageFormat::Jpeg)
+            )
+        {
+            return Ok(None);
+        }
+
+        let (width, height) = image::image_dimensions(path)?;
+        // Keep enough pixels for any orientation
+        let target = match Self::resize_size((width, height), options) {
+            Some((target_width, target_height)) => target_width.max(target_height),
+            None => return Ok(None),
+        };
+        if width.min(height) < target * 2 {
+            return Ok(None);
+        }
+
+        let file = BufReader::new(fs::File::open(path)?);
+        let mut decoder = image::codecs::jpeg::JpegDecoder::new(file)?;
+        let request = target.min(u16::MAX as u32) as u16;
+        let (scaled_width, scaled_height) = decoder.scale(request, request)?;
+        log::info!(
+            "image[{}] downscale decode: {}x{} -> {}x{}",
+            index,
+            width,
+            height,
+            scaled_width,
+            scaled_height
+        );
+
+        Ok(Some(image::DynamicImage::from_decoder(decoder)?))
+    }
+
+    /// Get the target size in logical pixels
+    fn logical_size(size: &Size2d<u32>) -> (u32, u32) {
         let logical_width = match size.scale_factor {
             Some(scale_factor) => {
                 if scale_factor > 0.0 {
@@ -330,27 +2149,13 @@ impl ImageLoader {
             }
             None => (size.height as f64),
         } as u32;
-        let img = img
-            .resize(logical_width, logical_height, filter_type)
-            .to_rgba8();
-        let time_resize = sw.elapsed_ms();
-
-        log::info!(
-            "image[{}] open: {} ms, exif: {} ms, resize: {} ms",
-            index,
-            time_image_open,
-            time_exif_orientation,
-            time_resize
-        );
-
-        Ok(img)
+        (logical_width, logical_height)
     }
 
     /// Get the Exif Orientation value
-    fn get_exif_orientation(file: &fs::File) -> Option<u16> {
-        let mut bufreader = std::io::BufReader::new(file);
+    fn get_exif_orientation<R: BufRead + Seek>(reader: &mut R) -> Option<u16> {
         let exifreader = exif::Reader::new();
-        if let Ok(exif) = exifreader.read_from_container(&mut bufreader) {
+        if let Ok(exif) = exifreader.read_from_container(reader) {
             if let Some(orient) = exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY) {
                 if let exif::Value::Short(v) = &orient.value {
                     if let Some(v) = v.first() {
@@ -0,0 +1,589 @@
+use crate::config::KeyNames;
+use std::collections::HashMap;
+use std::fmt;
+use winit::event::{ModifiersState, VirtualKeyCode};
+
+/// Actions triggered by the keyboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    Next,
+    Prev,
+    Next10,
+    Prev10,
+    First,
+    Last,
+    TogglePause,
+    Pause,
+    TogglePauseAtLast,
+    ShowPosition,
+    ToggleFullscreen,
+    ToggleAlwaysOnTop,
+    ToggleTitlebar,
+    Minimize,
+    WindowScale50,
+    WindowScale100,
+    WindowScale200,
+    TimerDecrease,
+    TimerIncrease,
+    TimerReset,
+    CycleFitMode,
+    RotateCw,
+    RotateCcw,
+    ToggleZoomMode,
+    ToggleInfo,
+    ToggleDirection,
+    ToggleTransition,
+    NextTransitionEffect,
+    Trash,
+    ToggleGrid,
+    SaveImage,
+    CopyPath,
+    CopyImage,
+    BrightnessIncrease,
+    BrightnessDecrease,
+    ContrastIncrease,
+    ContrastDecrease,
+    GammaIncrease,
+    GammaDecrease,
+    ResetAdjustments,
+    ToggleLockAdjustments,
+    FlipHorizontal,
+    FlipVertical,
+    NextMonitor,
+    ToggleHistogram,
+    GoTo,
+    Screenshot,
+    Blank,
+    BlankWhite,
+    ToggleFavorite,
+    CopyFavorites,
+    ToggleHelp,
+    PinCompare,
+    ExitCompare,
+    RotateFineCw,
+    RotateFineCcw,
+    ResetRotation,
+    RevealFile,
+    ToggleFilmstrip,
+    SetWallpaper,
+    ReloadImage,
+}
+
+/// Action names used in the config file
+const ACTION_NAMES: [(Action, &str); 62] = [
+    (Action::Quit, "quit"),
+    (Action::Next, "next"),
+    (Action::Prev, "prev"),
+    (Action::Next10, "next10"),
+    (Action::Prev10, "prev10"),
+    (Action::First, "first"),
+    (Action::Last, "last"),
+    (Action::TogglePause, "toggle_pause"),
+    (Action::Pause, "pause"),
+    (Action::TogglePauseAtLast, "toggle_pause_at_last"),
+    (Action::ShowPosition, "show_position"),
+    (Action::ToggleFullscreen, "toggle_fullscreen"),
+    (Action::ToggleAlwaysOnTop, "toggle_always_on_top"),
+    (Action::ToggleTitlebar, "toggle_titlebar"),
+    (Action::Minimize, "minimize"),
+    (Action::WindowScale50, "window_scale_50"),
+    (Action::WindowScale100, "window_scale_100"),
+    (Action::WindowScale200, "window_scale_200"),
+    (Action::TimerDecrease, "timer_decrease"),
+    (Action::TimerIncrease, "timer_increase"),
+    (Action::TimerReset, "timer_reset"),
+    (Action::CycleFitMode, "cycle_fit_mode"),
+    (Action::RotateCw, "rotate_cw"),
+    (Action::RotateCcw, "rotate_ccw"),
+    (Action::ToggleZoomMode, "toggle_zoom_mode"),
+    (Action::ToggleInfo, "toggle_info"),
+    (Action::ToggleDirection, "toggle_direction"),
+    (Action::ToggleTransition, "toggle_transition"),
+    (Action::NextTransitionEffect, "next_transition_effect"),
+    (Action::Trash, "trash"),
+    (Action::ToggleGrid, "toggle_grid"),
+    (Action::SaveImage, "save_image"),
+    (Action::CopyPath, "copy_path"),
+    (Action::CopyImage, "copy_image"),
+    (Action::BrightnessIncrease, "brightness_increase"),
+    (Action::BrightnessDecrease, "brightness_decrease"),
+    (Action::ContrastIncrease, "contrast_increase"),
+    (Action::ContrastDecrease, "contrast_decrease"),
+    (Action::GammaIncrease, "gamma_increase"),
+    (Action::GammaDecrease, "gamma_decrease"),
+    (Action::ResetAdjustments, "reset_adjustments"),
+    (Action::ToggleLockAdjustments, "toggle_lock_adjustments"),
+    (Action::FlipHorizontal, "flip_horizontal"),
+    (Action::FlipVertical, "flip_vertical"),
+    (Action::NextMonitor, "next_monitor"),
+    (Action::ToggleHistogram, "toggle_histogram"),
+    (Action::GoTo, "goto"),
+    (Action::Screenshot, "screenshot"),
+    (Action::Blank, "blank"),
+    (Action::BlankWhite, "blank_white"),
+    (Action::ToggleFavorite, "toggle_favorite"),
+    (Action::CopyFavorites, "copy_favorites"),
+    (Action::ToggleHelp, "toggle_help"),
+    (Action::PinCompare, "pin_compare"),
+    (Action::ExitCompare, "exit_compare"),
+    (Action::RotateFineCw, "rotate_fine_cw"),
+    (Action::RotateFineCcw, "rotate_fine_ccw"),
+    (Action::ResetRotation, "reset_rotation"),
+    (Action::RevealFile, "reveal_file"),
+    (Action::ToggleFilmstrip, "toggle_filmstrip"),
+    (Action::SetWallpaper, "set_wallpaper"),
+    (Action::ReloadImage, "reload_image"),
+];
+
+/// Default keys of the actions
+const DEFAULT_BINDINGS: [(Action, &[&str]); 62] = [
+    (Action::Quit, &["Q", "Escape"]),
+    (
+        Action::Next,
+        &["Right", "Down", "PageDown", "Period", "Return"],
+    ),
+    (Action::Prev, &["Left", "Up", "PageUp", "Comma"]),
+    (
+        Action::Next10,
+        &[
+            "Shift+Right",
+            "Shift+Down",
+            "Shift+PageDown",
+            "Shift+Period",
+            "Shift+Return",
+        ],
+    ),
+    (
+        Action::Prev10,
+        &["Shift+Left", "Shift+Up", "Shift+PageUp", "Shift+Comma"],
+    ),
+    (Action::First, &["Home"]),
+    (Action::Last, &["End"]),
+    (Action::TogglePause, &["Space", "P"]),
+    (Action::Pause, &["Pause"]),
+    (Action::TogglePauseAtLast, &["L"]),
+    (Action::ShowPosition, &["O"]),
+    (Action::ToggleFullscreen, &["F", "F11", "Alt+Return"]),
+    (Action::ToggleAlwaysOnTop, &["T"]),
+    (Action::ToggleTitlebar, &["D"]),
+    (Action::Minimize, &["Alt+M", "Alt+Down"]),
+    (Action::WindowScale50, &["Alt+0"]),
+    (Action::WindowScale100, &["Alt+1"]),
+    (Action::WindowScale200, &["Alt+2"]),
+    (Action::TimerDecrease, &["LBracket"]),
+    (Action::TimerIncrease, &["RBracket"]),
+    (Action::TimerReset, &["Back"]),
+    (Action::CycleFitMode, &["A"]),
+    (Action::RotateCw, &["R"]),
+    (Action::RotateCcw, &["Shift+R"]),
+    (Action::ToggleZoomMode, &["Z"]),
+    (Action::ToggleInfo, &["I"]),
+    (Action::ToggleDirection, &["V"]),
+    (Action::ToggleTransition, &["X"]),
+    (Action::NextTransitionEffect, &["Tab"]),
+    (Action::Trash, &["Delete"]),
+    (Action::ToggleGrid, &["G"]),
+    (Action::SaveImage, &["Ctrl+S"]),
+    (Action::CopyPath, &["Ctrl+C"]),
+    (Action::CopyImage, &["Ctrl+Shift+C"]),
+    (
+        Action::BrightnessIncrease,
+        &["Plus", "Equals", "Shift+Equals"],
+    ),
+    (Action::BrightnessDecrease, &["Minus"]),
+    (
+        Action::ContrastIncrease,
+        &["Ctrl+Plus", "Ctrl+Equals", "Ctrl+Shift+Equals"],
+    ),
+    (Action::ContrastDecrease, &["Ctrl+Minus"]),
+    (
+        Action::GammaIncrease,
+        &["Alt+Plus", "Alt+Equals", "Alt+Shift+Equals"],
+    ),
+    (Action::GammaDecrease, &["Alt+Minus"]),
+    (Action::ResetAdjustments, &["0"]),
+    (Action::ToggleLockAdjustments, &["K"]),
+    (Action::FlipHorizontal, &["H"]),
+    (Action::FlipVertical, &["Shift+H"]),
+    (Action::NextMonitor, &["M"]),
+    (Action::ToggleHistogram, &["Shift+I"]),
+    (Action::GoTo, &["Slash", "Colon", "Shift+Semicolon"]),
+    (Action::Screenshot, &["F12"]),
+    (Action::Blank, &["B"]),
+    (Action::BlankWhite, &["W"]),
+    (Action::ToggleFavorite, &["S"]),
+    (Action::CopyFavorites, &["Shift+S"]),
+    (Action::ToggleHelp, &["F1", "Shift+Slash"]),
+    (Action::PinCompare, &["C"]),
+    (Action::ExitCompare, &["Shift+C"]),
+    (Action::RotateFineCw, &["Ctrl+Right"]),
+    (Action::RotateFineCcw, &["Ctrl+Left"]),
+    (Action::ResetRotation, &["Ctrl+0"]),
+    (Action::RevealFile, &["E"]),
+    (Action::ToggleFilmstrip, &["Shift+G"]),
+    (Action::SetWallpaper, &["Ctrl+W"]),
+    (Action::ReloadImage, &["F5"]),
+];
+
+impl Action {
+    pub fn from_name(name: &str) -> Option<Self> {
+        ACTION_NAMES
+            .iter()
+            .find(|(_, v)| v.eq_ignore_ascii_case(name))
+            .map(|(action, _)| *action)
+    }
+
+    pub fn name(self) -> &'static str {
+        ACTION_NAMES
+            .iter()
+            .find(|(action, _)| *action == self)
+            .map(|(_, name)| *name)
+            .unwrap_or("unknown")
+    }
+
+    /// Return true if the action runs on the key press and repeats while holding the key,
+    /// other actions run on the key release
+    pub fn is_repeatable(self) -> bool {
+        matches!(
+            self,
+            Action::TimerDecrease
+                | Action::TimerIncrease
+                | Action::BrightnessIncrease
+                | Action::BrightnessDecrease
+                | Action::ContrastIncrease
+                | Action::ContrastDecrease
+                | Action::GammaIncrease
+                | Action::GammaDecrease
+                | Action::RotateFineCw
+                | Action::RotateFineCcw
+        )
+    }
+}
+
+/// A key with the modifiers, e.g. "Ctrl+Shift+Right"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyCombo {
+    pub key: VirtualKeyCode,
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl KeyCombo {
+    pub fn new(key: VirtualKeyCode, modifiers: ModifiersState) -> Self {
+        Self {
+            key,
+            ctrl: modifiers.ctrl(),
+            shift: modifiers.shift(),
+            alt: modifiers.alt(),
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        // "+" and "Ctrl++" are the plus key
+        let (modifier_names, key_name) = if s == "+" {
+            ("", s)
+        } else if let Some(rest) = s.strip_suffix("++") {
+            (rest, "+")
+        } else {
+            s.rsplit_once('+').unwrap_or(("", s))
+        };
+
+        let mut modifiers = ModifiersState::empty();
+        for modifier_name in modifier_names.split('+').map(|v| v.trim()) {
+            match modifier_name.to_ascii_lowercase().as_str() {
+                "" => {}
+                "ctrl" | "control" => modifiers |= ModifiersState::CTRL,
+                "shift" => modifiers |= ModifiersState::SHIFT,
+                "alt" => modifiers |= ModifiersState::ALT,
+                _ => return None,
+            }
+        }
+
+        Some(Self::new(parse_key_name(key_name.trim())?, modifiers))
+    }
+}
+
+impl fmt::Display for KeyCombo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.shift {
+            write!(f, "Shift+")?;
+        }
+        if self.alt {
+            write!(f, "Alt+")?;
+        }
+        write!(f, "{:?}", self.key)
+    }
+}
+
+/// Default numbers of the mouse side buttons, winit reports them differently
+/// on Windows, macOS, X11 and Wayland
+const DEFAULT_MOUSE_BACK: [u16; 4] = [1, 3, 8, 275];
+const DEFAULT_MOUSE_FORWARD: [u16; 4] = [2, 4, 9, 276];
+
+/// Default action of the double-click and the multi-finger taps
+const DEFAULT_GESTURE_ACTION: Action = Action::ToggleFullscreen;
+
+pub struct Keybindings {
+    map: HashMap<KeyCombo, Action>,
+    /// `MouseButton::Other` numbers of the back side button
+    mouse_back: Vec<u16>,
+    /// `MouseButton::Other` numbers of the forward side button
+    mouse_forward: Vec<u16>,
+    /// Actions of the left button double-click and the two and three finger taps, None for no action
+    pub double_click: Option<Action>,
+    pub two_finger_tap: Option<Action>,
+    pub three_finger_tap: Option<Action>,
+}
+
+impl Keybindings {
+    /// Build the key map from the config, the actions not in the config use the default keys
+    pub fn new(conf: &HashMap<String, KeyNames>) -> Self {
+        let mut bindings: Vec<(Action, Vec<&str>)> = DEFAULT_BINDINGS
+            .iter()
+            .map(|(action, keys)| (*action, keys.to_vec()))
+            .collect();
+
+        let mut mouse_back = DEFAULT_MOUSE_BACK.to_vec();
+        let mut mouse_forward = DEFAULT_MOUSE_FORWARD.to_vec();
+        let mut double_click = Some(DEFAULT_GESTURE_ACTION);
+        let mut two_finger_tap = Some(DEFAULT_GESTURE_ACTION);
+        let mut three_finger_tap = Some(DEFAULT_GESTURE_ACTION);
+        let mut overrides: Vec<(Action, Vec<&str>)> = vec![];
+        for (action_name, key_names) in conf {
+            match action_name.as_str() {
+                "mouse_back" => {
+                    mouse_back = key_names.buttons();
+                    continue;
+                }
+                "mouse_forward" => {
+                    mouse_forward = key_names.buttons();
+                    continue;
+                }
+                "double_click" => {
+                    double_click = Self::gesture_action(action_name, key_names, double_click);
+                    continue;
+                }
+                "two_finger_tap" => {
+                    two_finger_tap = Self::gesture_action(action_name, key_names, two_finger_tap);
+                    continue;
+                }
+                "three_finger_tap" => {
+                    three_finger_tap =
+                        Self::gesture_action(action_name, key_names, three_finger_tap);
+                    continue;
+                }
+                _ => (),
+            }
+            match Action::from_name(action_name) {
+                Some(action) => overrides.push((action, key_names.to_vec())),
+                None => log::warn!("Unknown keybinding action: {}", action_name),
+            }
+        }
+        bindings.retain(|(action, _)| !overrides.iter().any(|(v, _)| v == action));
+        // The config keys take priority over the default keys
+        bindings.extend(overrides);
+
+        let mut map = HashMap::new();
+        for (action, key_names) in bindings {
+            for key_name in key_names {
+                match KeyCombo::parse(key_name) {
+                    Some(combo) => {
+                        map.insert(combo, action);
+                    }
+                    None => log::warn!("Unknown key '{}' for '{}'", key_name, action.name()),
+                }
+            }
+        }
+
+        Self {
+            map,
+            mouse_back,
+            mouse_forward,
+            double_click,
+            two_finger_tap,
+            three_finger_tap,
+        }
+    }
+
+    /// Parse the action name of a gesture, 'none' disables the gesture,
+    /// an unknown name keeps the default
+    fn gesture_action(
+        gesture_name: &str,
+        key_names: &KeyNames,
+        default: Option<Action>,
+    ) -> Option<Action> {
+        let name = match key_names.to_vec().as_slice() {
+            [name] => name.trim(),
+            _ => {
+                log::warn!("Expected an action name for '{}'", gesture_name);
+                return default;
+            }
+        };
+        if name.is_empty() || name.eq_ignore_ascii_case("none") {
+            return None;
+        }
+        match Action::from_name(name) {
+            Some(action) => Some(action),
+            None => {
+                log::warn!("Unknown action '{}' for '{}'", name, gesture_name);
+                default
+            }
+        }
+    }
+
+    pub fn is_mouse_back(&self, button: u16) -> bool {
+        self.mouse_back.contains(&button)
+    }
+
+    pub fn is_mouse_forward(&self, button: u16) -> bool {
+        self.mouse_forward.contains(&button)
+    }
+
+    /// Lines of the actions and the bound keys for the help, e.g. "next: Down, Right"
+    pub fn help_lines(&self) -> Vec<String> {
+        ACTION_NAMES
+            .iter()
+            .filter_map(|(action, name)| {
+                let mut keys: Vec<String> = self
+                    .map
+                    .iter()
+                    .filter(|(_, v)| *v == action)
+                    .map(|(combo, _)| combo.to_string())
+                    .collect();
+                // The keys without the modifiers first
+                keys.sort_by_key(|v| (v.len(), v.clone()));
+                let gestures = [
+                    (self.double_click, "DoubleClick"),
+                    (self.two_finger_tap, "TwoFingerTap"),
+                    (self.three_finger_tap, "ThreeFingerTap"),
+                ];
+                keys.extend(
+                    gestures
+                        .iter()
+                        .filter(|(v, _)| v.as_ref() == Some(action))
+                        .map(|(_, gesture)| gesture.to_string()),
+                );
+                if keys.is_empty() {
+                    return None;
+                }
+                Some(format!("{}: {}", name, keys.join(", ")))
+            })
+            .collect()
+    }
+
+    /// Get the action of the key with the modifiers, fall back to the key without them,
+    /// e.g. Alt+Right is the next image unless it is bound
+    pub fn get(&self, key: VirtualKeyCode, modifiers: ModifiersState) -> Option<Action> {
+        self.map
+            .get(&KeyCombo::new(key, modifiers))
+            .or_else(|| self.map.get(&KeyCombo::new(key, ModifiersState::empty())))
+            .copied()
+    }
+}
+
+/// Parse the key name, the names are the same as `winit::event::VirtualKeyCode`
+/// with a few aliases
+pub fn parse_key_name(name: &str) -> Option<VirtualKeyCode> {
+    use VirtualKeyCode::*;
+
+    let key = match name.to_ascii_lowercase().as_str() {
+        "a" => A,
+        "b" => B,
+        "c" => C,
+        "d" => D,
+        "e" => E,
+        "f" => F,
+        "g" => G,
+        "h" => H,
+        "i" => I,
+        "j" => J,
+        "k" => K,
+        "l" => L,
+        "m" => M,
+        "n" => N,
+        "o" => O,
+        "p" => P,
+        "q" => Q,
+        "r" => R,
+        "s" => S,
+        "t" => T,
+        "u" => U,
+        "v" => V,
+        "w" => W,
+        "x" => X,
+        "y" => Y,
+        "z" => Z,
+        "0" | "key0" => Key0,
+        "1" | "key1" => Key1,
+        "2" | "key2" => Key2,
+        "3" | "key3" => Key3,
+        "4" | "key4" => Key4,
+        "5" | "key5" => Key5,
+        "6" | "key6" => Key6,
+        "7" | "key7" => Key7,
+        "8" | "key8" => Key8,
+        "9" | "key9" => Key9,
+        "f1" => F1,
+        "f2" => F2,
+        "f3" => F3,
+        "f4" => F4,
+        "f5" => F5,
+        "f6" => F6,
+        "f7" => F7,
+        "f8" => F8,
+        "f9" => F9,
+        "f10" => F10,
+        "f11" => F11,
+        "f12" => F12,
+        "numpad0" => Numpad0,
+        "numpad1" => Numpad1,
+        "numpad2" => Numpad2,
+        "numpad3" => Numpad3,
+        "numpad4" => Numpad4,
+        "numpad5" => Numpad5,
+        "numpad6" => Numpad6,
+        "numpad7" => Numpad7,
+        "numpad8" => Numpad8,
+        "numpad9" => Numpad9,
+        "left" => Left,
+        "right" => Right,
+        "up" => Up,
+        "down" => Down,
+        "home" => Home,
+        "end" => End,
+        "pageup" => PageUp,
+        "pagedown" => PageDown,
+        "insert" => Insert,
+        "delete" => Delete,
+        "back" | "backspace" => Back,
+        "return" | "enter" => Return,
+        "escape" | "esc" => Escape,
+        "space" => Space,
+        "tab" => Tab,
+        "pause" => Pause,
+        "snapshot" | "printscreen" => Snapshot,
+        "," | "comma" => Comma,
+        "." | "period" => Period,
+        "[" | "lbracket" => LBracket,
+        "]" | "rbracket" => RBracket,
+        "/" | "slash" => Slash,
+        "\\" | "backslash" => Backslash,
+        ";" | "semicolon" => Semicolon,
+        ":" | "colon" => Colon,
+        "'" | "apostrophe" => Apostrophe,
+        "`" | "grave" => Grave,
+        "-" | "minus" => Minus,
+        "=" | "equals" => Equals,
+        "+" | "plus" => Plus,
+        "numpadenter" => NumpadEnter,
+        _ => return None,
+    };
+
+    Some(key)
+}
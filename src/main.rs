@@ -1,43 +1,91 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // Hide console window at Windows
 
+mod capture;
+mod color_profile;
 mod config;
+mod disk_cache;
+mod favorites;
+mod filmstrip;
+#[cfg(feature = "gamepad")]
+mod gamepad;
+mod gpu_resize;
+mod histogram;
 mod image_loader;
+mod keybindings;
 mod logger;
+mod multi_image;
+mod overlay;
+mod readback;
+mod recent;
+mod remote_control;
+mod resume;
+mod sound;
 mod state;
 mod texture;
+mod transition;
 mod utils;
+mod wallpaper;
+mod window_geometry;
 
 #[cfg(windows)]
 mod common_win32;
 
-use crate::image_loader::{ImageCache, ImageLoader, Size2d};
+use crate::disk_cache::DiskCache;
+use crate::favorites::Favorites;
+use crate::gpu_resize::GpuResizer;
+use crate::image_loader::{
+    is_remote_path, read_capture_times, spawn_scan, DecodeOptions, ImageLoader, Size2d,
+};
+use crate::keybindings::{Action, Keybindings};
 use crate::logger::ResultLogging;
-use crate::state::{FullscreenController, State};
+use crate::recent::RecentPaths;
+use crate::remote_control::RemoteCommand;
+use crate::resume::ResumeState;
+use crate::state::{
+    AspectLock, FullscreenController, State, PIXEL_SHIFT_BLANK_DURATION, PIXEL_SHIFT_INTERVAL,
+};
 use crate::utils::*;
+use crate::window_geometry::WindowGeometry;
 use anyhow::Result;
 use futures::executor::block_on;
 use image::ImageFormat;
+use notify::{DebouncedEvent, RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
 use std::io::Cursor;
 use std::path::PathBuf;
 use std::rc::Rc;
 use std::sync::{mpsc, Arc, Mutex};
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 use winit::{
     dpi::{PhysicalPosition, PhysicalSize},
     event::{Event, KeyboardInput, WindowEvent},
-    event_loop::{ControlFlow, EventLoop},
+    event_loop::{ControlFlow, EventLoop, EventLoopProxy},
     window::WindowBuilder,
 };
 
 const APP_NAME: &str = "sldshow";
 
 const CURSOR_SLEEP_START_TIME: u64 = 3;
-const OSD_MESSAGE_DISPLAY_TIME: u64 = 3;
-const FILE_DROP_TIMEOUT: f32 = 0.5;
+const MIN_OSD_MESSAGE_DISPLAY_TIME: f32 = 0.1;
 const TIMER_VALUE_INCREMENT: u32 = 5;
 const FULLSCREEN_CHANGE_INTERVAL: Duration = Duration::from_millis(300);
 const MULTITOUCH_INTERVAL: Duration = Duration::from_millis(50);
 const TOUCH_DRAG_START_DISTANCE: f64 = 5.0;
+const ZOOM_STEP: f32 = 1.25;
+const BRIGHTNESS_STEP: f32 = 0.05;
+const CONTRAST_STEP: f32 = 0.1;
+const GAMMA_STEP: f32 = 0.1;
+const ROTATION_STEP_DEG: f32 = 0.5;
+const FOLDER_WATCH_DEBOUNCE: Duration = Duration::from_millis(500);
+const FOLDER_WATCH_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+const RESUME_SAVE_INTERVAL: Duration = Duration::from_secs(30);
+const CAPTURE_TIME_PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+const DELETE_CONFIRM_INTERVAL: Duration = Duration::from_millis(1500);
+const TEXTURE_RESIZE_DEBOUNCE: Duration = Duration::from_millis(500);
+const FILMSTRIP_IDLE_TIME: Duration = Duration::from_secs(3);
+const FILMSTRIP_FADE_DURATION: Duration = Duration::from_millis(500);
+const FILMSTRIP_FADE_STEPS: u32 = 15;
+const DIM_SCHEDULE_INTERVAL: Duration = Duration::from_secs(60);
 
 pub const SUPPORTED_IMAGE_FORMATS: [ImageFormat; 12] = [
     ImageFormat::Png,
@@ -58,11 +106,38 @@ pub const SUPPORTED_IMAGE_FORMATS: [ImageFormat; 12] = [
 #[derive(Debug)]
 pub enum CustomEvent {
     NextImage,
+    /// The image of the navigation waiting for the preload is decoded, or the wait is long
+    RetryNextImage,
+    NextFrame,
+    KenBurnsUpdate,
+    ThumbnailLoaded,
+    /// An image of the current contact sheet page is decoded
+    SheetCellLoaded,
     TransitionStart,
     TransitionUpdate,
     MouseCursorSleep,
     MouseCursorAwake,
+    /// Opacity of the filmstrip fading out while the cursor is idle
+    FilmstripFade(f32),
+    /// No user input for `idle_exit_secs`
+    IdleExit,
+    PixelShift,
+    /// Minute tick of the dim schedule
+    DimScheduleUpdate,
+    PixelShiftBlankEnd,
     ClearOsdMessage,
+    GapEnd,
+    /// Resize the texture to the window after the window stops resizing
+    TextureResize,
+    /// Number of the capture times read and the total
+    CaptureTimeProgress(usize, usize),
+    /// Number of the images found by the background scan
+    ScanProgress(usize),
+    ScanFinished,
+    CaptureTimesLoaded(HashMap<PathBuf, Option<SystemTime>>),
+    RemoteCommand(RemoteCommand),
+    /// Action from an input other than the window, e.g. a gamepad
+    Action(Action),
 }
 
 #[derive(Debug)]
@@ -79,6 +154,14 @@ enum DragState {
     Dragging,
 }
 
+/// Keyboard input mode, the keys don't trigger the actions while typing
+#[derive(Debug, PartialEq)]
+enum InputMode {
+    Normal,
+    /// Position or percentage to jump to
+    GoTo(String),
+}
+
 #[derive(Debug)]
 enum Nav {
     None,
@@ -95,20 +178,101 @@ fn main() -> Result<()> {
         eprintln!("logger init failed: {}", err);
     }
 
+    // Command-line arguments > config file > defaults
+    // Config errors keep the defaults and are shown at startup
     let conf_path = get_config_file_path();
-    let conf = conf_path
-        .as_ref()
-        .and_then(|p| config::get_config(p).ok())
-        .unwrap_or_default();
+    let mut config_messages = Vec::new();
+    let mut conf = match conf_path.as_ref().map(|p| config::get_config(p)) {
+        Some(Ok(conf)) => conf,
+        Some(Err(err)) => {
+            log::error!("{}", err);
+            config_messages.push(format!("Config error, using the defaults\n{}", err));
+            config::Config::default()
+        }
+        None => config::Config::default(),
+    };
+    let arg_overrides = config::ArgOverrides::parse(std::env::args().skip(1));
+    arg_overrides.apply(&mut conf);
+    for warning in conf.validate() {
+        log::warn!("{}", warning);
+        config_messages.push(warning);
+    }
+
+    if arg_overrides.check_config {
+        #[cfg(windows)]
+        common_win32::attach_parent_console();
+        match &conf_path {
+            Some(path) => println!("Config: {}", path.display()),
+            None => println!("Config: none, using the defaults"),
+        }
+        for message in &config_messages {
+            eprintln!("{}", message);
+        }
+        std::process::exit(if config_messages.is_empty() { 0 } else { 1 });
+    }
+
+    // Kiosk mode locks the window in fullscreen
+    if conf.viewer.kiosk {
+        conf.window.fullscreen = true;
+        conf.window.titlebar = false;
+    }
 
     log::info!("{:#?}", conf);
 
+    // Resume file is placed next to the config file
+    let resume_file = if conf.viewer.resume {
+        conf_path.as_ref().map(|p| {
+            let p = std::fs::canonicalize(p).unwrap_or_else(|_| p.clone());
+            (
+                resume::get_resume_file_path(&p),
+                resume::hash_config_path(&p),
+            )
+        })
+    } else {
+        None
+    };
+
+    // Recently opened folders are placed next to the config file
+    let mut recent_paths = if conf.viewer.reopen_last {
+        config_sidecar_path(conf_path.as_deref(), recent::RECENT_FILE_EXTENSION)
+            .map(RecentPaths::load)
+    } else {
+        None
+    };
+
+    // Screenshots are saved next to the config file
+    let screenshot_dir = conf_path
+        .as_ref()
+        .and_then(|p| std::fs::canonicalize(p).ok())
+        .and_then(|p| p.parent().map(|v| v.to_path_buf()))
+        .or_else(dirs::home_dir)
+        .unwrap_or_else(|| PathBuf::from("."));
+
+    // Favorites are listed next to the config file, copied into 'favorites' there by default
+    let mut favorites =
+        config_sidecar_path(conf_path.as_deref(), favorites::FAVORITES_FILE_EXTENSION)
+            .map(Favorites::load);
+    let favorites_dir = match &conf.viewer.favorites_dir {
+        Some(dir) => PathBuf::from(dir),
+        None => screenshot_dir.join("favorites"),
+    };
+
     // Change the current working directory to the location of the config file
     // to support loading relative image paths
     if let Some(conf_dir) = conf_path.as_ref().and_then(|p| p.parent()) {
         std::env::set_current_dir(conf_dir).log_info();
     }
 
+    // Window geometry is placed next to the config file
+    let geometry_file = if conf.window.restore_geometry {
+        config_sidecar_path(
+            conf_path.as_deref(),
+            window_geometry::GEOMETRY_FILE_EXTENSION,
+        )
+    } else {
+        None
+    };
+
     let window_size = Size2d {
         width: conf.window.width,
         height: conf.window.height,
@@ -143,27 +307,64 @@ fn main() -> Result<()> {
 
     // Create main window
     let event_loop: EventLoop<CustomEvent> = EventLoop::with_user_event();
+
+    // Skip the saved geometry if the monitor is disconnected
+    let saved_geometry = geometry_file.as_ref().and_then(|path| {
+        let geometry = WindowGeometry::load(path).ok()?;
+        if geometry.is_on_monitors(event_loop.available_monitors()) {
+            Some(geometry)
+        } else {
+            log::warn!("saved window position is out of the monitors");
+            None
+        }
+    });
+    let window_inner_size = match &saved_geometry {
+        Some(geometry) => geometry.size(),
+        None => PhysicalSize::from(window_size),
+    };
+
     let builder = WindowBuilder::new()
         .with_title(window_title)
         .with_window_icon(icon)
-        .with_inner_size(PhysicalSize::from(window_size))
+        .with_inner_size(window_inner_size)
         .with_always_on_top(conf.window.always_on_top)
         .with_transparent(conf.style.bg_color[3] < 255)
         .with_resizable(conf.window.resizable)
         .with_decorations(conf.window.titlebar);
     let main_window = Rc::new(builder.build(&event_loop)?);
-    let inner_size = Size2d::from(main_window.inner_size());
-    let mut texture_size = inner_size;
+    let mut texture_size = match conf.viewer.texture_target {
+        config::TextureTarget::Window | config::TextureTarget::FollowWindow => {
+            Size2d::from(main_window.inner_size())
+        }
+        config::TextureTarget::Monitor => main_window
+            .available_monitors()
+            .nth(conf.window.monitor_index)
+            .or_else(|| main_window.primary_monitor())
+            .map(|monitor| Size2d::from(monitor.size()))
+            .unwrap_or_else(|| Size2d::from(main_window.inner_size())),
+    };
     texture_size.scale_factor = main_window.scale_factor().into();
 
     // Set main window position
-    if let Some(target_monitor) = main_window
+    let mut startup_messages = config_messages;
+    if let Some(geometry) = &saved_geometry {
+        main_window.set_outer_position(geometry.position());
+    } else if let Some(target_monitor) = main_window
         .available_monitors()
         .nth(conf.window.monitor_index)
     {
         set_window_to_center(&main_window, &target_monitor);
-    } else if let Some(primary_monitor) = main_window.primary_monitor() {
-        set_window_to_center(&main_window, &primary_monitor);
+    } else {
+        let message = format!(
+            "monitor_index {} is out of range, {} monitors detected",
+            conf.window.monitor_index,
+            main_window.available_monitors().count()
+        );
+        log::warn!("{}", message);
+        startup_messages.push(message);
+        if let Some(primary_monitor) = main_window.primary_monitor() {
+            set_window_to_center(&main_window, &primary_monitor);
+        }
     }
 
     let mut fullscreen_controller = FullscreenController {
@@ -172,6 +373,9 @@ fn main() -> Result<()> {
         last_time: Instant::now(),
         rate_limit: FULLSCREEN_CHANGE_INTERVAL,
         window: main_window.clone(),
+        span: conf.window.fullscreen_span,
+        decorations: conf.window.titlebar,
+        restore_rect: None,
     };
     if conf.window.fullscreen {
         fullscreen_controller.enable();
@@ -180,26 +384,103 @@ fn main() -> Result<()> {
     // Create ImageLoader
     let image_loader = Arc::new(Mutex::new(ImageLoader::new(
         conf.viewer.scan_subfolders,
+        conf.viewer.sort,
         texture_size,
         resize_filter,
+        conf.viewer.fit_mode,
+        conf.viewer.no_upscale,
         conf.viewer.cache_extent,
     )));
 
     // Scan image paths
+    // The seed from args enables shuffle, otherwise generate a new seed to show it
+    let arg_shuffle_seed = get_arg_shuffle_seed();
+    let shuffle = conf.viewer.shuffle || conf.viewer.sort == config::SortOrder::Random;
+    let shuffle_seed = if shuffle || arg_shuffle_seed.is_some() {
+        let seed = arg_shuffle_seed.or(conf.viewer.shuffle_seed);
+        Some(seed.unwrap_or_else(rand::random))
+    } else {
+        None
+    };
     {
-        let input_paths: Vec<_> = conf.viewer.image_paths.iter().map(PathBuf::from).collect();
+        let mut input_paths: Vec<_> = conf.viewer.image_paths.iter().map(PathBuf::from).collect();
+        if let Some(recent_paths) = &mut recent_paths {
+            if input_paths.is_empty() {
+                if let Some(path) = recent_paths.most_recent() {
+                    log::info!("reopen the last folder: {}", path.display());
+                    input_paths.push(path.clone());
+                }
+            }
+            for path in &input_paths {
+                recent_paths.add(path);
+            }
+            recent_paths.save().log_err();
+        }
+
         let mut loader = image_loader.lock().unwrap();
-        loader.scan_input_paths(&input_paths);
-        if conf.viewer.shuffle {
-            loader.shuffle_paths();
+        loader.set_path_filters(&conf.viewer.include, &conf.viewer.exclude);
+        loader.downscale_decode = conf.viewer.downscale_decode;
+        loader.exif_orientation = conf.viewer.use_exif_orientation;
+        if let Some([columns, rows]) = conf.viewer.grid {
+            loader.set_contact_sheet(columns, rows);
         }
+        loader.color_management =
+            conf.viewer.color_management && cfg!(feature = "color_management");
+        if conf.viewer.color_management && !loader.color_management {
+            log::warn!("color_management requires the build with the 'color_management' feature");
+        }
+        loader.natural_sort = conf.viewer.natural_sort;
+        loader.case_insensitive_sort = conf.viewer.case_insensitive_sort;
+        loader.wrap = conf.viewer.wrap;
+        loader.reshuffle_each_loop = conf.viewer.reshuffle_each_loop && shuffle_seed.is_some();
+        loader.max_cache_bytes = conf.viewer.cache_max_bytes;
+        loader.max_depth = conf.viewer.max_depth;
+        loader.follow_symlinks = conf.viewer.follow_symlinks;
+        loader.tiff_pages = conf.viewer.tiff_pages;
+        loader.preload_bias = conf.viewer.preload_bias;
+        if conf.viewer.disk_cache {
+            let max_bytes = conf.viewer.disk_cache_max_mb * 1024 * 1024;
+            loader.disk_cache = DiskCache::default_dir()
+                .ok_or_else(|| anyhow::anyhow!("no cache folder for the disk cache"))
+                .and_then(|dir| DiskCache::new(dir, max_bytes))
+                .map(Arc::new)
+                .map_err(|err| log::warn!("disk cache disabled: {}", err))
+                .ok();
+        }
+        drop(loader);
+
+        // Scan in the background not to block the window on large folders
+        spawn_scan(
+            image_loader.clone(),
+            input_paths,
+            shuffle_seed,
+            event_loop.create_proxy(),
+        );
     }
 
+    // Restore the last position
+    let mut resume_state = resume_file.as_ref().and_then(|(path, config_hash)| {
+        if !path.is_file() {
+            return None;
+        }
+        match ResumeState::load(path) {
+            Ok(v) if &v.config_hash == config_hash => Some(v),
+            Ok(_) => None,
+            Err(err) => {
+                log::warn!("failed to load the resume file: {}", err);
+                None
+            }
+        }
+    });
     // Create channels for message passing
     let (tx_slideshow_timer, rx_slideshow_timer) = mpsc::channel::<TimerState>();
     let (tx_osd_message_timer, rx_osd_message_timer) = mpsc::channel::<()>();
     let (tx_mouse_cursor_watcher, rx_mouse_cursor_watcher) = mpsc::channel::<()>();
+    let (tx_filmstrip_activity, rx_filmstrip_activity) = mpsc::channel::<()>();
     let (tx_transition_throttle, rx_transition_throttle) = mpsc::channel::<Instant>();
+    let (tx_animation_timer, rx_animation_timer) = mpsc::channel::<Option<Duration>>();
+    let (tx_gap_timer, rx_gap_timer) = mpsc::channel::<Option<Duration>>();
+    let (tx_ken_burns_timer, rx_ken_burns_timer) = mpsc::channel::<bool>();
 
     // Create main application state
     let mut state = block_on(State::new(
@@ -209,9 +490,31 @@ fn main() -> Result<()> {
         fullscreen_controller,
         tx_slideshow_timer,
         tx_osd_message_timer,
+        tx_animation_timer,
+        tx_gap_timer,
+        tx_ken_burns_timer,
         event_loop.create_proxy(),
     ))?;
 
+    if let Some(seed) = shuffle_seed {
+        log::info!("shuffle seed: {}", seed);
+        startup_messages.push(format!("Shuffle seed: {}", seed));
+    }
+    if !startup_messages.is_empty() {
+        state.graphics.update_message(&startup_messages.join("\n"));
+    }
+
+    if let Some(resume_state) = &resume_state {
+        state.paused = resume_state.paused;
+        state.pause_at_last = resume_state.pause_at_last;
+        state.change_timer(resume_state.current_timer_secs);
+    }
+    let mut last_resume_save_time = Instant::now();
+
+    let loop_count = conf.viewer.loop_count;
+    let on_finish = conf.viewer.on_finish;
+    let mut loops_played = 0;
+
     // Window states
     let mut always_on_top = conf.window.always_on_top;
     let mut titlebar = conf.window.titlebar;
@@ -219,23 +522,44 @@ fn main() -> Result<()> {
     // Input states
     let double_click_duration = get_double_click_duration();
     let mut last_mouse_left_pressed_time = Instant::now();
+    let mut logged_mouse_buttons = HashSet::new();
     let mut last_touch_pressed_time = Instant::now();
     let mut touch_finger_count = 0;
     let mut last_touch_finger_count = touch_finger_count;
     let mut last_touch_finger_id = 0;
     let mut multifinger_touch = false;
+    let mut tap_finger_count = 0;
     let mut drag_finger = false;
     let mut drag_state = DragState::None;
     let mut drag_pos: Option<PhysicalPosition<f64>> = None;
-    let mut last_file_drop_event_time = Instant::now();
+    let mut cursor_pos = PhysicalPosition::new(0.0, 0.0);
+    let mut dropped_paths: Vec<PathBuf> = Vec::new();
+    let mut last_delete_pressed_time: Option<Instant> = None;
     let mut modifiers_state = winit::event::ModifiersState::default();
+    let mut input_mode = InputMode::Normal;
+    let lock_aspect = conf.window.lock_aspect;
+    let texture_on_monitor = conf.viewer.texture_target == config::TextureTarget::Monitor;
+    let texture_follows_window = conf.viewer.texture_target == config::TextureTarget::FollowWindow;
+    let reload_on_focus = conf.viewer.reload_on_focus;
+    let kiosk = conf.viewer.kiosk;
+    let mut last_resize_time = Instant::now();
+    let mut texture_resize_pending = false;
+    let watch_folder = conf.viewer.watch_folder;
+    let capture_time_sort =
+        conf.viewer.sort == config::SortOrder::CaptureTime && shuffle_seed.is_none();
+    let mut aspect_lock = AspectLock::default();
+    let keybindings = Keybindings::new(&conf.keybindings);
 
     //---------
     // Threads
     //---------
 
     // Slideshow timer
-    let timer = conf.viewer.timer;
+    let timer = if conf.viewer.manual {
+        0
+    } else {
+        conf.viewer.timer
+    };
     let proxy = event_loop.create_proxy();
     std::thread::spawn(move || {
         let mut dur = Duration::from_secs(timer as u64);
@@ -271,12 +595,15 @@ fn main() -> Result<()> {
 
     // OSD display timer
     let proxy = event_loop.create_proxy();
+    let osd_duration = Duration::from_secs_f32(
+        conf.style
+            .osd_duration_secs
+            .max(MIN_OSD_MESSAGE_DISPLAY_TIME),
+    );
     std::thread::spawn(move || {
-        const DURATION: Duration = Duration::from_secs(OSD_MESSAGE_DISPLAY_TIME);
-
         loop {
             if let Err(mpsc::RecvTimeoutError::Timeout) =
-                rx_osd_message_timer.recv_timeout(DURATION)
+                rx_osd_message_timer.recv_timeout(osd_duration)
             {
                 // Wait completed
                 proxy.send_event(CustomEvent::ClearOsdMessage).log_err()
@@ -284,8 +611,81 @@ fn main() -> Result<()> {
         }
     });
 
-    // Mouse cursor autohide timer
-    if conf.window.cursor_auto_hide {
+    // Animation frame timer
+    let proxy = event_loop.create_proxy();
+    std::thread::spawn(move || {
+        let mut delay: Option<Duration> = None;
+
+        loop {
+            let recv = match delay {
+                Some(dur) => rx_animation_timer.recv_timeout(dur),
+                None => rx_animation_timer
+                    .recv()
+                    .map_err(|_| mpsc::RecvTimeoutError::Disconnected),
+            };
+            match recv {
+                Ok(new_delay) => delay = new_delay,
+                // Wait completed, wait for the next frame delay
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    proxy.send_event(CustomEvent::NextFrame).log_err();
+                    delay = None;
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+        }
+    });
+
+    // Gap timer between the images
+    let proxy = event_loop.create_proxy();
+    std::thread::spawn(move || {
+        let mut delay: Option<Duration> = None;
+
+        loop {
+            let recv = match delay {
+                Some(dur) => rx_gap_timer.recv_timeout(dur),
+                None => rx_gap_timer
+                    .recv()
+                    .map_err(|_| mpsc::RecvTimeoutError::Disconnected),
+            };
+            match recv {
+                Ok(new_delay) => delay = new_delay,
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    proxy.send_event(CustomEvent::GapEnd).log_err();
+                    delay = None;
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+        }
+    });
+
+    // Ken Burns effect timer
+    let proxy = event_loop.create_proxy();
+    let fps = conf.transition.fps;
+    std::thread::spawn(move || {
+        let dur = Duration::from_secs_f32(1.0 / fps.max(1.0));
+        let mut active = false;
+
+        loop {
+            let recv = if active {
+                rx_ken_burns_timer.recv_timeout(dur)
+            } else {
+                rx_ken_burns_timer
+                    .recv()
+                    .map_err(|_| mpsc::RecvTimeoutError::Disconnected)
+            };
+            match recv {
+                Ok(v) => active = v,
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    proxy.send_event(CustomEvent::KenBurnsUpdate).log_err();
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+        }
+    });
+
+    // Mouse cursor autohide and activity timer
+    let cursor_auto_hide = conf.window.cursor_auto_hide;
+    if cursor_auto_hide || conf.viewer.pause_on_activity {
         let proxy = event_loop.create_proxy();
         std::thread::spawn(move || {
             let dur = Duration::from_secs(CURSOR_SLEEP_START_TIME);
@@ -313,6 +713,86 @@ fn main() -> Result<()> {
         });
     }
 
+    // Filmstrip fade out after the cursor stops
+    let proxy = event_loop.create_proxy();
+    std::thread::spawn(move || {
+        while rx_filmstrip_activity.recv().is_ok() {
+            loop {
+                match rx_filmstrip_activity.recv_timeout(FILMSTRIP_IDLE_TIME) {
+                    Ok(_) => continue,
+                    Err(mpsc::RecvTimeoutError::Timeout) => (),
+                    Err(mpsc::RecvTimeoutError::Disconnected) => return,
+                }
+
+                // Stop fading when the cursor moves again
+                let mut awake = false;
+                for step in 1..=FILMSTRIP_FADE_STEPS {
+                    std::thread::sleep(FILMSTRIP_FADE_DURATION / FILMSTRIP_FADE_STEPS);
+                    if rx_filmstrip_activity.try_recv().is_ok() {
+                        awake = true;
+                        break;
+                    }
+                    let opacity = 1.0 - step as f32 / FILMSTRIP_FADE_STEPS as f32;
+                    proxy
+                        .send_event(CustomEvent::FilmstripFade(opacity))
+                        .log_err();
+                }
+                if !awake {
+                    break;
+                }
+            }
+        }
+    });
+
+    // Burn-in protection of the paused image
+    if conf.viewer.pixel_shift {
+        let proxy = event_loop.create_proxy();
+        std::thread::spawn(move || loop {
+            std::thread::sleep(PIXEL_SHIFT_INTERVAL);
+            if proxy.send_event(CustomEvent::PixelShift).is_err() {
+                break;
+            }
+        });
+    }
+
+    // Dim the image in the scheduled hours
+    let dim_schedule = conf.style.dim_schedule.clone();
+    if dim_schedule.is_some() {
+        let proxy = event_loop.create_proxy();
+        std::thread::spawn(move || loop {
+            if proxy.send_event(CustomEvent::DimScheduleUpdate).is_err() {
+                break;
+            }
+            std::thread::sleep(DIM_SCHEDULE_INTERVAL);
+        });
+    }
+
+    // Quit after no user input, the slideshow's own advance doesn't count
+    let idle_exit_secs = conf.viewer.idle_exit_secs.filter(|v| *v > 0);
+    let tx_idle_watcher = idle_exit_secs.map(|secs| {
+        let (tx, rx) = mpsc::channel::<()>();
+        let proxy = event_loop.create_proxy();
+        std::thread::spawn(move || {
+            let dur = Duration::from_secs(secs as u64);
+            loop {
+                match rx.recv_timeout(dur) {
+                    Ok(_) => (),
+                    Err(mpsc::RecvTimeoutError::Timeout) => {
+                        proxy.send_event(CustomEvent::IdleExit).log_err();
+                        break;
+                    }
+                    Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                }
+            }
+        });
+        tx
+    });
+    let reset_idle_timer = move || {
+        if let Some(tx) = &tx_idle_watcher {
+            tx.send(()).log_err();
+        }
+    };
+
     // Fps throttling for the transition effect
     let proxy = event_loop.create_proxy();
     let fps = conf.transition.fps;
@@ -333,75 +813,139 @@ fn main() -> Result<()> {
         }
     });
 
-    // Image loader thread
-    std::thread::spawn(move || {
-        let dur = Duration::from_millis(100);
-        let texture_size = &texture_size.clone();
-        let mut idx: usize;
-        let mut load_needed: bool;
-        let mut prev_load_needed: bool = false;
-        let mut path: Option<PathBuf>;
+    // Remote control server
+    if let Some(port) = conf.viewer.control_port {
+        remote_control::spawn_server(
+            &conf.viewer.control_address,
+            port,
+            event_loop.create_proxy(),
+        )
+        .log_err();
+    }
 
-        loop {
-            // dequeue
-            {
-                let mut loader = image_loader.lock().unwrap();
-                match loader.preload_queue.pop_front() {
-                    Some(index) => {
-                        idx = index;
-                        load_needed = !loader.cache.contains_key(&index);
-                        path = Some(loader.scanned_paths.get(index).unwrap().to_path_buf());
-                    }
-                    None => {
-                        idx = 0;
-                        load_needed = false;
-                        path = None
+    // Gamepad input
+    #[cfg(feature = "gamepad")]
+    gamepad::spawn_gamepad_thread(event_loop.create_proxy());
+
+    // Image decode workers, each takes the next image of the preload queue,
+    // no more workers than the images to preload on either side
+    let decode_threads = match conf.viewer.decode_threads {
+        0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    }
+    .clamp(1, conf.viewer.cache_extent.max(1));
+    log::info!("image decode threads: {}", decode_threads);
+    for _ in 0..decode_threads {
+        let image_loader = image_loader.clone();
+        let proxy = event_loop.create_proxy();
+        std::thread::spawn(move || {
+            let dur = Duration::from_millis(100);
+            let mut idx: usize;
+            let mut load_needed: bool;
+            let mut path: Option<PathBuf>;
+            let mut options: DecodeOptions;
+            let mut gpu_resizer: Option<Arc<GpuResizer>>;
+            let mut disk_cache: Option<Arc<DiskCache>>;
+
+            loop {
+                // dequeue
+                {
+                    let mut loader = image_loader.lock().unwrap();
+                    // Skip the images cached or being decoded by another worker,
+                    // stop at the first image over the byte budget
+                    let next = loop {
+                        match loader.preload_queue.pop_front() {
+                            Some(index)
+                                if loader.cache.contains_key(&index)
+                                    || loader.loading.contains(&index) => {}
+                            Some(index) if !loader.fits_cache_budget(&index) => {
+                                loader.preload_queue.push_front(index);
+                                break None;
+                            }
+                            next => break next,
+                        }
+                    };
+                    match next {
+                        Some(index) => {
+                            idx = index;
+                            load_needed = true;
+                            path = Some(loader.scanned_paths.get(index).unwrap().to_path_buf());
+                            loader.loading.insert(index);
+                        }
+                        None => {
+                            idx = 0;
+                            load_needed = false;
+                            path = None
+                        }
                     }
+                    options = loader.decode_options(&idx);
+                    gpu_resizer = loader.gpu_resizer.clone();
+                    disk_cache = loader.disk_cache.clone();
                 }
-            }
 
-            // load image
-            if load_needed {
-                let mut emsg = None;
-                let image = match &path {
-                    Some(path) => {
-                        match ImageLoader::open_and_resize_image(
-                            &idx,
-                            path,
-                            texture_size,
-                            resize_filter,
-                        ) {
-                            Ok(image) => image,
-                            Err(err) => {
-                                log::error!("{}", err);
-                                emsg = Some(err.to_string());
-                                image::RgbaImage::new(1, 1)
+                // load image
+                if load_needed {
+                    let image_cache = ImageLoader::load_image_cache(
+                        &idx,
+                        path,
+                        &options,
+                        gpu_resizer.as_deref(),
+                        disk_cache.as_deref(),
+                    );
+
+                    {
+                        // Drop the result if the paths or settings were changed while decoding
+                        let mut loader = image_loader.lock().unwrap();
+                        loader.loading.remove(&idx);
+                        if loader.scanned_paths.get(idx) == image_cache.path.as_ref()
+                            && loader.decode_options(&idx) == options
+                        {
+                            loader.insert_cache(idx, image_cache).log_err();
+                            // Fill the empty cell of the contact sheet
+                            if loader.page_size > 1 && loader.page_range().contains(&idx) {
+                                proxy.send_event(CustomEvent::SheetCellLoaded).log_err();
                             }
                         }
                     }
-                    None => image::RgbaImage::new(1, 1),
-                };
-
-                {
-                    let mut loader = image_loader.lock().unwrap();
-                    loader.cache.insert(idx, ImageCache { path, image, emsg });
                 }
-            }
 
-            // limit queue size
-            if prev_load_needed && !load_needed {
-                if let Ok(mut loader) = image_loader.lock() {
-                    loader.limit_cache().log_err();
-                }
-            }
+                // Generate a thumbnail for the grid view when there is no image to preload
+                let mut thumbnail_needed = false;
+                if !load_needed {
+                    let request = {
+                        let mut loader = image_loader.lock().unwrap();
+                        loader.thumbnail_queue.pop_front().and_then(|index| {
+                            let path = loader.scanned_paths.get(index)?.clone();
+                            Some((
+                                index,
+                                path,
+                                loader.exif_orientation,
+                                loader.rotation(&index),
+                            ))
+                        })
+                    };
 
-            prev_load_needed = load_needed;
+                    if let Some((index, path, exif_orientation, rotation)) = request {
+                        thumbnail_needed = true;
+                        let thumbnail =
+                            ImageLoader::load_thumbnail(&index, &path, exif_orientation, rotation);
+
+                        let mut loader = image_loader.lock().unwrap();
+                        if loader.scanned_paths.get(index) == Some(&path)
+                            && loader.rotation(&index) == rotation
+                        {
+                            loader.thumbnails.insert(index, thumbnail);
+                            proxy.send_event(CustomEvent::ThumbnailLoaded).log_err();
+                        }
+                    }
+                }
 
-            if !load_needed {
-                std::thread::sleep(dur);
+                if !load_needed && !thumbnail_needed {
+                    std::thread::sleep(dur);
+                }
             }
-        }
-    });
+        });
+    }
 
     //-----------
     // Main Loop
@@ -415,16 +959,174 @@ fn main() -> Result<()> {
 
         *control_flow = ControlFlow::Wait;
 
+        let mut nav = Nav::None;
+        let mut action: Option<Action> = None;
+
         match &event {
             Event::UserEvent(event) => match event {
                 CustomEvent::NextImage => {
-                    if state.pause_at_last && state.image_loader.lock().unwrap().is_last() {
+                    if state.grid.is_some() {
+                        return;
+                    }
+
+                    // Wait for more images at the end while scanning
+                    if state.is_scanning() && state.is_slideshow_end() {
+                        return;
+                    }
+
+                    // Pause at the first image in reverse
+                    if state.pause_at_last && state.is_slideshow_end() {
+                        state.paused = true;
+                        return;
+                    }
+
+                    // Stop at the end of the list without wrapping
+                    if state.is_slideshow_stopped() {
                         state.paused = true;
+                        state.tx_slideshow_timer.send(TimerState::Pause).log_err();
+                        state.graphics.update_message("End of list");
                         return;
                     }
 
-                    state.next_image(1).log_err();
+                    // Count the loops played by the timer, manual navigation is not counted
+                    if state.is_slideshow_end() {
+                        loops_played += 1;
+                        if loop_count > 0 && loops_played >= loop_count {
+                            match on_finish {
+                                config::Finish::Quit => {
+                                    *control_flow = ControlFlow::Exit;
+                                    return;
+                                }
+                                config::Finish::Pause => {
+                                    state.paused = true;
+                                    state.tx_slideshow_timer.send(TimerState::Pause).log_err();
+                                    state.graphics.update_message("Finished");
+                                    loops_played = 0;
+                                    return;
+                                }
+                                config::Finish::Loop => loops_played = 0,
+                            }
+                        }
+                    }
+
+                    // Wait for the animation to finish the first loop
+                    if let Some(playback) = &mut state.animation {
+                        if !playback.played_once {
+                            playback.advance_pending = true;
+                            return;
+                        }
+                    }
+
+                    state.advance_slideshow().log_err();
+                }
+                CustomEvent::RetryNextImage => state.retry_next_image().log_err(),
+                CustomEvent::NextFrame => state.next_frame().log_err(),
+                CustomEvent::KenBurnsUpdate => state.update_ken_burns(),
+                CustomEvent::GapEnd => state.finish_gap().log_err(),
+                CustomEvent::PixelShift => {
+                    if state.shift_pixels() {
+                        let proxy = state.event_proxy.clone();
+                        std::thread::spawn(move || {
+                            std::thread::sleep(PIXEL_SHIFT_BLANK_DURATION);
+                            proxy.send_event(CustomEvent::PixelShiftBlankEnd).log_err();
+                        });
+                    }
+                }
+                CustomEvent::PixelShiftBlankEnd => state.end_pixel_shift_blank(),
+                CustomEvent::DimScheduleUpdate => {
+                    if let Some(schedule) = &dim_schedule {
+                        let factor = schedule.factor_at(local_minute_of_day());
+                        state.graphics.set_dim(factor);
+                    }
+                }
+                CustomEvent::TextureResize => {
+                    // Wait until the drag-resize stops not to decode the images on each step
+                    let elapsed = last_resize_time.elapsed();
+                    if elapsed < TEXTURE_RESIZE_DEBOUNCE {
+                        let proxy = state.event_proxy.clone();
+                        std::thread::spawn(move || {
+                            std::thread::sleep(TEXTURE_RESIZE_DEBOUNCE - elapsed);
+                            proxy.send_event(CustomEvent::TextureResize).log_err();
+                        });
+                    } else {
+                        texture_resize_pending = false;
+                        state.fit_texture_to_window().log_err();
+                    }
+                }
+                CustomEvent::ScanProgress(found) => {
+                    state
+                        .graphics
+                        .update_message(&format!("Scanning… {} found", found));
+                    // Show the first image as soon as it's found
+                    let first_found = {
+                        let loader = state.image_loader.lock().unwrap();
+                        loader.current_path.is_none() && !loader.scanned_paths.is_empty()
+                    };
+                    if first_found {
+                        state.draw_current_image().log_err();
+                    } else if state.grid.is_some() {
+                        state.draw_grid().log_err();
+                    }
+                }
+                CustomEvent::ScanFinished => {
+                    let count = {
+                        let mut loader = state.image_loader.lock().unwrap();
+                        // Keep the image if the user already moved, only the first scan resumes
+                        if loader.current_index == 0 {
+                            if let Some(resume_state) = resume_state.take() {
+                                if let Some(index) =
+                                    resume_state.restore_index(&loader.scanned_paths)
+                                {
+                                    loader.current_index = index;
+                                }
+                            }
+                        }
+                        if let Some(favorites) = &favorites {
+                            loader.restore_favorites(&favorites.paths);
+                        }
+                        loader.scanned_paths.len()
+                    };
+                    state.draw_current_image().log_err();
+                    state.graphics.update_message(&format!("{} images", count));
+
+                    if watch_folder {
+                        spawn_folder_watcher(state.image_loader.clone());
+                    }
+                    if capture_time_sort {
+                        spawn_capture_time_reader(&state.image_loader, state.event_proxy.clone());
+                    }
+                }
+                CustomEvent::CaptureTimeProgress(done, total) => state
+                    .graphics
+                    .update_message(&format!("Reading capture times {}/{}", done, total)),
+                CustomEvent::CaptureTimesLoaded(times) => {
+                    let changed = {
+                        let mut loader = state.image_loader.lock().unwrap();
+                        loader.apply_capture_times(times.clone())
+                    };
+                    {
+                        let loader = state.image_loader.lock().unwrap();
+                        if state.graphics.progress.is_some() {
+                            state.graphics.progress =
+                                Some((loader.current_index, loader.scanned_paths.len()));
+                        }
+                    }
+                    state.graphics.update_message("Sorted by capture time");
+                    if state.grid.is_some() {
+                        state.draw_grid().log_err();
+                    } else if changed {
+                        state.draw_current_image().log_err();
+                    }
+                }
+                CustomEvent::SheetCellLoaded => state.redraw_contact_sheet(),
+                CustomEvent::ThumbnailLoaded => {
+                    if state.grid.is_some() {
+                        state.draw_grid().log_err();
+                    } else {
+                        state.update_filmstrip();
+                    }
                 }
+                CustomEvent::FilmstripFade(opacity) => state.graphics.filmstrip_opacity = *opacity,
                 CustomEvent::TransitionStart => {
                     state.transition.active = true;
                     state.transition.last_time = Instant::now();
@@ -441,22 +1143,86 @@ fn main() -> Result<()> {
                         tx_transition_throttle.send(Instant::now()).log_err();
                     };
                 }
-                CustomEvent::MouseCursorAwake => main_window.set_cursor_visible(true),
-                CustomEvent::MouseCursorSleep => main_window.set_cursor_visible(false),
-                CustomEvent::ClearOsdMessage => state.graphics.update_message(""),
+                CustomEvent::MouseCursorAwake => {
+                    if cursor_auto_hide {
+                        main_window.set_cursor_visible(true);
+                        state.graphics.progress_bar_hidden = false;
+                    }
+                    state.set_activity_paused(true).log_err();
+                }
+                CustomEvent::MouseCursorSleep => {
+                    if cursor_auto_hide {
+                        main_window.set_cursor_visible(false);
+                        state.graphics.progress_bar_hidden = state.fullscreen_ctrl.active;
+                    }
+                    state.set_activity_paused(false).log_err();
+                }
+                CustomEvent::ClearOsdMessage => match &input_mode {
+                    // Keep the prompt while typing
+                    InputMode::GoTo(input) => state.graphics.update_message(&goto_prompt(input)),
+                    InputMode::Normal => state.graphics.update_message(""),
+                },
+                CustomEvent::IdleExit => {
+                    log::info!(
+                        "no input for {} seconds (viewer.idle_exit_secs), exiting",
+                        idle_exit_secs.unwrap_or_default()
+                    );
+                    *control_flow = ControlFlow::Exit;
+                }
+                CustomEvent::Action(event_action) => {
+                    reset_idle_timer();
+                    action = Some(*event_action);
+                }
+                CustomEvent::RemoteCommand(command) => {
+                    reset_idle_timer();
+                    log::info!("remote command: {:?}", command);
+                    // Leave the grid view, the navigation draws the image
+                    if matches!(
+                        command,
+                        RemoteCommand::Next | RemoteCommand::Prev | RemoteCommand::Goto(_)
+                    ) {
+                        state.grid = None;
+                    }
+                    match command {
+                        RemoteCommand::Next => state.next_image(1).log_err(),
+                        RemoteCommand::Prev => state.next_image(-1).log_err(),
+                        RemoteCommand::Goto(pos) => state.goto_image(*pos - 1).log_err(),
+                        RemoteCommand::Pause | RemoteCommand::Play if state.manual => {
+                            state.graphics.update_message("Manual");
+                        }
+                        RemoteCommand::Pause => {
+                            state.paused = true;
+                            state.tx_slideshow_timer.send(TimerState::Pause).log_err();
+                            state.graphics.update_message("Pause");
+                        }
+                        RemoteCommand::Play => {
+                            state.paused = false;
+                            state.tx_slideshow_timer.send(TimerState::Play).log_err();
+                            state.graphics.update_message("Play");
+                        }
+                        RemoteCommand::Fullscreen => {
+                            state.fullscreen_ctrl.toggle();
+                            state.draw_current_image().log_err();
+                        }
+                        RemoteCommand::Quit => *control_flow = ControlFlow::Exit,
+                    }
+                }
             },
             Event::WindowEvent { event, window_id } if window_id == &main_window.id() => {
-                use winit::event::{
-                    MouseScrollDelta,
-                    VirtualKeyCode::{
-                        Back, Comma, Down, End, Escape, Home, Key0, Key1, Key2, LBracket, Left,
-                        PageDown, PageUp, Pause, Period, RBracket, Return, Right, Space, Up, C, D,
-                        F, F11, L, M, O, P, Q, T,
-                    },
-                };
+                use winit::event::MouseScrollDelta;
 
                 let mut gfx = &mut state.graphics;
-                let mut nav = Nav::None;
+
+                if matches!(
+                    event,
+                    WindowEvent::KeyboardInput { .. }
+                        | WindowEvent::MouseInput { .. }
+                        | WindowEvent::MouseWheel { .. }
+                        | WindowEvent::CursorMoved { .. }
+                        | WindowEvent::Touch(_)
+                ) {
+                    reset_idle_timer();
+                }
 
                 match event {
                     WindowEvent::ModifiersChanged(newstate) => {
@@ -471,171 +1237,90 @@ fn main() -> Result<()> {
                             },
                         ..
                     } => {
-                        match press_state {
-                            Pressed => match virtual_code {
-                                LBracket => {
-                                    // Decrease the display time
-                                    state.current_timer_secs = state
-                                        .current_timer_secs
-                                        .saturating_sub(TIMER_VALUE_INCREMENT);
-                                    state
-                                        .tx_slideshow_timer
-                                        .send(TimerState::Change(state.current_timer_secs))
-                                        .log_err();
-                                    if state.paused {
-                                        state.tx_slideshow_timer.send(TimerState::Pause).log_err();
-                                    }
-                                    gfx.update_message(&format!(
-                                        "Timer: {}",
-                                        state.current_timer_secs
-                                    ));
-                                }
-                                RBracket => {
-                                    // Increase the display time
-                                    state.current_timer_secs = state
-                                        .current_timer_secs
-                                        .saturating_add(TIMER_VALUE_INCREMENT);
-                                    state
-                                        .tx_slideshow_timer
-                                        .send(TimerState::Change(state.current_timer_secs))
-                                        .log_err();
-                                    if state.paused {
-                                        state.tx_slideshow_timer.send(TimerState::Pause).log_err();
-                                    }
-                                    gfx.update_message(&format!(
-                                        "Timer: {}",
-                                        state.current_timer_secs
-                                    ));
-                                }
-                                _ => {}
-                            },
-                            Released => match virtual_code {
-                                Q | Escape => *control_flow = ControlFlow::Exit,
-                                Key0 if modifiers_state.alt() => {
-                                    main_window.set_inner_size(PhysicalSize::new(
-                                        gfx.texture_size.width / 2,
-                                        gfx.texture_size.height / 2,
-                                    ));
-                                    gfx.update_message("Window Scale: 0.5");
-                                }
-                                Key1 if modifiers_state.alt() => {
-                                    main_window.set_inner_size(gfx.texture_size);
-                                    gfx.update_message("Window Scale: 1.0");
-                                }
-                                Key2 if modifiers_state.alt() => {
-                                    main_window.set_inner_size(PhysicalSize::new(
-                                        gfx.texture_size.width * 2,
-                                        gfx.texture_size.height * 2,
-                                    ));
-                                    gfx.update_message("Window Scale: 2.0");
-                                }
-                                M | Down if modifiers_state.alt() => {
-                                    main_window.set_minimized(true)
-                                }
-                                F | F11 => {
-                                    state.fullscreen_ctrl.toggle();
-                                    state.draw_current_image().log_err();
-                                }
-                                Return if modifiers_state.alt() => {
-                                    state.fullscreen_ctrl.toggle();
-                                    state.draw_current_image().log_err();
-                                }
-                                T => {
-                                    always_on_top = !always_on_top;
-                                    main_window.set_always_on_top(always_on_top);
-                                    gfx.update_message(&format!(
-                                        "Always on top: {}",
-                                        yes_no(always_on_top)
-                                    ));
-                                }
-                                D => {
-                                    titlebar = !titlebar;
-                                    let inner_size = main_window.inner_size();
-                                    main_window.set_decorations(titlebar);
-                                    main_window.set_inner_size(inner_size);
-                                    state
-                                        .graphics
-                                        .update_message(&format!("Titlebar: {}", yes_no(titlebar)));
-                                }
-                                Right | Down | PageDown | Period | Return => {
-                                    nav = if modifiers_state.shift() {
-                                        Nav::Next10
-                                    } else {
-                                        Nav::Next
-                                    };
-                                }
-                                Left | Up | PageUp | Comma => {
-                                    nav = if modifiers_state.shift() {
-                                        Nav::Prev10
-                                    } else {
-                                        Nav::Prev
-                                    };
-                                }
-                                Home => nav = Nav::First,
-                                End => nav = Nav::Last,
-                                Space | P => {
-                                    // Toggle Pause
-                                    if state.paused {
-                                        state.tx_slideshow_timer.send(TimerState::Play).log_err();
-                                        gfx.update_message("Play");
-                                    } else {
-                                        state.tx_slideshow_timer.send(TimerState::Pause).log_err();
-                                        gfx.update_message("Pause");
+                        use winit::event::VirtualKeyCode::{
+                            Back, Down, Escape, LAlt, LControl, LShift, LWin, Left, NumpadEnter,
+                            RAlt, RControl, RShift, RWin, Return, Right, Up, Q,
+                        };
+
+                        // The grid view takes the arrow keys, Enter and Esc
+                        let grid_key = state.grid.is_some()
+                            && matches!(virtual_code, Left | Right | Up | Down | Return | Escape);
+                        if kiosk
+                            && *virtual_code == Q
+                            && modifiers_state.ctrl()
+                            && modifiers_state.shift()
+                        {
+                            // The only exit of the kiosk mode
+                            if press_state == &Released {
+                                *control_flow = ControlFlow::Exit;
+                            }
+                        } else if state.graphics.help.is_some() {
+                            // Any key except the modifiers closes the help
+                            let modifier = matches!(
+                                virtual_code,
+                                LShift | RShift | LControl | RControl | LAlt | RAlt | LWin | RWin
+                            );
+                            if press_state == &Released && !modifier {
+                                state.hide_help().log_err();
+                            }
+                        } else if let InputMode::GoTo(input) = &mut input_mode {
+                            // The typed characters are handled by `ReceivedCharacter`
+                            match (press_state, virtual_code) {
+                                (Released, Return | NumpadEnter) => {
+                                    let count =
+                                        state.image_loader.lock().unwrap().scanned_paths.len();
+                                    let index = parse_goto_input(input, count);
+                                    input_mode = InputMode::Normal;
+                                    match index {
+                                        Some(index) => {
+                                            state.grid = None;
+                                            state.graphics.update_message("");
+                                            state.goto_image(index).log_err();
+                                        }
+                                        None => state.graphics.update_message("Go to: invalid"),
                                     }
-                                    state.paused = !state.paused;
                                 }
-                                Pause => {
-                                    // Pause
-                                    state.paused = true;
-                                    state.tx_slideshow_timer.send(TimerState::Pause).log_err();
-                                    gfx.update_message("Pause");
-                                }
-                                L => {
-                                    state.pause_at_last = !state.pause_at_last;
-                                    gfx.update_message(&format!(
-                                        "Pause at last: {}",
-                                        yes_no(state.pause_at_last)
-                                    ));
-                                }
-                                O => {
-                                    let (index, count) = {
-                                        let loader = state.image_loader.lock().unwrap();
-                                        (loader.current_index, loader.scanned_paths.len())
-                                    };
-                                    state.graphics.update_message(&format!(
-                                        "Pos: {}/{}",
-                                        index + 1,
-                                        count
-                                    ));
+                                (Released, Escape) => {
+                                    input_mode = InputMode::Normal;
+                                    state.graphics.update_message("");
                                 }
-                                Back => {
-                                    // Reset the display time to the default value
-                                    state.current_timer_secs = state.default_timer_secs;
-                                    state
-                                        .tx_slideshow_timer
-                                        .send(TimerState::Change(state.current_timer_secs))
-                                        .log_err();
-                                    if state.paused {
-                                        state.tx_slideshow_timer.send(TimerState::Pause).log_err();
-                                    }
-                                    gfx.update_message(&format!(
-                                        "Timer: {} (reset)",
-                                        state.current_timer_secs
-                                    ));
-                                }
-                                C if modifiers_state.ctrl() => {
-                                    let loader = state.image_loader.lock().unwrap();
-                                    if let Some(path) = &loader.current_path {
-                                        if path_copy_to_clipboard(path) {
-                                            gfx.update_message(&format!(
-                                                "File path copied\n'{}'",
-                                                path.display()
-                                            ));
-                                        }
-                                    }
+                                (Pressed, Back) => {
+                                    input.pop();
+                                    state.graphics.update_message(&goto_prompt(input));
                                 }
                                 _ => {}
-                            },
+                            }
+                        } else if grid_key {
+                            match (press_state, virtual_code) {
+                                (Pressed, Left) => state.move_grid_selection(-1, 0).log_err(),
+                                (Pressed, Right) => state.move_grid_selection(1, 0).log_err(),
+                                (Pressed, Up) => state.move_grid_selection(0, -1).log_err(),
+                                (Pressed, Down) => state.move_grid_selection(0, 1).log_err(),
+                                (Released, Return) => state.select_grid_item().log_err(),
+                                (Released, Escape) => state.toggle_grid().log_err(),
+                                _ => {}
+                            }
+                        } else if let Some(key_action) =
+                            keybindings.get(*virtual_code, modifiers_state)
+                        {
+                            // Repeatable actions run on the key press, others on the release
+                            if (press_state == &Pressed) == key_action.is_repeatable() {
+                                action = Some(key_action);
+                            }
+                        }
+                    }
+                    WindowEvent::ReceivedCharacter(c) => {
+                        if let InputMode::GoTo(input) = &mut input_mode {
+                            // Digits with an optional trailing '%'
+                            let accepted = if *c == '%' {
+                                !input.is_empty() && !input.ends_with('%')
+                            } else {
+                                c.is_ascii_digit() && !input.ends_with('%')
+                            };
+                            if accepted {
+                                input.push(*c);
+                                state.graphics.update_message(&goto_prompt(input));
+                            }
                         }
                     }
                     WindowEvent::MouseInput {
@@ -650,19 +1335,29 @@ fn main() -> Result<()> {
                                 }
 
                                 if last_mouse_left_pressed_time.elapsed() <= double_click_duration {
-                                    state.fullscreen_ctrl.toggle();
-                                    state.draw_current_image().log_err();
+                                    if state.zoom_mode {
+                                        // Reset to fit
+                                        gfx.reset_view();
+                                        gfx.update_message("Zoom: 100%");
+                                    } else {
+                                        action = keybindings.double_click;
+                                    }
                                 }
 
                                 last_mouse_left_pressed_time = Instant::now();
                             }
                             Released => {
                                 if drag_state != DragState::Dragging {
-                                    nav = if modifiers_state.shift() {
-                                        Nav::Next10
+                                    // A click on the filmstrip jumps to the thumbnail
+                                    if let Some(index) = gfx.filmstrip_index_at(cursor_pos) {
+                                        state.goto_image(index).log_err();
                                     } else {
-                                        Nav::Next
-                                    };
+                                        nav = if modifiers_state.shift() {
+                                            Nav::Next10
+                                        } else {
+                                            Nav::Next
+                                        };
+                                    }
                                 }
 
                                 drag_state = DragState::None;
@@ -676,9 +1371,20 @@ fn main() -> Result<()> {
                                 Nav::Prev
                             }
                         }
-                        MouseButton::Middle if clickstate == &Released => {
+                        MouseButton::Middle if clickstate == &Released && !kiosk => {
                             *control_flow = ControlFlow::Exit
                         }
+                        // Side buttons, back and forward as in the browsers
+                        MouseButton::Other(button) if clickstate == &Released => {
+                            if logged_mouse_buttons.insert(*button) {
+                                log::info!("mouse button: {}", button);
+                            }
+                            if keybindings.is_mouse_back(*button) {
+                                nav = Nav::Prev;
+                            } else if keybindings.is_mouse_forward(*button) {
+                                nav = Nav::Next;
+                            }
+                        }
                         _ => {}
                     },
                     WindowEvent::MouseWheel { delta, .. } => {
@@ -687,20 +1393,39 @@ fn main() -> Result<()> {
                             MouseScrollDelta::PixelDelta(v) => v.y > 0.0,
                         };
 
-                        nav = if up {
-                            if modifiers_state.shift() {
-                                Nav::Prev10
-                            } else {
-                                Nav::Prev
-                            }
-                        } else if modifiers_state.shift() {
-                            Nav::Next10
+                        if state.zoom_mode {
+                            let factor = if up { ZOOM_STEP } else { 1.0 / ZOOM_STEP };
+                            let zoom = gfx.zoom_at(factor, cursor_pos);
+                            gfx.update_message(&format!("Zoom: {:.0}%", zoom * 100.0));
                         } else {
-                            Nav::Next
-                        };
+                            nav = if up {
+                                if modifiers_state.shift() {
+                                    Nav::Prev10
+                                } else {
+                                    Nav::Prev
+                                }
+                            } else if modifiers_state.shift() {
+                                Nav::Next10
+                            } else {
+                                Nav::Next
+                            };
+                        }
                     }
                     WindowEvent::CursorMoved { position, .. } => {
                         tx_mouse_cursor_watcher.send(()).unwrap();
+                        cursor_pos = *position;
+
+                        if gfx.filmstrip.is_some() {
+                            gfx.filmstrip_opacity = 1.0;
+                            tx_filmstrip_activity.send(()).log_err();
+                        }
+
+                        // The split of the compare mode follows the mouse
+                        if gfx.compare.is_some() {
+                            gfx.set_compare_split(
+                                (position.x / gfx.inner_size.width.max(1) as f64) as f32,
+                            );
+                        }
 
                         match drag_state {
                             DragState::Awake => {
@@ -708,7 +1433,14 @@ fn main() -> Result<()> {
                                 drag_pos = Some(*position);
                                 drag_finger = false;
                             }
-                            DragState::Dragging if !drag_finger => {
+                            DragState::Dragging if !drag_finger && state.zoom_mode => {
+                                // Pan the zoomed image
+                                if let Some(drag_pos) = drag_pos {
+                                    gfx.pan_by(position.x - drag_pos.x, position.y - drag_pos.y);
+                                }
+                                drag_pos = Some(*position);
+                            }
+                            DragState::Dragging if !drag_finger && !kiosk => {
                                 if main_window.fullscreen().is_some() {
                                     state.fullscreen_ctrl.toggle();
                                     state.draw_current_image().log_err();
@@ -740,12 +1472,12 @@ fn main() -> Result<()> {
 
                         match touch.phase {
                             TouchPhase::Started if new_fingers == 1 => {
-                                // Multi-finger tapping
+                                // Multi-finger tapping, the action runs when a finger is lifted
                                 if last_touch_pressed_time.elapsed() <= MULTITOUCH_INTERVAL {
-                                    state.fullscreen_ctrl.toggle();
-                                    state.draw_current_image().log_err();
+                                    tap_finger_count += 1;
                                     multifinger_touch = true;
                                 } else {
+                                    tap_finger_count = 1;
                                     multifinger_touch = false;
                                 }
                                 last_touch_pressed_time = Instant::now();
@@ -755,7 +1487,7 @@ fn main() -> Result<()> {
                                     drag_state = DragState::Awake;
                                     drag_pos = Some(touch.location);
                                 }
-                                DragState::Awake if !state.fullscreen_ctrl.active => {
+                                DragState::Awake if !state.fullscreen_ctrl.active && !kiosk => {
                                     if let Some(drag_pos_in) = drag_pos {
                                         let delta = distance(&touch.location, &drag_pos_in);
                                         if delta > TOUCH_DRAG_START_DISTANCE {
@@ -785,6 +1517,16 @@ fn main() -> Result<()> {
                             TouchPhase::Ended | TouchPhase::Cancelled => {
                                 touch_finger_count -= 1; // Sometimes not called and may cause leaks
 
+                                // On the first lifted finger, the count may leak
+                                if multifinger_touch && tap_finger_count > 0 {
+                                    action = if tap_finger_count >= 3 {
+                                        keybindings.three_finger_tap
+                                    } else {
+                                        keybindings.two_finger_tap
+                                    };
+                                    tap_finger_count = 0;
+                                }
+
                                 if drag_state != DragState::Dragging && !multifinger_touch {
                                     let size = main_window.inner_size();
                                     let loc = touch.location;
@@ -811,36 +1553,47 @@ fn main() -> Result<()> {
                         last_touch_finger_id = touch.id;
                         last_touch_finger_count = touch_finger_count;
                     }
-                    WindowEvent::DroppedFile(path) => {
-                        let mut new = false;
-
-                        if let Ok(loader) = &mut state.image_loader.lock() {
-                            if last_file_drop_event_time.elapsed().as_secs_f32() > FILE_DROP_TIMEOUT
-                            {
-                                loader.scanned_paths.clear();
-                                new = true;
-                            } else if loader.scanned_paths.is_empty() {
-                                new = true;
-                            }
-
-                            loader.append_path(path.clone());
+                    // Collect the burst of drops, the list is rebuilt once in MainEventsCleared
+                    WindowEvent::DroppedFile(path) => dropped_paths.push(path.clone()),
+                    WindowEvent::CloseRequested if !kiosk => *control_flow = ControlFlow::Exit,
+                    // Show the edits made in another app
+                    WindowEvent::Focused(true) if reload_on_focus => {
+                        let result = state.reload_current_image(true);
+                        result.log_err();
+                        if let Ok(true) = result {
+                            state.graphics.update_message("Reloaded");
+                        }
+                    }
+                    WindowEvent::Moved(_) if texture_on_monitor => {
+                        state.fit_texture_to_monitor().log_err()
+                    }
+                    WindowEvent::Resized(physical_size) => {
+                        let previous_size = gfx.inner_size;
+                        let aspect = gfx.image_aspect.unwrap_or(
+                            gfx.texture_size.width as f32 / gfx.texture_size.height.max(1) as f32,
+                        );
+                        gfx.resize(*physical_size);
 
-                            if new {
-                                loader.current_index = 0;
-                                loader.cache.clear();
-                                loader.force_reload_cache(&0).log_err();
+                        if texture_follows_window {
+                            last_resize_time = Instant::now();
+                            if !texture_resize_pending {
+                                texture_resize_pending = true;
+                                let proxy = state.event_proxy.clone();
+                                std::thread::spawn(move || {
+                                    std::thread::sleep(TEXTURE_RESIZE_DEBOUNCE);
+                                    proxy.send_event(CustomEvent::TextureResize).log_err();
+                                });
                             }
                         }
 
-                        if new {
-                            state.draw_current_image().log_err();
+                        // Snap to the aspect ratio of the image, skip the fullscreen
+                        if lock_aspect && !state.fullscreen_ctrl.active {
+                            if let Some(size) =
+                                aspect_lock.snap(previous_size, *physical_size, aspect)
+                            {
+                                main_window.set_inner_size(size);
+                            }
                         }
-
-                        last_file_drop_event_time = Instant::now();
-                    }
-                    WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
-                    WindowEvent::Resized(physical_size) => {
-                        gfx.resize(*physical_size);
                         state.draw_current_image().log_err();
                     }
                     WindowEvent::ScaleFactorChanged {
@@ -853,18 +1606,67 @@ fn main() -> Result<()> {
                     }
                     _ => {}
                 };
+            }
+            Event::MainEventsCleared => {
+                // Replace the list with the files dropped at once
+                if !dropped_paths.is_empty() {
+                    let paths = std::mem::take(&mut dropped_paths);
+                    if let Some(recent_paths) = &mut recent_paths {
+                        for path in &paths {
+                            recent_paths.add(path);
+                        }
+                        recent_paths.save().log_err();
+                    }
 
-                match nav {
-                    Nav::Next => state.next_image(1).log_err(),
-                    Nav::Prev => state.next_image(-1).log_err(),
-                    Nav::Next10 => state.next_image(10).log_err(),
-                    Nav::Prev10 => state.next_image(-10).log_err(),
-                    Nav::First => state.first_image().log_err(),
-                    Nav::Last => state.last_image().log_err(),
-                    _ => {}
-                };
+                    {
+                        let mut loader = state.image_loader.lock().unwrap();
+                        loader.cancel_scan();
+                        loader.scanned_paths.clear();
+                        loader.scanned_dirs.clear();
+                        loader.current_index = 0;
+                        loader.current_path = None;
+                        loader.clear_cache();
+                        loader.preload_queue.clear();
+                        loader.rotations.clear();
+                        loader.favorites.clear();
+                    }
+                    // Same as the startup scan, the first image is shown when it's found
+                    spawn_scan(
+                        state.image_loader.clone(),
+                        paths,
+                        shuffle_seed,
+                        state.event_proxy.clone(),
+                    );
+                }
+
+                // The position is not saved until the scan finishes
+                if let Some((path, config_hash)) =
+                    resume_file.as_ref().filter(|_| !state.is_scanning())
+                {
+                    if last_resume_save_time.elapsed() >= RESUME_SAVE_INTERVAL {
+                        last_resume_save_time = Instant::now();
+                        state.resume_state(config_hash).save(path).log_err();
+                    }
+                }
+                main_window.request_redraw();
+            }
+            Event::LoopDestroyed => {
+                if let Some((path, config_hash)) =
+                    resume_file.as_ref().filter(|_| !state.is_scanning())
+                {
+                    state.resume_state(config_hash).save(path).log_err();
+                }
+                if let Some(favorites) = favorites.as_mut().filter(|_| !state.is_scanning()) {
+                    let loader = state.image_loader.lock().unwrap();
+                    favorites.update(&loader.scanned_paths, loader.favorite_paths());
+                    favorites.save().log_err();
+                }
+                if let Some(path) = &geometry_file {
+                    if let Some((position, size)) = state.fullscreen_ctrl.windowed_rect() {
+                        WindowGeometry::new(position, size).save(path).log_err();
+                    }
+                }
             }
-            Event::MainEventsCleared => main_window.request_redraw(),
             Event::RedrawRequested(_) => {
                 let current_path = {
                     let loader = state.image_loader.lock().unwrap();
@@ -878,8 +1680,580 @@ fn main() -> Result<()> {
                     Err(OutOfMemory) => *control_flow = ControlFlow::Exit,
                     Err(e) => log::error!("{:}", e),
                 }
+
+                let captured = state.graphics.take_captured_frame();
+                match captured.map(|v| v.and_then(|image| save_screenshot(&screenshot_dir, &image)))
+                {
+                    Some(Ok(path)) => state
+                        .graphics
+                        .update_message(&format!("Screenshot saved\n'{}'", path.display())),
+                    Some(Err(err)) => {
+                        log::error!("{}", err);
+                        state
+                            .graphics
+                            .update_message(&format!("Failed to save the screenshot\n{}", err));
+                    }
+                    None => {}
+                }
             }
             _ => (),
         }
+
+        // Kiosk mode keeps the window as it is
+        if kiosk
+            && matches!(
+                action,
+                Some(
+                    Action::Quit
+                        | Action::ToggleFullscreen
+                        | Action::ToggleTitlebar
+                        | Action::Minimize
+                )
+            )
+        {
+            action = None;
+        }
+
+        match action {
+            Some(Action::Quit) => *control_flow = ControlFlow::Exit,
+            Some(Action::Next) => nav = Nav::Next,
+            Some(Action::Prev) => nav = Nav::Prev,
+            Some(Action::Next10) => nav = Nav::Next10,
+            Some(Action::Prev10) => nav = Nav::Prev10,
+            Some(Action::First) => nav = Nav::First,
+            Some(Action::Last) => nav = Nav::Last,
+            Some(Action::WindowScale50) => {
+                let texture_size = state.graphics.texture_size;
+                main_window.set_inner_size(PhysicalSize::new(
+                    texture_size.width / 2,
+                    texture_size.height / 2,
+                ));
+                state.graphics.update_message("Window Scale: 0.5");
+            }
+            Some(Action::WindowScale100) => {
+                main_window.set_inner_size(state.graphics.texture_size);
+                state.graphics.update_message("Window Scale: 1.0");
+            }
+            Some(Action::WindowScale200) => {
+                let texture_size = state.graphics.texture_size;
+                main_window.set_inner_size(PhysicalSize::new(
+                    texture_size.width * 2,
+                    texture_size.height * 2,
+                ));
+                state.graphics.update_message("Window Scale: 2.0");
+            }
+            Some(Action::Minimize) => main_window.set_minimized(true),
+            Some(Action::ToggleFullscreen) => {
+                state.fullscreen_ctrl.toggle();
+                state.draw_current_image().log_err();
+            }
+            Some(Action::ToggleAlwaysOnTop) => {
+                always_on_top = !always_on_top;
+                main_window.set_always_on_top(always_on_top);
+                state
+                    .graphics
+                    .update_message(&format!("Always on top: {}", yes_no(always_on_top)));
+            }
+            Some(Action::ToggleTitlebar) => {
+                titlebar = !titlebar;
+                state.fullscreen_ctrl.decorations = titlebar;
+                let inner_size = main_window.inner_size();
+                main_window.set_decorations(titlebar);
+                main_window.set_inner_size(inner_size);
+                state
+                    .graphics
+                    .update_message(&format!("Titlebar: {}", yes_no(titlebar)));
+            }
+            Some(Action::TogglePause) => {
+                if state.manual {
+                    state.graphics.update_message("Manual");
+                } else {
+                    if state.paused {
+                        // Resumed when the mouse stops with `pause_on_activity`
+                        if !state.activity_paused {
+                            state.tx_slideshow_timer.send(TimerState::Play).log_err();
+                        }
+                        state.graphics.update_message("Play");
+                    } else {
+                        state.tx_slideshow_timer.send(TimerState::Pause).log_err();
+                        state.graphics.update_message("Pause");
+                    }
+                    state.paused = !state.paused;
+                }
+            }
+            Some(Action::Pause) => {
+                if state.manual {
+                    state.graphics.update_message("Manual");
+                } else {
+                    state.paused = true;
+                    state.tx_slideshow_timer.send(TimerState::Pause).log_err();
+                    state.graphics.update_message("Pause");
+                }
+            }
+            Some(Action::TogglePauseAtLast) => {
+                state.pause_at_last = !state.pause_at_last;
+                state
+                    .graphics
+                    .update_message(&format!("Pause at last: {}", yes_no(state.pause_at_last)));
+            }
+            Some(Action::ShowPosition) => {
+                let (index, count) = {
+                    let loader = state.image_loader.lock().unwrap();
+                    (loader.current_index, loader.scanned_paths.len())
+                };
+                state
+                    .graphics
+                    .update_message(&format!("Pos: {}/{}", index + 1, count));
+            }
+            Some(Action::TimerDecrease) => {
+                let secs = state
+                    .current_timer_secs
+                    .saturating_sub(TIMER_VALUE_INCREMENT);
+                state.change_timer(secs);
+                state
+                    .graphics
+                    .update_message(&format!("Timer: {}", state.current_timer_secs));
+            }
+            Some(Action::TimerIncrease) => {
+                let secs = state
+                    .current_timer_secs
+                    .saturating_add(TIMER_VALUE_INCREMENT);
+                state.change_timer(secs);
+                state
+                    .graphics
+                    .update_message(&format!("Timer: {}", state.current_timer_secs));
+            }
+            Some(Action::TimerReset) => {
+                state.change_timer(state.default_timer_secs);
+                state
+                    .graphics
+                    .update_message(&format!("Timer: {} (reset)", state.current_timer_secs));
+            }
+            Some(Action::CycleFitMode) => {
+                let fit_mode = {
+                    let mut loader = state.image_loader.lock().unwrap();
+                    let fit_mode = loader.fit_mode.next();
+                    loader.set_fit_mode(fit_mode).log_err();
+                    fit_mode
+                };
+                state.draw_current_image().log_err();
+                state
+                    .graphics
+                    .update_message(&format!("Fit mode: {:?}", fit_mode));
+            }
+            Some(Action::RotateCw) | Some(Action::RotateCcw) => {
+                let rotation = {
+                    let mut loader = state.image_loader.lock().unwrap();
+                    let rotation = if action == Some(Action::RotateCcw) {
+                        loader.rotate_ccw()
+                    } else {
+                        loader.rotate_cw()
+                    };
+                    let index = loader.current_index;
+                    loader.force_reload_cache(&index).log_err();
+                    rotation
+                };
+                state.draw_current_image().log_err();
+                state
+                    .graphics
+                    .update_message(&format!("Rotation: {}°", rotation));
+            }
+            Some(Action::ToggleZoomMode) => {
+                state.stop_ken_burns();
+                state.zoom_mode = !state.zoom_mode;
+                if !state.zoom_mode {
+                    state.graphics.reset_view();
+                }
+                state
+                    .graphics
+                    .update_message(&format!("Zoom mode: {}", yes_no(state.zoom_mode)));
+            }
+            Some(Action::ToggleInfo) => {
+                state.graphics.show_info = !state.graphics.show_info;
+            }
+            Some(Action::GoTo) => {
+                input_mode = InputMode::GoTo(String::new());
+                state.graphics.update_message(&goto_prompt(""));
+            }
+            Some(Action::ToggleHistogram) => {
+                state.graphics.show_histogram = !state.graphics.show_histogram;
+                state.update_histogram();
+            }
+            Some(Action::ToggleDirection) => {
+                state.reverse = !state.reverse;
+                state.graphics.update_message(&format!(
+                    "Direction: {}",
+                    if state.reverse { "backward" } else { "forward" }
+                ));
+            }
+            Some(Action::ToggleTransition) => {
+                state.transition.enabled = !state.transition.enabled;
+                state
+                    .graphics
+                    .update_message(&format!("Transition: {}", yes_no(state.transition.enabled)));
+            }
+            Some(Action::NextTransitionEffect) => {
+                let name = state.next_transition_effect();
+                state
+                    .graphics
+                    .update_message(&format!("Transition: {}", name));
+            }
+            Some(Action::Trash) => {
+                let confirmed = last_delete_pressed_time
+                    .map_or(false, |t| t.elapsed() <= DELETE_CONFIRM_INTERVAL);
+                if confirmed {
+                    last_delete_pressed_time = None;
+                    let result = {
+                        let mut loader = state.image_loader.lock().unwrap();
+                        loader.trash_current()
+                    };
+                    match result {
+                        Ok(path) => {
+                            state.draw_current_image().log_err();
+                            state
+                                .graphics
+                                .update_message(&format!("Moved to trash\n'{}'", path.display()));
+                        }
+                        Err(err) => {
+                            log::error!("{}", err);
+                            state
+                                .graphics
+                                .update_message(&format!("Failed to move to trash\n{}", err));
+                        }
+                    }
+                } else {
+                    last_delete_pressed_time = Some(Instant::now());
+                    state
+                        .graphics
+                        .update_message("Press again to move to trash");
+                }
+            }
+            Some(Action::SaveImage) => match state.save_displayed_image() {
+                Ok(path) => state
+                    .graphics
+                    .update_message(&format!("Image saved\n'{}'", path.display())),
+                Err(err) => {
+                    log::error!("{}", err);
+                    state
+                        .graphics
+                        .update_message(&format!("Failed to save the image\n{}", err));
+                }
+            },
+            Some(Action::Screenshot) => state.graphics.request_capture(),
+            Some(Action::Blank) => {
+                let bg_color = state.graphics.bg_color;
+                state.graphics.toggle_blank(bg_color);
+            }
+            Some(Action::BlankWhite) => state.graphics.toggle_blank(image::Rgba([255; 4])),
+            Some(Action::PinCompare) => {
+                state.pin_compare().log_err();
+                if state.graphics.compare.is_some() {
+                    state.graphics.update_message("Compare: pinned");
+                }
+            }
+            Some(Action::ExitCompare) => {
+                if state.graphics.compare.is_some() {
+                    state.stop_compare();
+                    state.graphics.update_message("Compare: off");
+                }
+            }
+            Some(Action::ToggleHelp) => {
+                if state.graphics.help.is_some() {
+                    state.hide_help().log_err();
+                } else {
+                    state.show_help(keybindings.help_lines()).log_err();
+                }
+            }
+            Some(Action::ToggleFavorite) => {
+                let marked = state.image_loader.lock().unwrap().toggle_favorite();
+                state.graphics.favorite = marked;
+                state.graphics.update_message(if marked {
+                    "Favorite: marked"
+                } else {
+                    "Favorite: unmarked"
+                });
+            }
+            Some(Action::CopyFavorites) => {
+                let paths = state.image_loader.lock().unwrap().favorite_paths();
+                match favorites::copy_to_dir(&paths, &favorites_dir) {
+                    Ok(count) => state.graphics.update_message(&format!(
+                        "{} favorites copied\n'{}'",
+                        count,
+                        favorites_dir.display()
+                    )),
+                    Err(err) => {
+                        log::error!("{}", err);
+                        state
+                            .graphics
+                            .update_message(&format!("Failed to copy the favorites\n{}", err));
+                    }
+                }
+            }
+            Some(Action::ToggleGrid) => state.toggle_grid().log_err(),
+            Some(Action::ToggleFilmstrip) => {
+                let shown = state.toggle_filmstrip();
+                if shown {
+                    tx_filmstrip_activity.send(()).log_err();
+                }
+                state
+                    .graphics
+                    .update_message(&format!("Filmstrip: {}", if shown { "on" } else { "off" }));
+            }
+            Some(Action::CopyPath) => {
+                let loader = state.image_loader.lock().unwrap();
+                if let Some(path) = &loader.current_path {
+                    if path_copy_to_clipboard(path) {
+                        state
+                            .graphics
+                            .update_message(&format!("File path copied\n'{}'", path.display()));
+                    }
+                }
+            }
+            Some(Action::RevealFile) => {
+                let current_path = state.image_loader.lock().unwrap().current_path.clone();
+                let message = match current_path {
+                    None => "No image to reveal".to_string(),
+                    Some(path) if is_remote_path(&path) => {
+                        "Remote images have no folder to open".to_string()
+                    }
+                    Some(path) => {
+                        let path = multi_image::file_path(&path);
+                        match reveal_in_file_manager(&path) {
+                            Ok(_) => format!("Opened the folder\n'{}'", path.display()),
+                            Err(err) => {
+                                log::error!("{}", err);
+                                format!("Failed to open the folder\n{}", err)
+                            }
+                        }
+                    }
+                };
+                state.graphics.update_message(&message);
+            }
+            Some(Action::ReloadImage) => match state.reload_current_image(false) {
+                Ok(_) => state.graphics.update_message("Reloaded"),
+                Err(err) => {
+                    log::error!("{}", err);
+                    state
+                        .graphics
+                        .update_message(&format!("Failed to reload the image\n{}", err));
+                }
+            },
+            Some(Action::SetWallpaper) => {
+                let current_path = state.image_loader.lock().unwrap().current_path.clone();
+                let message = match current_path {
+                    None => "No image to set as the wallpaper".to_string(),
+                    Some(path) => match wallpaper::set_wallpaper(&path) {
+                        Ok(_) => "Wallpaper set".to_string(),
+                        Err(err) => {
+                            // Also the unsupported builds and desktop environments
+                            log::warn!("{}", err);
+                            format!("Failed to set the wallpaper\n{}", err)
+                        }
+                    },
+                };
+                state.graphics.update_message(&message);
+            }
+            Some(
+                action @ (Action::BrightnessIncrease
+                | Action::BrightnessDecrease
+                | Action::ContrastIncrease
+                | Action::ContrastDecrease
+                | Action::GammaIncrease
+                | Action::GammaDecrease),
+            ) => {
+                let (brightness, contrast, gamma) = match action {
+                    Action::BrightnessIncrease => (BRIGHTNESS_STEP, 0.0, 0.0),
+                    Action::BrightnessDecrease => (-BRIGHTNESS_STEP, 0.0, 0.0),
+                    Action::ContrastIncrease => (0.0, CONTRAST_STEP, 0.0),
+                    Action::ContrastDecrease => (0.0, -CONTRAST_STEP, 0.0),
+                    Action::GammaIncrease => (0.0, 0.0, GAMMA_STEP),
+                    _ => (0.0, 0.0, -GAMMA_STEP),
+                };
+                state.graphics.adjust_image(brightness, contrast, gamma);
+                let message = state.graphics.adjustments_message();
+                state.graphics.update_message(&message);
+            }
+            Some(Action::ResetAdjustments) => {
+                state.graphics.reset_adjustments();
+                let message = state.graphics.adjustments_message();
+                state.graphics.update_message(&message);
+            }
+            Some(Action::ToggleLockAdjustments) => {
+                state.lock_adjustments = !state.lock_adjustments;
+                state.graphics.update_message(&format!(
+                    "Lock adjustments: {}",
+                    yes_no(state.lock_adjustments)
+                ));
+            }
+            Some(Action::FlipHorizontal) => {
+                let flipped = state.graphics.toggle_flip(true);
+                state
+                    .graphics
+                    .update_message(&format!("Flip horizontal: {}", yes_no(flipped)));
+            }
+            Some(Action::FlipVertical) => {
+                let flipped = state.graphics.toggle_flip(false);
+                state
+                    .graphics
+                    .update_message(&format!("Flip vertical: {}", yes_no(flipped)));
+            }
+            Some(action @ (Action::RotateFineCw | Action::RotateFineCcw)) => {
+                let deg = if action == Action::RotateFineCw {
+                    ROTATION_STEP_DEG
+                } else {
+                    -ROTATION_STEP_DEG
+                };
+                let angle = state.graphics.rotate_by(deg);
+                state
+                    .graphics
+                    .update_message(&format!("Rotation: {:+.1}°", angle));
+            }
+            Some(Action::ResetRotation) => {
+                state.graphics.reset_rotation();
+                state.graphics.update_message("Rotation: 0.0°");
+            }
+            Some(Action::NextMonitor) => {
+                if state.fullscreen_ctrl.active && state.fullscreen_ctrl.span {
+                    state.graphics.update_message("Spanning all monitors");
+                } else if let Some((index, count)) = state.fullscreen_ctrl.move_to_next_monitor() {
+                    if texture_on_monitor {
+                        state.fit_texture_to_monitor().log_err();
+                    } else {
+                        state.draw_current_image().log_err();
+                    }
+                    state
+                        .graphics
+                        .update_message(&format!("Monitor: {}/{}", index + 1, count));
+                } else {
+                    state.graphics.update_message("No monitor detected");
+                }
+            }
+            Some(Action::CopyImage) => match state.copy_image_to_clipboard() {
+                Ok(()) => state.graphics.update_message("Image copied"),
+                Err(err) => {
+                    log::error!("{}", err);
+                    state
+                        .graphics
+                        .update_message(&format!("Failed to copy the image\n{}", err));
+                }
+            },
+            None => {}
+        };
+
+        // Navigate the grid view instead of the images
+        if state.grid.is_some() {
+            match nav {
+                Nav::Next => state.move_grid_selection(1, 0).log_err(),
+                Nav::Prev => state.move_grid_selection(-1, 0).log_err(),
+                Nav::Next10 => state.move_grid_selection(0, 1).log_err(),
+                Nav::Prev10 => state.move_grid_selection(0, -1).log_err(),
+                Nav::First => state.move_grid_selection(i32::MIN, 0).log_err(),
+                Nav::Last => state.move_grid_selection(i32::MAX, 0).log_err(),
+                _ => {}
+            };
+            nav = Nav::None;
+        }
+
+        match nav {
+            Nav::Next => state.scroll_or_next_image(1).log_err(),
+            Nav::Prev => state.scroll_or_next_image(-1).log_err(),
+            Nav::Next10 => state.next_image(10).log_err(),
+            Nav::Prev10 => state.next_image(-10).log_err(),
+            Nav::First => state.first_image().log_err(),
+            Nav::Last => state.last_image().log_err(),
+            _ => {}
+        };
+    });
+}
+
+/// OSD message of the position input
+fn goto_prompt(input: &str) -> String {
+    format!("Go to: {}_", input)
+}
+
+/// Get the 0-based index from the typed 1-based position or percentage, e.g. "142", "50%"
+fn parse_goto_input(input: &str, count: usize) -> Option<usize> {
+    let last = count.checked_sub(1)?;
+    match input.strip_suffix('%') {
+        Some(percent) => {
+            let percent = percent.parse::<f64>().ok()?.min(100.0);
+            Some((last as f64 * percent / 100.0).round() as usize)
+        }
+        None => Some(input.parse::<usize>().ok()?.saturating_sub(1).min(last)),
+    }
+}
+
+/// Watch the scanned folders and insert the new files in the sorted position
+fn spawn_folder_watcher(image_loader: Arc<Mutex<ImageLoader>>) {
+    let (watch_dirs, recursive_mode, scan_id) = {
+        let loader = image_loader.lock().unwrap();
+        let recursive_mode = if loader.scan_subfolders {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        (
+            loader.scanned_dirs.clone(),
+            recursive_mode,
+            loader.scan_id(),
+        )
+    };
+
+    std::thread::spawn(move || {
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = match notify::watcher(tx, FOLDER_WATCH_DEBOUNCE) {
+            Ok(watcher) => watcher,
+            Err(err) => {
+                log::error!("{}", err);
+                return;
+            }
+        };
+        for dir in &watch_dirs {
+            watcher.watch(dir, recursive_mode).log_err();
+        }
+
+        // Stop when the list is scanned again, e.g. by dropping files, it starts a new watcher
+        loop {
+            let event = match rx.recv_timeout(FOLDER_WATCH_CHECK_INTERVAL) {
+                Ok(event) => Some(event),
+                Err(mpsc::RecvTimeoutError::Timeout) => None,
+                Err(mpsc::RecvTimeoutError::Disconnected) => return,
+            };
+            let mut loader = image_loader.lock().unwrap();
+            if loader.scan_id() != scan_id {
+                return;
+            }
+            if let Some(DebouncedEvent::Create(path) | DebouncedEvent::Rename(_, path)) = event {
+                if loader.insert_path(path.clone()) {
+                    log::info!("new file: {}", path.display());
+                }
+            }
+        }
+    });
+}
+
+/// Read the capture times in the background, the paths are sorted by the modified time until done
+fn spawn_capture_time_reader(
+    image_loader: &Mutex<ImageLoader>,
+    proxy: EventLoopProxy<CustomEvent>,
+) {
+    let paths = image_loader.lock().unwrap().uncached_capture_time_paths();
+    if paths.is_empty() {
+        return;
+    }
+
+    std::thread::spawn(move || {
+        let mut last_progress_time = Instant::now();
+        let times = read_capture_times(paths, |done, total| {
+            if last_progress_time.elapsed() >= CAPTURE_TIME_PROGRESS_INTERVAL {
+                last_progress_time = Instant::now();
+                proxy
+                    .send_event(CustomEvent::CaptureTimeProgress(done, total))
+                    .log_err();
+            }
+        });
+        proxy
+            .send_event(CustomEvent::CaptureTimesLoaded(times))
+            .log_err();
     });
 }
@@ -0,0 +1,193 @@
+use anyhow::{anyhow, Result};
+use image::{DynamicImage, ImageBuffer, ImageFormat};
+use std::fs;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use tiff::decoder::{Decoder, DecodingResult};
+
+const ICO_HEADER_SIZE: usize = 6;
+const ICO_ENTRY_SIZE: usize = 16;
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+const PAGE_SEPARATOR: &str = "#page=";
+
+/// Path of a TIFF page shown as a separate slide, e.g. "scan.tif#page=2"
+pub fn page_path(path: &Path, page: usize) -> PathBuf {
+    let mut s = path.as_os_str().to_owned();
+    s.push(format!("{}{}", PAGE_SEPARATOR, page + 1));
+    PathBuf::from(s)
+}
+
+/// Split the page path into the file path and the 0-based page index,
+/// None if the path is not a page path or a file with that name exists
+pub fn split_page_path(path: &Path) -> Option<(PathBuf, usize)> {
+    let (file, page) = path.to_str()?.rsplit_once(PAGE_SEPARATOR)?;
+    let page = page.parse::<usize>().ok()?.checked_sub(1)?;
+    if path.is_file() {
+        return None;
+    }
+    Some((PathBuf::from(file), page))
+}
+
+/// The file of the image, the TIFF file of a page path
+pub fn file_path(path: &Path) -> PathBuf {
+    split_page_path(path).map_or_else(|| path.to_path_buf(), |(file, _)| file)
+}
+
+/// Open the largest image of a multi-image ICO or TIFF file,
+/// None for the other formats and the files with one image
+pub fn open_largest(path: &Path) -> Result<Option<DynamicImage>> {
+    match ImageFormat::from_path(path) {
+        Ok(ImageFormat::Ico) => open_largest_ico(path),
+        Ok(ImageFormat::Tiff) => {
+            let sizes = tiff_page_sizes(path)?;
+            if sizes.len() <= 1 {
+                return Ok(None);
+            }
+            let largest = largest_index(&sizes);
+            open_tiff_page(path, largest).map(Some)
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Number of the pages in the TIFF file
+pub fn tiff_page_count(path: &Path) -> Result<usize> {
+    Ok(tiff_page_sizes(path)?.len())
+}
+
+/// Decode a page of the TIFF file, 8 and 16 bits per sample gray and RGB(A) only
+pub fn open_tiff_page(path: &Path, page: usize) -> Result<DynamicImage> {
+    let mut decoder = Decoder::new(BufReader::new(fs::File::open(path)?))?;
+    for _ in 0..page {
+        if !decoder.more_images() {
+            return Err(anyhow!("{}: no page {}", path.display(), page + 1));
+        }
+        decoder.next_image()?;
+    }
+
+    let (width, height) = decoder.dimensions()?;
+    let color_type = decoder.colortype()?;
+    let img = match (color_type, decoder.read_image()?) {
+        (tiff::ColorType::Gray(8), DecodingResult::U8(v)) => {
+            ImageBuffer::from_raw(width, height, v).map(DynamicImage::ImageLuma8)
+        }
+        (tiff::ColorType::GrayA(8), DecodingResult::U8(v)) => {
+            ImageBuffer::from_raw(width, height, v).map(DynamicImage::ImageLumaA8)
+        }
+        (tiff::ColorType::RGB(8), DecodingResult::U8(v)) => {
+            ImageBuffer::from_raw(width, height, v).map(DynamicImage::ImageRgb8)
+        }
+        (tiff::ColorType::RGBA(8), DecodingResult::U8(v)) => {
+            ImageBuffer::from_raw(width, height, v).map(DynamicImage::ImageRgba8)
+        }
+        (tiff::ColorType::Gray(16), DecodingResult::U16(v)) => {
+            ImageBuffer::from_raw(width, height, v).map(DynamicImage::ImageLuma16)
+        }
+        (tiff::ColorType::GrayA(16), DecodingResult::U16(v)) => {
+            ImageBuffer::from_raw(width, height, v).map(DynamicImage::ImageLumaA16)
+        }
+        (tiff::ColorType::RGB(16), DecodingResult::U16(v)) => {
+            ImageBuffer::from_raw(width, height, v).map(DynamicImage::ImageRgb16)
+        }
+        (tiff::ColorType::RGBA(16), DecodingResult::U16(v)) => {
+            ImageBuffer::from_raw(width, height, v).map(DynamicImage::ImageRgba16)
+        }
+        (color_type, _) => {
+            return Err(anyhow!(
+                "{}: unsupported TIFF color type {:?}",
+                path.display(),
+                color_type
+            ))
+        }
+    };
+    img.ok_or_else(|| anyhow!("{}: invalid TIFF page {}", path.display(), page + 1))
+}
+
+/// Sizes of the TIFF pages in the file order
+fn tiff_page_sizes(path: &Path) -> Result<Vec<(u32, u32)>> {
+    let mut decoder = Decoder::new(BufReader::new(fs::File::open(path)?))?;
+    let mut sizes = vec![decoder.dimensions()?];
+    while decoder.more_images() {
+        decoder.next_image()?;
+        sizes.push(decoder.dimensions()?);
+    }
+    Ok(sizes)
+}
+
+/// Decode the largest icon, it's copied into a single icon file for the image decoder
+fn open_largest_ico(path: &Path) -> Result<Option<DynamicImage>> {
+    let data = fs::read(path)?;
+    let header = data
+        .get(..ICO_HEADER_SIZE)
+        .ok_or_else(|| anyhow!("{}: invalid icon header", path.display()))?;
+    let count = u16::from_le_bytes([header[4], header[5]]) as usize;
+    if count <= 1 {
+        return Ok(None);
+    }
+
+    // Directory entry: width, height, colors, reserved, planes, bit count, size, offset
+    let mut entries = Vec::with_capacity(count);
+    for i in 0..count {
+        let start = ICO_HEADER_SIZE + i * ICO_ENTRY_SIZE;
+        let entry = data
+            .get(start..start + ICO_ENTRY_SIZE)
+            .ok_or_else(|| anyhow!("{}: invalid icon directory", path.display()))?;
+        let size = u32::from_le_bytes([entry[8], entry[9], entry[10], entry[11]]) as usize;
+        let offset = u32::from_le_bytes([entry[12], entry[13], entry[14], entry[15]]) as usize;
+        let image_data = data
+            .get(offset..offset.saturating_add(size))
+            .ok_or_else(|| anyhow!("{}: invalid icon entry {}", path.display(), i))?;
+        entries.push((entry, image_data));
+    }
+
+    let sizes: Vec<(u32, u32)> = entries
+        .iter()
+        .map(|(entry, image_data)| ico_entry_size(entry, image_data))
+        .collect();
+    let (entry, image_data) = entries[largest_index(&sizes)];
+
+    let mut single = Vec::with_capacity(ICO_HEADER_SIZE + ICO_ENTRY_SIZE + image_data.len());
+    single.extend_from_slice(&header[..4]);
+    single.extend_from_slice(&1u16.to_le_bytes());
+    single.extend_from_slice(&entry[..12]);
+    single.extend_from_slice(&((ICO_HEADER_SIZE + ICO_ENTRY_SIZE) as u32).to_le_bytes());
+    single.extend_from_slice(image_data);
+
+    Ok(Some(image::load_from_memory_with_format(
+        &single,
+        ImageFormat::Ico,
+    )?))
+}
+
+/// Size of the icon, read from the PNG header since the directory stores 256 or more as 0
+fn ico_entry_size(entry: &[u8], image_data: &[u8]) -> (u32, u32) {
+    if image_data.starts_with(&PNG_SIGNATURE) && image_data.len() >= 24 {
+        let width = u32::from_be_bytes([
+            image_data[16],
+            image_data[17],
+            image_data[18],
+            image_data[19],
+        ]);
+        let height = u32::from_be_bytes([
+            image_data[20],
+            image_data[21],
+            image_data[22],
+            image_data[23],
+        ]);
+        return (width, height);
+    }
+    let dimension = |v: u8| if v == 0 { 256 } else { v as u32 };
+    (dimension(entry[0]), dimension(entry[1]))
+}
+
+/// Index of the largest size by the pixel area, the first one on ties
+fn largest_index(sizes: &[(u32, u32)]) -> usize {
+    let mut largest = 0;
+    for (i, (width, height)) in sizes.iter().enumerate() {
+        let (largest_width, largest_height) = sizes[largest];
+        if *width as u64 * *height as u64 > largest_width as u64 * largest_height as u64 {
+            largest = i;
+        }
+    }
+    largest
+}
@@ -0,0 +1,72 @@
+use anyhow::{anyhow, Result};
+use futures::executor::block_on;
+use std::num::NonZeroU32;
+
+/// Buffer to read a 4 bytes per pixel texture back from the GPU,
+/// the buffer rows are padded for the texture copy
+pub struct ReadbackBuffer {
+    size: wgpu::Extent3d,
+    buffer: wgpu::Buffer,
+    padded_bytes_per_row: u32,
+}
+
+impl ReadbackBuffer {
+    pub fn new(device: &wgpu::Device, label: &str, size: wgpu::Extent3d) -> Self {
+        // The buffer rows must be aligned for the texture copy
+        let unpadded_bytes_per_row = 4 * size.width;
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = (unpadded_bytes_per_row + align - 1) / align * align;
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: (padded_bytes_per_row * size.height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::COPY_DST | wgpu::BufferUsage::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            size,
+            buffer,
+            padded_bytes_per_row,
+        }
+    }
+
+    /// Copy the texture of the same size to the buffer
+    pub fn copy_from(&self, encoder: &mut wgpu::CommandEncoder, texture: &wgpu::Texture) {
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &self.buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(self.padded_bytes_per_row),
+                    rows_per_image: NonZeroU32::new(self.size.height),
+                },
+            },
+            self.size,
+        );
+    }
+
+    /// Map the buffer after the copy is submitted, return the pixels without the row padding
+    pub fn read(self, device: &wgpu::Device) -> Result<Vec<u8>> {
+        let buffer_slice = self.buffer.slice(..);
+        let mapping = buffer_slice.map_async(wgpu::MapMode::Read);
+        device.poll(wgpu::Maintain::Wait);
+        block_on(mapping).map_err(|_| anyhow!("failed to map the readback buffer."))?;
+
+        let unpadded_bytes_per_row = (4 * self.size.width) as usize;
+        let mut pixels = Vec::with_capacity(unpadded_bytes_per_row * self.size.height as usize);
+        {
+            let data = buffer_slice.get_mapped_range();
+            for row in data.chunks(self.padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..unpadded_bytes_per_row]);
+            }
+        }
+        self.buffer.unmap();
+
+        Ok(pixels)
+    }
+}
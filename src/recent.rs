@@ -0,0 +1,66 @@
+use crate::image_loader::{is_remote_path, ImageLoader};
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const RECENT_FILE_EXTENSION: &str = "recent";
+const MAX_RECENT_PATHS: usize = 10;
+
+/// Recently opened folders and playlists, the most recent first
+#[derive(Debug, Clone)]
+pub struct RecentPaths {
+    file_path: PathBuf,
+    pub paths: Vec<PathBuf>,
+}
+
+impl RecentPaths {
+    /// Load the list, the paths which no longer exist are dropped
+    pub fn load(file_path: PathBuf) -> Self {
+        let paths = match fs::read_to_string(&file_path) {
+            Ok(text) => text
+                .lines()
+                .map(PathBuf::from)
+                .filter(|p| p.exists())
+                .take(MAX_RECENT_PATHS)
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+
+        Self { file_path, paths }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let lines: Vec<_> = self.paths.iter().map(|p| p.to_string_lossy()).collect();
+        fs::write(&self.file_path, lines.join("\n"))?;
+
+        Ok(())
+    }
+
+    pub fn most_recent(&self) -> Option<&PathBuf> {
+        self.paths.first()
+    }
+
+    /// Move the root of the path to the top, the images are stored as the parent folder
+    pub fn add(&mut self, path: &Path) {
+        if is_remote_path(path) {
+            return;
+        }
+
+        let root = if path.is_file() && !ImageLoader::is_playlist(path) {
+            match path.parent() {
+                Some(parent) => parent,
+                None => return,
+            }
+        } else {
+            path
+        };
+        let root = match fs::canonicalize(root) {
+            Ok(v) => v,
+            Err(_) => return,
+        };
+
+        self.paths.retain(|p| p != &root);
+        self.paths.insert(0, root);
+        self.paths.truncate(MAX_RECENT_PATHS);
+    }
+}
@@ -0,0 +1,156 @@
+use crate::logger::ResultLogging;
+use crate::CustomEvent;
+use anyhow::{anyhow, Result};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use winit::event_loop::EventLoopProxy;
+
+/// Commands received from the control port
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoteCommand {
+    Next,
+    Prev,
+    /// 1-based position, the same as the position display
+    Goto(usize),
+    Pause,
+    Play,
+    Fullscreen,
+    Quit,
+}
+
+impl RemoteCommand {
+    /// Parse a line command, e.g. `next`, `goto 5`
+    pub fn parse(line: &str) -> Result<Self> {
+        let mut words = line.split_whitespace();
+        let name = words.next().unwrap_or_default().to_ascii_lowercase();
+        let command = match name.as_str() {
+            "next" => RemoteCommand::Next,
+            "prev" => RemoteCommand::Prev,
+            "goto" => {
+                let index = words
+                    .next()
+                    .ok_or_else(|| anyhow!("missing index"))?
+                    .parse::<usize>()?;
+                if index == 0 {
+                    return Err(anyhow!("index starts from 1"));
+                }
+                RemoteCommand::Goto(index)
+            }
+            "pause" => RemoteCommand::Pause,
+            "play" => RemoteCommand::Play,
+            "fullscreen" => RemoteCommand::Fullscreen,
+            "quit" => RemoteCommand::Quit,
+            "" => return Err(anyhow!("empty command")),
+            _ => return Err(anyhow!("unknown command '{}'", name)),
+        };
+
+        if words.next().is_some() {
+            return Err(anyhow!("too many arguments"));
+        }
+
+        Ok(command)
+    }
+}
+
+/// Listen to the control port, each connection is handled in a new thread
+pub fn spawn_server(address: &str, port: u16, proxy: EventLoopProxy<CustomEvent>) -> Result<()> {
+    let listener = TcpListener::bind((address, port))?;
+    log::info!("control port: {}", listener.local_addr()?);
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let proxy = proxy.clone();
+                    std::thread::spawn(move || handle_connection(stream, proxy).log_info());
+                }
+                Err(err) => log::warn!("control port: {}", err),
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Read line commands until the connection is closed,
+/// an HTTP request (e.g. `POST /goto/5`) is answered once
+fn handle_connection(stream: TcpStream, proxy: EventLoopProxy<CustomEvent>) -> Result<()> {
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+
+        if let Some((method, path)) = http_request(&line) {
+            let (method, path) = (method.to_owned(), path.to_owned());
+            // The browsers send the Origin header with the requests of the web pages
+            let mut has_origin = false;
+            loop {
+                line.clear();
+                if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, _)) = line.split_once(':') {
+                    has_origin |= name.trim().eq_ignore_ascii_case("origin");
+                }
+            }
+
+            let (status, reply) = if method != "POST" {
+                ("405 Method Not Allowed", "ERR use POST".to_owned())
+            } else if has_origin {
+                (
+                    "403 Forbidden",
+                    "ERR requests from web pages are not allowed".to_owned(),
+                )
+            } else {
+                match run_command(&path.trim_matches('/').replace('/', " "), &proxy) {
+                    Ok(_) => ("200 OK", "OK".to_owned()),
+                    Err(err) => ("400 Bad Request", format!("ERR {}", err)),
+                }
+            };
+            write!(
+                writer,
+                "HTTP/1.0 {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}\r\n",
+                status,
+                reply.len() + 2,
+                reply
+            )?;
+            return Ok(());
+        }
+
+        let command_line = line.trim();
+        if command_line.is_empty() {
+            continue;
+        }
+        let reply = match run_command(command_line, &proxy) {
+            Ok(_) => "OK".to_owned(),
+            Err(err) => format!("ERR {}", err),
+        };
+        writeln!(writer, "{}", reply)?;
+    }
+}
+
+/// Parse the command and send it to the event loop
+fn run_command(command_line: &str, proxy: &EventLoopProxy<CustomEvent>) -> Result<()> {
+    let command = RemoteCommand::parse(command_line)?;
+    proxy
+        .send_event(CustomEvent::RemoteCommand(command))
+        .map_err(|_| anyhow!("event loop closed"))
+}
+
+/// Get the method and the path of an HTTP request line, e.g. `POST /next HTTP/1.1`
+fn http_request(line: &str) -> Option<(&str, &str)> {
+    let mut words = line.split_whitespace();
+    match (words.next(), words.next(), words.next()) {
+        (Some(method), Some(path), Some(version))
+            if version.starts_with("HTTP/") && method.chars().all(|c| c.is_ascii_uppercase()) =>
+        {
+            Some((method, path))
+        }
+        _ => None,
+    }
+}
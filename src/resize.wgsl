@@ -0,0 +1,51 @@
+// Vertex shader
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] tex_coords: vec2<f32>;
+};
+
+// Full screen triangle
+[[stage(vertex)]]
+fn main([[builtin(vertex_index)]] index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.tex_coords = uv;
+    out.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    return out;
+}
+
+// Fragment shader
+
+[[block]]
+struct Params {
+    dst_width: f32;
+    dst_height: f32;
+};
+
+[[group(0), binding(0)]]
+var t_src: texture_2d<f32>;
+[[group(0), binding(1)]]
+var s_src: sampler;
+[[group(0), binding(2)]]
+var<uniform> params: Params;
+
+// Average 4x4 bilinear samples in the source area of the destination pixel
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let pixel = vec2<f32>(1.0 / params.dst_width, 1.0 / params.dst_height);
+    var color: vec4<f32> = vec4<f32>(0.0, 0.0, 0.0, 0.0);
+    var y: i32 = 0;
+    loop {
+        if (y >= 4) { break; }
+        var x: i32 = 0;
+        loop {
+            if (x >= 4) { break; }
+            let offset = ((vec2<f32>(f32(x), f32(y)) + 0.5) / 4.0) - 0.5;
+            color = color + textureSampleLevel(t_src, s_src, in.tex_coords + offset * pixel, 0.0);
+            x = x + 1;
+        }
+        y = y + 1;
+    }
+    return color / 16.0;
+}
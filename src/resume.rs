@@ -0,0 +1,87 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+pub const RESUME_FILE_EXTENSION: &str = "resume";
+
+/// Runtime state saved to restore the slideshow on the next launch
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ResumeState {
+    pub config_hash: String,
+    pub paths_hash: String,
+    pub current_index: usize,
+    pub current_path: Option<PathBuf>,
+    pub current_timer_secs: u32,
+    pub paused: bool,
+    pub pause_at_last: bool,
+}
+
+impl ResumeState {
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = &fs::read_to_string(path)?;
+        let resume_state: Self = toml::from_str(data)?;
+
+        Ok(resume_state)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, toml::to_string(self)?)?;
+
+        Ok(())
+    }
+
+    /// Find the index to restore in the scanned paths
+    pub fn restore_index(&self, scanned_paths: &[PathBuf]) -> Option<usize> {
+        if scanned_paths.is_empty() {
+            return None;
+        }
+
+        if self.paths_hash == hash_paths(scanned_paths)
+            && scanned_paths.get(self.current_index) == self.current_path.as_ref()
+        {
+            return Some(self.current_index);
+        }
+
+        // The files were added or removed
+        if let Some(path) = &self.current_path {
+            if let Some(index) = scanned_paths.iter().position(|v| v == path) {
+                return Some(index);
+            }
+        }
+
+        let index = self.current_index.min(scanned_paths.len() - 1);
+        log::warn!(
+            "image paths changed since the last run, resume from {} instead of {}",
+            index,
+            self.current_index
+        );
+        Some(index)
+    }
+}
+
+/// Get the resume file path ('<config file>.resume')
+pub fn get_resume_file_path(conf_path: &Path) -> PathBuf {
+    let mut path = conf_path.as_os_str().to_owned();
+    path.push(".");
+    path.push(RESUME_FILE_EXTENSION);
+    PathBuf::from(path)
+}
+
+pub fn hash_config_path(conf_path: &Path) -> String {
+    let mut hasher = DefaultHasher::new();
+    conf_path.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Hash the set of paths, the order is ignored
+pub fn hash_paths(paths: &[PathBuf]) -> String {
+    let mut sorted: Vec<_> = paths.iter().collect();
+    sorted.sort();
+
+    let mut hasher = DefaultHasher::new();
+    sorted.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
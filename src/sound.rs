@@ -0,0 +1,54 @@
+#[cfg(not(feature = "sound"))]
+use anyhow::anyhow;
+use anyhow::Result;
+use std::path::Path;
+
+/// Sound played when the slideshow advances by the timer,
+/// decoded once and played on the kept output stream
+#[cfg(feature = "sound")]
+pub struct AdvanceSound {
+    // The output stops when the stream is dropped
+    _stream: rodio::OutputStream,
+    handle: rodio::OutputStreamHandle,
+    samples: rodio::source::Buffered<rodio::Decoder<std::io::BufReader<std::fs::File>>>,
+    volume: f32,
+}
+
+#[cfg(feature = "sound")]
+impl AdvanceSound {
+    pub fn new(path: &Path, volume: f32) -> Result<Self> {
+        use rodio::Source;
+
+        let (stream, handle) = rodio::OutputStream::try_default()?;
+        let file = std::io::BufReader::new(std::fs::File::open(path)?);
+        let samples = rodio::Decoder::new(file)?.buffered();
+        Ok(Self {
+            _stream: stream,
+            handle,
+            samples,
+            volume,
+        })
+    }
+
+    pub fn play(&self) -> Result<()> {
+        use rodio::Source;
+
+        let source = self.samples.clone().amplify(self.volume);
+        self.handle.play_raw(source.convert_samples())?;
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "sound"))]
+pub struct AdvanceSound;
+
+#[cfg(not(feature = "sound"))]
+impl AdvanceSound {
+    pub fn new(_path: &Path, _volume: f32) -> Result<Self> {
+        Err(anyhow!("sound is not supported in this build."))
+    }
+
+    pub fn play(&self) -> Result<()> {
+        Ok(())
+    }
+}
@@ -1,7 +1,16 @@
-use crate::config;
-use crate::image_loader::ImageLoader;
+use crate::capture::WindowCapture;
+use crate::config::{self, Corner};
+use crate::filmstrip::{self, FilmstripRenderer};
+use crate::gpu_resize::GpuResizer;
+use crate::histogram::Histogram;
+use crate::image_loader::{is_remote_path, AnimatedImage, ImageCache, ImageLoader, THUMBNAIL_SIZE};
 use crate::logger::ResultLogging;
+use crate::multi_image;
+use crate::overlay::{OverlayRenderer, Rect};
+use crate::resume::{self, ResumeState};
+use crate::sound::AdvanceSound;
 use crate::texture;
+use crate::transition;
 use crate::utils::*;
 use crate::CustomEvent;
 use crate::TimerState;
@@ -9,23 +18,93 @@ use anyhow::{anyhow, Result};
 use font_kit::{
     family_name::FamilyName, handle::Handle, properties::Properties, source::SystemSource,
 };
+use futures::executor::block_on;
 use futures::task::SpawnExt;
 use image::Pixel;
 use rand::prelude::*;
-use std::path::PathBuf;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::sync::{mpsc, Arc, Mutex};
 use std::time::Duration;
 use std::time::Instant;
 use wgpu::util::DeviceExt;
 use wgpu_glyph::{
-    ab_glyph, GlyphBrushBuilder, HorizontalAlign, Layout, Section, Text, VerticalAlign,
+    ab_glyph, FontId, GlyphBrushBuilder, GlyphCruncher, HorizontalAlign, Layout, Section, Text,
+    VerticalAlign,
 };
 use winit::window::Fullscreen;
-use winit::{dpi::PhysicalSize, event_loop::EventLoopProxy, window::Window};
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event_loop::EventLoopProxy,
+    window::Window,
+};
 
-const TRANSITION_MAX_MODE_IDX: i32 = 21; // See the transition shader file
 const FONT_SIZE_DROP_HERE_TEXT: f32 = 20.0;
+const PROGRESS_BAR_HEIGHT: f32 = 4.0;
+const COMPARE_DIVIDER_WIDTH: f32 = 2.0;
+/// Size of the filmstrip cells in logical pixels
+const FILMSTRIP_CELL_SIZE: f32 = 64.0;
+const FILMSTRIP_CELL_PADDING: f32 = 4.0;
+const FILMSTRIP_FRAME_WIDTH: f32 = 2.0;
+const FILMSTRIP_BG_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+const HISTOGRAM_SIZE: (f32, f32) = (256.0, 100.0);
+const MIN_ZOOM: f32 = 1.0;
+const MAX_ZOOM: f32 = 16.0;
+const GRID_CELL_PADDING: u32 = 8;
+const SAVED_IMAGE_SUFFIX: &str = "_sldshow";
+const FAVORITE_MARK: &str = "★";
+/// System fonts for the CJK glyphs and the emoji, the first found one of each list is used
+const FALLBACK_CJK_FONTS: &[&str] = &[
+    "Yu Gothic UI",
+    "Meiryo",
+    "Microsoft YaHei",
+    "Malgun Gothic",
+    "Hiragino Sans",
+    "PingFang SC",
+    "Noto Sans CJK JP",
+    "Source Han Sans",
+    "WenQuanYi Micro Hei",
+];
+const FALLBACK_EMOJI_FONTS: &[&str] = &["Segoe UI Emoji", "Segoe UI Symbol", "Noto Emoji"];
+const HELP_PANEL_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.75];
+const HELP_LINE_SPACING: f32 = 1.3;
+const GRID_SELECTION_BORDER: u32 = 3;
+/// Gap around the images of the contact sheet in logical pixels
+const SHEET_CELL_PADDING: f64 = 4.0;
+const ASPECT_SNAP_DEBOUNCE: Duration = Duration::from_millis(200);
+/// Navigation faster than this snaps to the image without the transition
+const SCRUB_INTERVAL: Duration = Duration::from_millis(150);
+/// The next image not decoded yet is waited for up to this, decoded on the main thread after it,
+/// or the error placeholder is shown
+const NEXT_IMAGE_WAIT_TIMEOUT: Duration = Duration::from_secs(3);
+/// Longer wait with `wait_for_load`, e.g. on network drives
+const LOAD_WAIT_TIMEOUT: Duration = Duration::from_secs(10);
+/// The loading indicator is shown if the next image is not decoded in this
+const NEXT_IMAGE_INDICATOR_DELAY: Duration = Duration::from_millis(150);
+const NEXT_IMAGE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+/// Scroll amount of the tall images relative to the texture height
+const SCROLL_STEP_RATIO: f32 = 0.25;
+/// Time between the steps of the pixel shift of a paused image
+pub const PIXEL_SHIFT_INTERVAL: Duration = Duration::from_secs(60);
+/// Largest pixel shift in physical pixels
+const PIXEL_SHIFT_AMPLITUDE: f32 = 2.0;
+/// Steps of a whole cycle of the pixel shift pattern
+const PIXEL_SHIFT_CYCLE: u32 = 24;
+/// Steps between the brief blanks of the pixel shift
+const PIXEL_SHIFT_BLANK_STEPS: u32 = 60;
+pub const PIXEL_SHIFT_BLANK_DURATION: Duration = Duration::from_secs(2);
+/// Window size change relative to the texture size to resize the images again
+const TEXTURE_RESIZE_MIN_RATIO: f32 = 0.1;
+const BRIGHTNESS_RANGE: (f32, f32) = (-1.0, 1.0);
+const CONTRAST_RANGE: (f32, f32) = (0.0, 4.0);
+const GAMMA_RANGE: (f32, f32) = (0.1, 4.0);
+const CHECKER_SIZE: f64 = 8.0;
+const CHECKER_COLORS: [image::Rgba<u8>; 2] = [
+    image::Rgba([204, 204, 204, 255]),
+    image::Rgba([255, 255, 255, 255]),
+];
 
 type IsTransitionEnd = bool;
 
@@ -36,13 +115,19 @@ pub struct FullscreenController {
     pub last_time: Instant,
     pub rate_limit: Duration,
     pub window: Rc<Window>,
+    /// Cover all monitors with an undecorated window instead of the real fullscreen
+    pub span: bool,
+    /// Window decorations to restore after the spanned fullscreen
+    pub decorations: bool,
+    pub restore_rect: Option<(PhysicalPosition<i32>, PhysicalSize<u32>)>,
 }
 
 impl FullscreenController {
     pub fn toggle(&mut self) {
-        match self.window.fullscreen() {
-            None => self.enable(),
-            Some(_) => self.disable(),
+        if self.active {
+            self.disable();
+        } else {
+            self.enable();
         }
     }
 
@@ -50,10 +135,14 @@ impl FullscreenController {
         if self.limit_reached() {
             return;
         }
-        const FULLSCREEN_TYPE: Option<Fullscreen> = Some(Fullscreen::Borderless(None));
-        self.window.set_fullscreen(FULLSCREEN_TYPE);
+        if self.span {
+            self.enable_span();
+        } else {
+            const FULLSCREEN_TYPE: Option<Fullscreen> = Some(Fullscreen::Borderless(None));
+            self.window.set_fullscreen(FULLSCREEN_TYPE);
+            self.size = self.window.current_monitor().and_then(|f| f.size().into());
+        }
         self.active = true;
-        self.size = self.window.current_monitor().and_then(|f| f.size().into());
         self.last_time = Instant::now();
     }
 
@@ -61,17 +150,134 @@ impl FullscreenController {
         if self.limit_reached() {
             return;
         }
-        self.window.set_fullscreen(None);
+        if let Some((position, size)) = self.restore_rect.take() {
+            self.window.set_decorations(self.decorations);
+            self.window.set_outer_position(position);
+            self.window.set_inner_size(size);
+        } else {
+            self.window.set_fullscreen(None);
+        }
         self.active = false;
         self.size = None;
         self.last_time = Instant::now();
     }
 
+    /// Move the window to the next monitor, returns the new monitor index and the monitor count
+    pub fn move_to_next_monitor(&mut self) -> Option<(usize, usize)> {
+        // Query the monitors every time, they can be connected or disconnected while running
+        let monitors: Vec<_> = self.window.available_monitors().collect();
+        if monitors.is_empty() {
+            return None;
+        }
+        let current_index = self
+            .window
+            .current_monitor()
+            .and_then(|current| monitors.iter().position(|v| v == &current));
+        let index = current_index.map_or(0, |i| (i + 1) % monitors.len());
+        let monitor = &monitors[index];
+
+        if self.active && !self.span {
+            self.window
+                .set_fullscreen(Some(Fullscreen::Borderless(Some(monitor.clone()))));
+            self.size = Some(monitor.size());
+        } else if !self.active {
+            set_window_to_center(&self.window, monitor);
+        }
+
+        Some((index, monitors.len()))
+    }
+
+    /// Position and size of the window outside the fullscreen, None in the real fullscreen
+    pub fn windowed_rect(&self) -> Option<(PhysicalPosition<i32>, PhysicalSize<u32>)> {
+        if !self.active {
+            let position = self.window.outer_position().ok()?;
+            return Some((position, self.window.inner_size()));
+        }
+        self.restore_rect
+    }
+
+    /// Cover the bounding box of all monitors
+    fn enable_span(&mut self) {
+        let mut monitors = self.window.available_monitors();
+        let first = match monitors.next() {
+            Some(v) => v,
+            None => return,
+        };
+        let (mut left, mut top) = (first.position().x, first.position().y);
+        let mut right = left + first.size().width as i32;
+        let mut bottom = top + first.size().height as i32;
+        for monitor in monitors {
+            let position = monitor.position();
+            let size = monitor.size();
+            left = left.min(position.x);
+            top = top.min(position.y);
+            right = right.max(position.x + size.width as i32);
+            bottom = bottom.max(position.y + size.height as i32);
+        }
+
+        let position = self.window.outer_position().unwrap_or_default();
+        self.restore_rect = Some((position, self.window.inner_size()));
+
+        let size = PhysicalSize::new((right - left) as u32, (bottom - top) as u32);
+        self.window.set_decorations(false);
+        self.window
+            .set_outer_position(PhysicalPosition::new(left, top));
+        self.window.set_inner_size(size);
+        self.size = Some(size);
+    }
+
     fn limit_reached(&self) -> bool {
         self.last_time.elapsed() <= self.rate_limit
     }
 }
 
+/// Snap the resized window to an aspect ratio
+#[derive(Debug, Default)]
+pub struct AspectLock {
+    /// The size requested by the last snap and the time, its resize event is not snapped again
+    last_request: Option<(PhysicalSize<u32>, Instant)>,
+}
+
+impl AspectLock {
+    /// Get the corrected size, the dimension changed more by the user is kept
+    pub fn snap(
+        &mut self,
+        previous_size: PhysicalSize<u32>,
+        new_size: PhysicalSize<u32>,
+        aspect: f32,
+    ) -> Option<PhysicalSize<u32>> {
+        if let Some((size, time)) = self.last_request {
+            if size == new_size || time.elapsed() < ASPECT_SNAP_DEBOUNCE {
+                return None;
+            }
+        }
+        if new_size.width == 0 || new_size.height == 0 || !aspect.is_normal() {
+            return None;
+        }
+
+        let width_change = (new_size.width as f32 / previous_size.width.max(1) as f32 - 1.0).abs();
+        let height_change =
+            (new_size.height as f32 / previous_size.height.max(1) as f32 - 1.0).abs();
+        let size = if width_change >= height_change {
+            PhysicalSize::new(
+                new_size.width,
+                (new_size.width as f32 / aspect).round().max(1.0) as u32,
+            )
+        } else {
+            PhysicalSize::new(
+                (new_size.height as f32 * aspect).round().max(1.0) as u32,
+                new_size.height,
+            )
+        };
+        if size == new_size {
+            return None;
+        }
+
+        self.last_request = Some((size, Instant::now()));
+        Some(size)
+    }
+}
+
 #[rustfmt::skip]
 const QUAD_VERTICES: &[Vertex] = &[
     Vertex { position: [-1.0, 1.0, 0.0], tex_coords: [0.0, 0.0] },
@@ -109,6 +315,8 @@ impl Vertex {
     }
 }
 
+/// Uniform buffer of the transition shader, keep in sync with `Uniforms` in transition.wgsl
+/// and the custom shader section of README
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Uniforms {
@@ -117,6 +325,23 @@ pub struct Uniforms {
     pub mode: i32,
     pub resized_window_scale: [f32; 2],
     pub bg: [f32; 4],
+    pub zoom: f32,
+    pub pan: [f32; 2],
+    pub brightness: f32,
+    pub contrast: f32,
+    pub gamma: f32,
+    pub flip_h: f32,
+    pub flip_v: f32,
+    pub slide_dir: f32,
+    /// Window x of the compare split in 0.0-1.0
+    pub split_x: f32,
+    /// Free rotation of the view, clockwise
+    pub rotation_deg: f32,
+    /// Width / height of the window
+    pub window_aspect: f32,
+    /// Brightness multiplier of the dim schedule
+    pub dim: f32,
+    _padding: [f32; 2],
 }
 
 impl Uniforms {
@@ -127,6 +352,19 @@ impl Uniforms {
             mode: 0,
             resized_window_scale: [1.0, 1.0],
             bg: [0.0, 0.0, 0.0, 1.0],
+            zoom: 1.0,
+            pan: [0.0, 0.0],
+            brightness: 0.0,
+            contrast: 1.0,
+            gamma: 1.0,
+            flip_h: 0.0,
+            flip_v: 0.0,
+            slide_dir: 1.0,
+            split_x: 0.5,
+            rotation_deg: 0.0,
+            window_aspect: 1.0,
+            dim: 1.0,
+            _padding: [0.0; 2],
         }
     }
 }
@@ -136,13 +374,20 @@ pub struct TransitionState {
     pub direction: f32,
     pub last_time: Instant,
     pub time: f32,
+    /// Longest frame time before the slow frame handling, None to disable
+    pub max_frame_time: Option<f32>,
+    pub slow_frame: config::SlowFrame,
+    pub enabled: bool,
     pub random: bool,
+    pub mode: i32,
+    /// Direction of the slide effect, 1.0 for the next image and -1.0 for the previous image
+    pub slide_dir: f32,
 }
 
 pub struct GraphicsState {
     pub surface: wgpu::Surface,
-    pub device: wgpu::Device,
-    pub queue: wgpu::Queue,
+    pub device: Arc<wgpu::Device>,
+    pub queue: Arc<wgpu::Queue>,
     pub sc_desc: wgpu::SwapChainDescriptor,
     pub swap_chain: wgpu::SwapChain,
     pub inner_size: winit::dpi::PhysicalSize<u32>,
@@ -153,42 +398,114 @@ pub struct GraphicsState {
     pub num_indices: u32,
     pub diffuse_image_temp: image::RgbaImage,
     pub diffuse_textures: [texture::Texture; 2],
+    texture_bind_group_layout: wgpu::BindGroupLayout,
     pub diffuse_bind_group: wgpu::BindGroup,
     pub uniforms: Uniforms,
     pub uniform_buffer: wgpu::Buffer,
     pub uniform_bind_group: wgpu::BindGroup,
     pub bg_color: image::Rgba<u8>,
+    pub bg_pattern: config::BgPattern,
+    /// Color of the bars around the image, the background color if None
+    pub letterbox_color: Option<image::Rgba<u8>>,
+    /// Width of the frame around the image in logical pixels, 0 for no frame
+    pub border_px: u32,
+    pub border_color: image::Rgba<u8>,
     pub text_color: [f32; 4],
     pub show_image_path: bool,
+    pub show_image_details: bool,
+    pub image_details: Option<String>,
     pub font_size_osd: f32,
     pub font_size_image_path: f32,
+    pub osd_position: config::Corner,
     pub glyph_brush: wgpu_glyph::GlyphBrush<()>,
+    /// Fonts of the OSD texts and the image path, the fallback fonts follow the first one
+    font_chain_osd: Vec<FontId>,
+    font_chain_path: Vec<FontId>,
+    pub overlay: OverlayRenderer,
+    filmstrip_renderer: FilmstripRenderer,
+    /// Image indices of the filmstrip slots, the current image in the middle, None if hidden
+    pub filmstrip: Option<Vec<Option<usize>>>,
+    /// Fades out while the cursor is idle
+    pub filmstrip_opacity: f32,
+    pub show_progress_bar: bool,
+    pub progress_bar_hidden: bool,
+    pub progress: Option<(usize, usize)>,
+    pub show_info: bool,
+    pub info: Option<String>,
+    pub show_caption: bool,
+    pub caption: Option<String>,
+    /// Width / height of the current image
+    pub image_aspect: Option<f32>,
+    /// The current image is marked as favorite
+    pub favorite: bool,
+    /// Lines of the keybinding help, None if hidden
+    pub help: Option<Vec<String>>,
+    pub show_histogram: bool,
+    /// Top of the visible area of the tall image in the fit width mode, None to center crop
+    pub scroll_offset: Option<u32>,
+    /// Color of the blank screen, None if the image is shown
+    pub blanked: Option<image::Rgba<u8>>,
+    /// Texture index of the pinned image in the compare mode
+    pub compare: Option<usize>,
+    pub histogram: Option<Arc<Histogram>>,
+    capture_requested: bool,
+    captured_frame: Option<Result<image::RgbaImage>>,
     pub main_texture_index: usize,
     pub dpi_scale_factor: f64,
     pub message: Option<String>,
     pub tx_osd_message_timer: mpsc::Sender<()>,
     minimized: bool,
+    msaa_samples: u32,
+    /// Multisampled render target of the image quad, resolved to the frame
+    msaa_view: Option<wgpu::TextureView>,
 }
 
 impl GraphicsState {
     pub async fn new(
         window: &Window,
         conf: &config::Config,
+        texture_size: PhysicalSize<u32>,
         tx_osd_message_timer: mpsc::Sender<()>,
     ) -> Result<Self> {
         let inner_size = window.inner_size();
         let dpi_scale_factor = window.scale_factor();
 
-        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
-
-        let surface = unsafe { instance.create_surface(window) };
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
-            })
-            .await
-            .ok_or_else(|| anyhow!("failed to retrieve a device (wgpu::Adapter)."))?;
+        let power_preference = match conf.window.gpu_power {
+            config::GpuPower::Low => wgpu::PowerPreference::LowPower,
+            config::GpuPower::High => wgpu::PowerPreference::HighPerformance,
+        };
+        let backends = conf
+            .window
+            .gpu_backend
+            .as_deref()
+            .and_then(Self::parse_backend);
+        // Fall back to the primary backends if the requested one has no adapter
+        let requested = match backends {
+            Some(backends) => {
+                let requested = Self::request_adapter(window, backends, power_preference).await;
+                if requested.is_none() {
+                    log::warn!(
+                        "No GPU adapter for the backend {:?}, use the default backends",
+                        conf.window.gpu_backend.as_deref().unwrap_or_default()
+                    );
+                }
+                requested
+            }
+            None => None,
+        };
+        let (surface, adapter) = match requested {
+            Some(v) => v,
+            None => Self::request_adapter(window, wgpu::BackendBit::PRIMARY, power_preference)
+                .await
+                .ok_or_else(|| anyhow!("failed to retrieve a device (wgpu::Adapter)."))?,
+        };
+        let adapter_info = adapter.get_info();
+        log::info!(
+            "GPU adapter: {} ({:?}, {:?})",
+            adapter_info.name,
+            adapter_info.backend,
+            adapter_info.device_type
+        );
 
         let (device, queue) = adapter
             .request_device(
@@ -201,26 +518,64 @@ impl GraphicsState {
             )
             .await?;
 
-        let render_format = adapter
+        // The textures are sRGB, render to an sRGB target to encode the output consistently
+        let render_format = match adapter
             .get_swap_chain_preferred_format(&surface)
-            .ok_or_else(|| anyhow!("failed to get a texture format."))?;
+            .ok_or_else(|| anyhow!("failed to get a texture format."))?
+        {
+            wgpu::TextureFormat::Bgra8Unorm => wgpu::TextureFormat::Bgra8UnormSrgb,
+            wgpu::TextureFormat::Rgba8Unorm => wgpu::TextureFormat::Rgba8UnormSrgb,
+            format => format,
+        };
 
         let sc_desc = wgpu::SwapChainDescriptor {
             usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
             format: render_format,
             width: inner_size.width,
             height: inner_size.height,
-            present_mode: wgpu::PresentMode::Fifo,
+            present_mode: Self::present_mode(conf.window.vsync),
         };
         let swap_chain = device.create_swap_chain(&surface, &sc_desc);
 
+        let msaa_samples =
+            Self::msaa_sample_count(&adapter, render_format, conf.style.msaa_samples);
+        let msaa_view = Self::create_msaa_view(&device, &sc_desc, msaa_samples);
+
         let bg_color: image::Rgba<u8> = image::Rgba(conf.style.bg_color);
 
-        let font = Self::load_font(conf.style.font_name.as_deref())?;
-        let glyph_brush = GlyphBrushBuilder::using_font(font).build(&device, render_format);
+        // The font of each text falls back to font_name, then to the system sans-serif
+        let font_name_osd = conf
+            .style
+            .font_name_osd
+            .as_ref()
+            .or(conf.style.font_name.as_ref());
+        let font_name_path = conf
+            .style
+            .font_name_path
+            .as_ref()
+            .or(conf.style.font_name.as_ref());
+        let font_osd = Self::load_font(font_name_osd.map(String::as_str))?;
+        let mut glyph_brush = GlyphBrushBuilder::using_font(font_osd).build(&device, render_format);
+        let font_id_path = if font_name_path == font_name_osd {
+            FontId::default()
+        } else {
+            glyph_brush.add_font(Self::load_font(font_name_path.map(String::as_str))?)
+        };
+        let fallback_font_ids: Vec<FontId> = Self::load_fallback_fonts(&conf.style.font_names)
+            .into_iter()
+            .map(|font| glyph_brush.add_font(font))
+            .collect();
+        let font_chain_osd = std::iter::once(FontId::default())
+            .chain(fallback_font_ids.iter().copied())
+            .collect();
+        let font_chain_path = std::iter::once(font_id_path)
+            .chain(fallback_font_ids)
+            .collect();
+        let overlay = OverlayRenderer::new(&device, render_format);
+        let filmstrip_renderer = FilmstripRenderer::new(&device, &queue, render_format)?;
 
         let diffuse_image_temp =
-            image::ImageBuffer::from_pixel(inner_size.width, inner_size.height, bg_color);
+            image::ImageBuffer::from_pixel(texture_size.width, texture_size.height, bg_color);
 
         let diffuse_textures = [
             texture::Texture::from_image(&device, &queue, &diffuse_image_temp, Some("Texture A"))?,
@@ -229,6 +584,7 @@ impl GraphicsState {
 
         let mut uniforms = Uniforms::new();
         uniforms.blend = 1.0;
+        uniforms.window_aspect = inner_size.width.max(1) as f32 / inner_size.height.max(1) as f32;
         for (i, v) in bg_color.channels().iter().enumerate() {
             uniforms.bg[i] = (*v as f32 / 255.0).clamp(0.0, 1.0);
         }
@@ -274,24 +630,8 @@ impl GraphicsState {
                 label: Some("Texture Bind Group Layout"),
             });
 
-        let diffuse_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &texture_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&diffuse_textures[0].view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::TextureView(&diffuse_textures[1].view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: wgpu::BindingResource::Sampler(&diffuse_textures[0].sampler),
-                },
-            ],
-            label: Some("Diffuse Bind Group"),
-        });
+        let diffuse_bind_group =
+            Self::create_diffuse_bind_group(&device, &texture_bind_group_layout, &diffuse_textures);
 
         let uniform_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -301,7 +641,10 @@ impl GraphicsState {
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
                         has_dynamic_offset: false,
-                        min_binding_size: None,
+                        // Checked against the shader when the pipeline is created, not on drawing
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<Uniforms>() as u64
+                        ),
                     },
                     count: None,
                 }],
@@ -317,12 +660,6 @@ impl GraphicsState {
             label: Some("Uniform Bind Group"),
         });
 
-        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
-            label: Some("Shader"),
-            flags: wgpu::ShaderFlags::all(),
-            source: wgpu::ShaderSource::Wgsl(include_str!("transition.wgsl").into()),
-        });
-
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layout"),
@@ -330,39 +667,13 @@ impl GraphicsState {
                 push_constant_ranges: &[],
             });
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "main",
-                buffers: &[Vertex::desc()],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "main",
-                targets: &[wgpu::ColorTargetState {
-                    format: sc_desc.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrite::ALL,
-                }],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                clamp_depth: false,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-        });
+        let render_pipeline = Self::create_transition_pipeline(
+            &device,
+            &render_pipeline_layout,
+            sc_desc.format,
+            msaa_samples,
+            conf.transition.shader_path.as_deref(),
+        );
 
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Vertex Buffer"),
@@ -379,36 +690,170 @@ impl GraphicsState {
 
         Ok(GraphicsState {
             surface,
-            device,
-            queue,
+            device: Arc::new(device),
+            queue: Arc::new(queue),
             sc_desc,
             swap_chain,
             inner_size,
-            texture_size: inner_size,
+            texture_size,
             render_pipeline,
             vertex_buffer,
             index_buffer,
             num_indices,
             diffuse_image_temp,
             diffuse_textures,
+            texture_bind_group_layout,
             diffuse_bind_group,
             uniforms,
             uniform_buffer,
             uniform_bind_group,
             bg_color,
+            bg_pattern: conf.style.bg_pattern,
+            letterbox_color: conf.style.letterbox_color.map(image::Rgba),
+            border_px: conf.style.border_px,
+            border_color: image::Rgba(conf.style.border_color),
             show_image_path: conf.style.show_image_path,
+            show_image_details: conf.style.show_image_details,
+            image_details: None,
             font_size_osd: conf.style.font_size_osd,
             font_size_image_path: conf.style.font_size_image_path,
+            osd_position: conf.style.osd_position,
             text_color: rgba_u8_to_f32(conf.style.text_color),
             glyph_brush,
+            font_chain_osd,
+            font_chain_path,
+            overlay,
+            filmstrip_renderer,
+            filmstrip: None,
+            filmstrip_opacity: 1.0,
+            show_progress_bar: conf.style.show_progress_bar,
+            progress_bar_hidden: false,
+            progress: None,
+            show_info: false,
+            info: None,
+            show_caption: conf.style.show_caption,
+            caption: None,
+            image_aspect: None,
+            favorite: false,
+            help: None,
+            show_histogram: false,
+            scroll_offset: None,
+            blanked: None,
+            compare: None,
+            histogram: None,
+            capture_requested: false,
+            captured_frame: None,
             main_texture_index: 0,
             dpi_scale_factor,
             message: None,
             tx_osd_message_timer,
             minimized: false,
+            msaa_samples,
+            msaa_view,
         })
     }
 
+    /// Create a surface on the backends and request an adapter compatible with it
+    async fn request_adapter(
+        window: &Window,
+        backends: wgpu::BackendBit,
+        power_preference: wgpu::PowerPreference,
+    ) -> Option<(wgpu::Surface, wgpu::Adapter)> {
+        let instance = wgpu::Instance::new(backends);
+        let surface = unsafe { instance.create_surface(window) };
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference,
+                compatible_surface: Some(&surface),
+            })
+            .await?;
+        Some((surface, adapter))
+    }
+
+    /// Parse the backend name of `gpu_backend`, None for the unknown names
+    fn parse_backend(name: &str) -> Option<wgpu::BackendBit> {
+        match name.to_ascii_lowercase().as_str() {
+            "vulkan" => Some(wgpu::BackendBit::VULKAN),
+            "dx12" => Some(wgpu::BackendBit::DX12),
+            "dx11" => Some(wgpu::BackendBit::DX11),
+            "metal" => Some(wgpu::BackendBit::METAL),
+            "gl" => Some(wgpu::BackendBit::GL),
+            _ => {
+                log::warn!("Unknown GPU backend: {}", name);
+                None
+            }
+        }
+    }
+
+    /// Map the vsync mode to the present mode
+    fn present_mode(vsync: config::VsyncMode) -> wgpu::PresentMode {
+        // wgpu 0.9 has no query of the supported present modes, the swap chain falls back to
+        // Fifo with a warning log when the surface doesn't support the mode
+        let present_mode = match vsync {
+            config::VsyncMode::Fifo => wgpu::PresentMode::Fifo,
+            config::VsyncMode::Mailbox => wgpu::PresentMode::Mailbox,
+            config::VsyncMode::Immediate => wgpu::PresentMode::Immediate,
+        };
+        if present_mode != wgpu::PresentMode::Fifo {
+            log::info!(
+                "present mode: {:?}, Fifo if the surface doesn't support it",
+                present_mode
+            );
+        }
+        present_mode
+    }
+
+    /// Validate the requested sample count, 1 if the adapter can't render it
+    fn msaa_sample_count(
+        adapter: &wgpu::Adapter,
+        format: wgpu::TextureFormat,
+        requested: u32,
+    ) -> u32 {
+        if requested <= 1 {
+            return 1;
+        }
+        let renderable = adapter
+            .get_texture_format_features(format)
+            .allowed_usages
+            .contains(wgpu::TextureUsage::RENDER_ATTACHMENT);
+        if renderable && config::SUPPORTED_MSAA_SAMPLES.contains(&requested) {
+            requested
+        } else {
+            log::warn!(
+                "msaa_samples {} is not supported on {:?}, use 1 (supported: {:?})",
+                requested,
+                format,
+                config::SUPPORTED_MSAA_SAMPLES
+            );
+            1
+        }
+    }
+
+    /// Create the multisampled render target of the window size, None without MSAA
+    fn create_msaa_view(
+        device: &wgpu::Device,
+        sc_desc: &wgpu::SwapChainDescriptor,
+        samples: u32,
+    ) -> Option<wgpu::TextureView> {
+        if samples <= 1 {
+            return None;
+        }
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Multisampled Frame"),
+            size: wgpu::Extent3d {
+                width: sc_desc.width,
+                height: sc_desc.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: samples,
+            dimension: wgpu::TextureDimension::D2,
+            format: sc_desc.format,
+            usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
+        });
+        Some(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+    }
+
     pub fn render(&mut self, path: &Option<PathBuf>) -> Result<(), wgpu::SwapChainError> {
         if self.minimized {
             return Ok(());
@@ -420,13 +865,105 @@ impl GraphicsState {
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("Render Encoder"),
             });
+        let mut staging_belt = wgpu::util::StagingBelt::new(1024);
+        self.encode_frame(&mut encoder, &mut staging_belt, &frame.view, path);
+
+        // Draw the same frame again to read it back
+        let capture = if self.capture_requested {
+            self.capture_requested = false;
+            let capture = WindowCapture::new(
+                &self.device,
+                self.sc_desc.format,
+                self.inner_size.width,
+                self.inner_size.height,
+            );
+            self.encode_frame(&mut encoder, &mut staging_belt, &capture.view, path);
+            capture.copy_to_buffer(&mut encoder);
+            Some(capture)
+        } else {
+            None
+        };
+
+        staging_belt.finish();
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        // Recall unused staging buffers
+        let mut local_pool = futures::executor::LocalPool::new();
+        local_pool
+            .spawner()
+            .spawn(staging_belt.recall())
+            .expect("Recall staging belt");
+        local_pool.run_until_stalled();
+
+        if let Some(capture) = capture {
+            self.captured_frame = Some(capture.read(&self.device));
+        }
+
+        Ok(())
+    }
+
+    /// Blank the screen with the color, or show the image again if it's already blanked with it.
+    /// The current frame is kept as is, so showing it again doesn't start a transition
+    pub fn toggle_blank(&mut self, color: image::Rgba<u8>) {
+        self.blanked = if self.blanked == Some(color) {
+            None
+        } else {
+            Some(color)
+        };
+    }
+
+    /// Request to read back the next rendered frame including the OSD
+    pub fn request_capture(&mut self) {
+        self.capture_requested = true;
+    }
+
+    /// Take the frame read back by the last render
+    pub fn take_captured_frame(&mut self) -> Option<Result<image::RgbaImage>> {
+        self.captured_frame.take()
+    }
+
+    /// Record the draw commands of the image, the overlays and the text
+    fn encode_frame(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        staging_belt: &mut wgpu::util::StagingBelt,
+        view: &wgpu::TextureView,
+        path: &Option<PathBuf>,
+    ) {
+        // Blank screen hides the image and the overlays
+        if let Some(color) = self.blanked {
+            let [r, g, b, a] = rgba_u8_to_f32(color.0);
+            encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Blank Pass"),
+                color_attachments: &[wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: r as f64,
+                            g: g as f64,
+                            b: b as f64,
+                            a: a as f64,
+                        }),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            });
+            return;
+        }
 
         {
+            // The overlays and the text are drawn on the resolved frame
+            let (attachment, resolve_target) = match &self.msaa_view {
+                Some(msaa_view) => (msaa_view, Some(view)),
+                None => (view, None),
+            };
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[wgpu::RenderPassColorAttachment {
-                    view: &frame.view,
-                    resolve_target: None,
+                    view: attachment,
+                    resolve_target,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::default(),
                         store: true,
@@ -443,130 +980,911 @@ impl GraphicsState {
             render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
         }
 
+        // Progress bar
+        //   position: bottom
+        if self.show_progress_bar && !self.progress_bar_hidden {
+            if let Some((index, count)) = self.progress {
+                let width = self.inner_size.width as f32;
+                let height = PROGRESS_BAR_HEIGHT * self.dpi_scale_factor as f32;
+                let y = self.inner_size.height as f32 - height;
+                let ratio = (index + 1) as f32 / count.max(1) as f32;
+                self.overlay.queue(Rect {
+                    x: 0.0,
+                    y,
+                    width,
+                    height,
+                    color: rgba_u8_to_f32(self.bg_color.0),
+                });
+                self.overlay.queue(Rect {
+                    x: 0.0,
+                    y,
+                    width: width * ratio,
+                    height,
+                    color: self.text_color,
+                });
+            }
+        }
+
+        // Divider of the compare mode
+        //   position: split x
+        if self.compare.is_some() {
+            let width = COMPARE_DIVIDER_WIDTH * self.dpi_scale_factor as f32;
+            self.overlay.queue(Rect {
+                x: self.uniforms.split_x * self.inner_size.width as f32 - width / 2.0,
+                y: 0.0,
+                width,
+                height: self.inner_size.height as f32,
+                color: self.text_color,
+            });
+        }
+
+        // Histogram
+        //   position: bottom-right
+        let histogram_rect = self.histogram_rect();
+        if let Some((histogram, (x, y, width, height))) = &histogram_rect {
+            for rect in histogram.rects(*x, *y, *width, *height) {
+                self.overlay.queue(rect);
+            }
+        }
+
+        // Keybinding help panel
+        //   position: whole window
+        let help_area = self.help.as_ref().map(|_| {
+            let offset = (self.font_size_osd / 2.0) * self.dpi_scale_factor as f32;
+            let area = (
+                offset,
+                offset,
+                (self.inner_size.width as f32 - offset * 2.0).max(0.0),
+                (self.inner_size.height as f32 - offset * 2.0).max(0.0),
+            );
+            self.overlay.queue(Rect {
+                x: area.0,
+                y: area.1,
+                width: area.2,
+                height: area.3,
+                color: HELP_PANEL_COLOR,
+            });
+            (area, offset)
+        });
+
+        // Filmstrip background and the frame of the current image
+        //   position: bottom
+        let filmstrip_cells = self.filmstrip_cells();
+        if let (Some((_, x, y, size)), Some((_, last_x, _, _))) =
+            (filmstrip_cells.first(), filmstrip_cells.last())
         {
-            let mut staging_belt = wgpu::util::StagingBelt::new(1024);
-            let mut local_pool = futures::executor::LocalPool::new();
-            let local_spawner = local_pool.spawner();
+            let opacity = self.filmstrip_opacity;
+            let mut bg_color = FILMSTRIP_BG_COLOR;
+            bg_color[3] *= opacity;
+            self.overlay.queue(Rect {
+                x: 0.0,
+                y: *y,
+                width: self.inner_size.width as f32,
+                height: *size,
+                color: bg_color,
+            });
+            let current_x = x + (last_x - x) / 2.0;
+            let mut frame_color = self.text_color;
+            frame_color[3] *= opacity;
+            let border = FILMSTRIP_FRAME_WIDTH * self.dpi_scale_factor as f32;
+            for (frame_x, frame_y, width, height) in [
+                (current_x, *y, *size, border),
+                (current_x, y + size - border, *size, border),
+                (current_x, *y, border, *size),
+                (current_x + size - border, *y, border, *size),
+            ] {
+                self.overlay.queue(Rect {
+                    x: frame_x,
+                    y: frame_y,
+                    width,
+                    height,
+                    color: frame_color,
+                });
+            }
+        }
 
-            {
-                let scale_factor = self.dpi_scale_factor as f32;
-                if let Some(path) = path.as_ref().and_then(|p| p.to_str()) {
-                    if self.show_image_path {
-                        // Image file path
-                        //   position: top-left
-                        self.glyph_brush.queue(Section {
-                            screen_position: (4.0, 2.0),
-                            bounds: (self.inner_size.width as f32, self.inner_size.height as f32),
-                            text: vec![Text::new(path)
-                                .with_color(self.text_color)
-                                .with_scale(self.font_size_image_path * scale_factor)],
-                            ..Section::default()
-                        });
-                    }
-                } else {
-                    // Drop here message
-                    //   position: center
+        self.overlay.draw_queued(
+            &self.device,
+            encoder,
+            view,
+            self.inner_size.width,
+            self.inner_size.height,
+        );
+
+        if !filmstrip_cells.is_empty() && self.filmstrip_opacity > 0.0 {
+            let padding = FILMSTRIP_CELL_PADDING * self.dpi_scale_factor as f32;
+            let cells: Vec<_> = filmstrip_cells
+                .iter()
+                .map(|(slot, x, y, size)| {
+                    (
+                        *slot,
+                        x + padding,
+                        y + padding,
+                        (size - padding * 2.0).max(0.0),
+                    )
+                })
+                .collect();
+            self.filmstrip_renderer.draw(
+                &self.device,
+                encoder,
+                view,
+                self.inner_size.width,
+                self.inner_size.height,
+                &cells,
+                self.filmstrip_opacity,
+            );
+        }
+
+        {
+            let scale_factor = self.dpi_scale_factor as f32;
+            if let Some(path) = path.as_ref().and_then(|p| p.to_str()) {
+                if self.show_image_path {
+                    // Image file path
+                    //   position: top-left
+                    let details = self
+                        .image_details
+                        .as_ref()
+                        .filter(|_| self.show_image_details);
+                    let text = match details {
+                        Some(details) => format!("{} ({})", path, details),
+                        None => path.to_owned(),
+                    };
                     self.glyph_brush.queue(Section {
-                        screen_position: (
-                            self.inner_size.width as f32 / 2.0,
-                            self.inner_size.height as f32 / 2.0,
-                        ),
+                        screen_position: (4.0, 2.0),
                         bounds: (self.inner_size.width as f32, self.inner_size.height as f32),
-                        text: vec![Text::new("drop image files here.")
-                            .with_color(self.text_color)
-                            .with_scale(FONT_SIZE_DROP_HERE_TEXT * scale_factor)],
-                        layout: Layout::default()
-                            .h_align(HorizontalAlign::Center)
-                            .v_align(VerticalAlign::Center),
+                        text: self.text_runs(
+                            &text,
+                            &self.font_chain_path,
+                            self.font_size_image_path * scale_factor,
+                        ),
+                        ..Section::default()
+                    });
+                }
+            } else {
+                // Drop here message
+                //   position: center
+                self.glyph_brush.queue(Section {
+                    screen_position: (
+                        self.inner_size.width as f32 / 2.0,
+                        self.inner_size.height as f32 / 2.0,
+                    ),
+                    bounds: (self.inner_size.width as f32, self.inner_size.height as f32),
+                    text: vec![Text::new("drop image files here.")
+                        .with_color(self.text_color)
+                        .with_scale(FONT_SIZE_DROP_HERE_TEXT * scale_factor)],
+                    layout: Layout::default()
+                        .h_align(HorizontalAlign::Center)
+                        .v_align(VerticalAlign::Center),
+                });
+            }
+
+            // Image information
+            //   position: bottom-left
+            if let Some(info) = self.info.as_ref().filter(|_| self.show_info) {
+                let offset = (self.font_size_osd / 2.0) * scale_factor;
+                self.glyph_brush.queue(Section {
+                    screen_position: (offset, self.inner_size.height as f32 - offset),
+                    bounds: (self.inner_size.width as f32, self.inner_size.height as f32),
+                    text: self.text_runs(
+                        info,
+                        &self.font_chain_osd,
+                        self.font_size_osd * scale_factor,
+                    ),
+                    layout: Layout::default()
+                        .h_align(HorizontalAlign::Left)
+                        .v_align(VerticalAlign::Bottom),
+                })
+            }
+
+            // Caption of the sidecar file, wrapped within the window
+            //   position: bottom-center
+            if let Some(caption) = self.caption.as_ref().filter(|_| self.show_caption) {
+                let offset = (self.font_size_osd / 2.0) * scale_factor;
+                let mut bottom = self.inner_size.height as f32 - offset;
+                if self.show_progress_bar && !self.progress_bar_hidden {
+                    bottom -= PROGRESS_BAR_HEIGHT * scale_factor;
+                }
+                self.glyph_brush.queue(Section {
+                    screen_position: (self.inner_size.width as f32 / 2.0, bottom),
+                    bounds: (
+                        (self.inner_size.width as f32 - offset * 2.0).max(0.0),
+                        self.inner_size.height as f32,
+                    ),
+                    text: self.text_runs(
+                        caption,
+                        &self.font_chain_osd,
+                        self.font_size_osd * scale_factor,
+                    ),
+                    layout: Layout::default_wrap()
+                        .h_align(HorizontalAlign::Center)
+                        .v_align(VerticalAlign::Bottom),
+                })
+            }
+
+            // Favorite mark
+            //   position: bottom-right, left of the histogram
+            if self.favorite && path.is_some() {
+                let offset = (self.font_size_osd / 2.0) * scale_factor;
+                let (x, y) = match &histogram_rect {
+                    Some((_, (x, y, _, height))) => (x - offset, y + height),
+                    None => {
+                        let mut bottom = self.inner_size.height as f32 - offset;
+                        if self.show_progress_bar && !self.progress_bar_hidden {
+                            bottom -= PROGRESS_BAR_HEIGHT * scale_factor;
+                        }
+                        (self.inner_size.width as f32 - offset, bottom)
+                    }
+                };
+                self.glyph_brush.queue(Section {
+                    screen_position: (x, y),
+                    bounds: (self.inner_size.width as f32, self.inner_size.height as f32),
+                    text: self.text_runs(
+                        FAVORITE_MARK,
+                        &self.font_chain_osd,
+                        self.font_size_osd * scale_factor,
+                    ),
+                    layout: Layout::default()
+                        .h_align(HorizontalAlign::Right)
+                        .v_align(VerticalAlign::Bottom),
+                });
+            }
+
+            // Keybinding help, in two columns if it doesn't fit in the height
+            if let (Some(lines), Some(((x, y, width, height), padding))) = (&self.help, help_area) {
+                let font_size = self.font_size_image_path * scale_factor;
+                let rows = ((height - padding * 2.0) / (font_size * HELP_LINE_SPACING)).max(1.0);
+                let columns = if lines.len() as f32 > rows { 2 } else { 1 };
+                let column_width = width / columns as f32;
+                let per_column = (lines.len() + columns - 1) / columns;
+                for (i, column) in lines.chunks(per_column.max(1)).enumerate() {
+                    let text = column.join("\n");
+                    self.glyph_brush.queue(Section {
+                        screen_position: (x + padding + column_width * i as f32, y + padding),
+                        bounds: (column_width - padding, height - padding * 2.0),
+                        text: self.text_runs(&text, &self.font_chain_osd, font_size),
+                        ..Section::default()
                     });
                 }
+            }
 
-                // Latest message
-                //   position: top-right
-                if let Some(message) = &self.message {
-                    let offset = (self.font_size_osd / 2.0) * scale_factor;
+            // Note on the histogram of a resized image
+            //   position: above the histogram
+            if let Some((histogram, (x, y, width, _))) = &histogram_rect {
+                if histogram.approximate {
                     self.glyph_brush.queue(Section {
-                        screen_position: (self.inner_size.width as f32 - offset, offset),
+                        screen_position: (x + width, *y),
                         bounds: (self.inner_size.width as f32, self.inner_size.height as f32),
-                        text: vec![Text::new(message)
+                        text: vec![Text::new("approximate (resized image)")
                             .with_color(self.text_color)
-                            .with_scale(self.font_size_osd * scale_factor)],
+                            .with_scale(self.font_size_image_path * scale_factor)],
                         layout: Layout::default()
                             .h_align(HorizontalAlign::Right)
-                            .v_align(VerticalAlign::Top),
-                    })
+                            .v_align(VerticalAlign::Bottom),
+                    });
                 }
             }
 
-            self.glyph_brush
-                .draw_queued(
-                    &self.device,
-                    &mut staging_belt,
-                    &mut encoder,
-                    &frame.view,
-                    self.inner_size.width,
-                    self.inner_size.height,
-                )
-                .expect("Draw queued");
-            staging_belt.finish();
-
-            self.queue.submit(std::iter::once(encoder.finish()));
-
-            // Recall unused staging buffers
-            local_spawner
-                .spawn(staging_belt.recall())
-                .expect("Recall staging belt");
-            local_pool.run_until_stalled();
+            // Latest message
+            //   position: osd_position (default: top-right)
+            if let Some(message) = &self.message {
+                let offset = (self.font_size_osd / 2.0) * scale_factor;
+                let width = self.inner_size.width as f32;
+                let height = self.inner_size.height as f32;
+                let (x, h_align) = match self.osd_position {
+                    Corner::TopLeft | Corner::BottomLeft => (offset, HorizontalAlign::Left),
+                    Corner::TopRight | Corner::BottomRight => {
+                        (width - offset, HorizontalAlign::Right)
+                    }
+                    Corner::Center => (width / 2.0, HorizontalAlign::Center),
+                };
+                let (y, v_align) = match self.osd_position {
+                    Corner::TopLeft | Corner::TopRight => (offset, VerticalAlign::Top),
+                    Corner::BottomLeft | Corner::BottomRight => {
+                        (height - offset, VerticalAlign::Bottom)
+                    }
+                    Corner::Center => (height / 2.0, VerticalAlign::Center),
+                };
+                self.glyph_brush.queue(Section {
+                    screen_position: (x, y),
+                    bounds: (width, height),
+                    text: self.text_runs(
+                        message,
+                        &self.font_chain_osd,
+                        self.font_size_osd * scale_factor,
+                    ),
+                    layout: Layout::default().h_align(h_align).v_align(v_align),
+                })
+            }
         }
 
-        Ok(())
+        self.glyph_brush
+            .draw_queued(
+                &self.device,
+                staging_belt,
+                encoder,
+                view,
+                self.inner_size.width,
+                self.inner_size.height,
+            )
+            .expect("Draw queued");
     }
 
-    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
-        // Window minimized
-        if new_size.width == 0 || new_size.height == 0 {
-            self.minimized = true;
-            return;
-        } else if self.minimized {
-            self.minimized = false;
+    /// Histogram to draw and its area in the window
+    fn histogram_rect(&self) -> Option<(Arc<Histogram>, (f32, f32, f32, f32))> {
+        let histogram = self.histogram.clone().filter(|_| self.show_histogram)?;
+        let scale_factor = self.dpi_scale_factor as f32;
+        let offset = (self.font_size_osd / 2.0) * scale_factor;
+        let width = HISTOGRAM_SIZE.0 * scale_factor;
+        let height = HISTOGRAM_SIZE.1 * scale_factor;
+        let x = self.inner_size.width as f32 - width - offset;
+        let y = self.inner_size.height as f32 - height - offset;
+        Some((histogram, (x, y, width, height)))
+    }
+
+    /// Number of the filmstrip slots fitting the window width, odd to center the current image
+    pub fn filmstrip_slot_count(&self) -> usize {
+        let cell_size = FILMSTRIP_CELL_SIZE * self.dpi_scale_factor as f32;
+        let count =
+            ((self.inner_size.width as f32 / cell_size) as usize).clamp(1, filmstrip::MAX_SLOTS);
+        if count % 2 == 0 {
+            count - 1
+        } else {
+            count
         }
+    }
 
-        self.inner_size = new_size;
-        self.sc_desc.width = new_size.width;
-        self.sc_desc.height = new_size.height;
-        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+    /// Filmstrip cells `(slot, x, y, size)` in the window, above the progress bar
+    fn filmstrip_cells(&self) -> Vec<(usize, f32, f32, f32)> {
+        let count = match &self.filmstrip {
+            Some(slots) => slots.len(),
+            None => return Vec::new(),
+        };
+        let scale_factor = self.dpi_scale_factor as f32;
+        let cell_size = FILMSTRIP_CELL_SIZE * scale_factor;
+        let left = (self.inner_size.width as f32 - cell_size * count as f32) / 2.0;
+        let mut top = self.inner_size.height as f32 - cell_size;
+        if self.show_progress_bar && !self.progress_bar_hidden {
+            top -= PROGRESS_BAR_HEIGHT * scale_factor;
+        }
+        (0..count)
+            .map(|slot| (slot, left + cell_size * slot as f32, top, cell_size))
+            .collect()
     }
 
-    pub fn redraw_image(&mut self) {
-        self.diffuse_textures[self.main_texture_index]
-            .write_queue(&self.queue, &self.diffuse_image_temp);
+    /// Image index of the filmstrip thumbnail at the window position
+    pub fn filmstrip_index_at(&self, position: PhysicalPosition<f64>) -> Option<usize> {
+        let slots = self
+            .filmstrip
+            .as_ref()
+            .filter(|_| self.filmstrip_opacity > 0.0)?;
+        let (x, y) = (position.x as f32, position.y as f32);
+        self.filmstrip_cells()
+            .into_iter()
+            .find(|(_, cell_x, cell_y, size)| {
+                x >= *cell_x && x < cell_x + size && y >= *cell_y && y < cell_y + size
+            })
+            .and_then(|(slot, _, _, _)| slots.get(slot).copied().flatten())
     }
 
-    fn load_font(font_name: Option<&str>) -> Result<ab_glyph::FontArc> {
-        let source = SystemSource::new();
-        let mut handle: Option<Handle> = None;
+    /// Show the filmstrip with the thumbnails of the slot indices
+    pub fn set_filmstrip(
+        &mut self,
+        indices: Vec<Option<usize>>,
+        thumbnails: &HashMap<usize, image::RgbaImage>,
+    ) {
+        for (slot, index) in indices.iter().enumerate() {
+            let thumbnail = index.and_then(|i| thumbnails.get(&i));
+            self.filmstrip_renderer
+                .write_slot(&self.queue, slot, *index, thumbnail);
+        }
+        self.filmstrip = Some(indices);
+    }
 
-        if let Some(font_name) = font_name {
-            if let Ok(family) = source.select_family_by_name(font_name) {
-                if let [v, ..] = family.fonts() {
-                    handle = v.clone().into();
-                }
-            }
+    /// Create the transition pipeline, an external shader not matching the layouts falls back
+    /// to the built-in shader, wgpu panics on the errors outside of the error scope
+    fn create_transition_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        format: wgpu::TextureFormat,
+        msaa_samples: u32,
+        shader_path: Option<&Path>,
+    ) -> wgpu::RenderPipeline {
+        let source = transition::load_shader_source(shader_path);
+        if let Cow::Borrowed(_) = source {
+            return Self::create_render_pipeline(device, layout, format, msaa_samples, source);
+        }
 
-            if handle.is_none() {
-                log::info!("Font '{}' not found!", font_name);
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let pipeline = Self::create_render_pipeline(device, layout, format, msaa_samples, source);
+        match block_on(device.pop_error_scope()) {
+            None => pipeline,
+            Some(err) => {
+                log::error!(
+                    "the transition shader doesn't match the bindings, use the built-in shader: {}",
+                    err
+                );
+                let source = Cow::Borrowed(transition::EMBEDDED_SHADER);
+                Self::create_render_pipeline(device, layout, format, msaa_samples, source)
             }
-        };
-
-        if handle.is_none() {
-            // default font
-            handle = source
-                .select_best_match(&[FamilyName::SansSerif], &Properties::new())
-                .ok();
         }
+    }
 
-        let font_data = handle
-            .unwrap()
+    fn create_render_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        format: wgpu::TextureFormat,
+        msaa_samples: u32,
+        source: Cow<'static, str>,
+    ) -> wgpu::RenderPipeline {
+        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some("Shader"),
+            flags: wgpu::ShaderFlags::all(),
+            source: wgpu::ShaderSource::Wgsl(source),
+        });
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Render Pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "main",
+                buffers: &[Vertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "main",
+                targets: &[wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrite::ALL,
+                }],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                clamp_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: msaa_samples,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+        })
+    }
+
+    fn create_diffuse_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        diffuse_textures: &[texture::Texture; 2],
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&diffuse_textures[0].view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&diffuse_textures[1].view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&diffuse_textures[0].sampler),
+                },
+            ],
+            label: Some("Diffuse Bind Group"),
+        })
+    }
+
+    /// Recreate the textures in the new size, the images must be composed again
+    pub fn set_texture_size(&mut self, size: PhysicalSize<u32>) -> Result<()> {
+        self.diffuse_image_temp =
+            image::ImageBuffer::from_pixel(size.width, size.height, self.bg_color);
+        self.diffuse_textures = [
+            texture::Texture::from_image(
+                &self.device,
+                &self.queue,
+                &self.diffuse_image_temp,
+                Some("Texture A"),
+            )?,
+            texture::Texture::from_image(
+                &self.device,
+                &self.queue,
+                &self.diffuse_image_temp,
+                Some("Texture B"),
+            )?,
+        ];
+        self.diffuse_bind_group = Self::create_diffuse_bind_group(
+            &self.device,
+            &self.texture_bind_group_layout,
+            &self.diffuse_textures,
+        );
+        self.texture_size = size;
+
+        Ok(())
+    }
+
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        // Window minimized
+        if new_size.width == 0 || new_size.height == 0 {
+            self.minimized = true;
+            return;
+        } else if self.minimized {
+            self.minimized = false;
+        }
+
+        self.inner_size = new_size;
+        self.sc_desc.width = new_size.width;
+        self.sc_desc.height = new_size.height;
+        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+        self.msaa_view = Self::create_msaa_view(&self.device, &self.sc_desc, self.msaa_samples);
+
+        self.uniforms.window_aspect = new_size.width as f32 / new_size.height as f32;
+        self.write_uniforms();
+    }
+
+    /// Write the image into the texture buffer, centered on the background
+    pub fn compose_image(&mut self, src_image: &image::RgbaImage) {
+        let border = (self.border_px as f64 * self.dpi_scale_factor).round() as u32;
+        let fit_width = self.scroll_offset.is_some();
+        let src_image = Self::inset_image(src_image, &self.texture_size, border, fit_width);
+        let src_image = src_image.as_ref();
+        let src_height = src_image.height();
+        let src_width = src_image.width();
+        let dst_width = self.texture_size.width;
+        let dst_height = self.texture_size.height;
+        let pad_left = dst_width.saturating_sub(src_width) / 2;
+        let pad_top = dst_height.saturating_sub(src_height) / 2;
+        // Center crop the images larger than the texture
+        let crop_left = src_width.saturating_sub(dst_width) / 2;
+        let max_crop_top = src_height.saturating_sub(dst_height);
+        let crop_top = self
+            .scroll_offset
+            .map_or(max_crop_top / 2, |offset| offset.min(max_crop_top));
+
+        // Write background pixels, the letterbox color outside the image area.
+        // An empty image (the gap between the slides) shows the background only.
+        let image_right = (pad_left + src_width - crop_left).min(dst_width);
+        let image_bottom = (pad_top + src_height - crop_top).min(dst_height);
+        let letterbox_color = self
+            .letterbox_color
+            .filter(|_| src_width > 0 && src_height > 0);
+        let checker = self.bg_pattern == config::BgPattern::Checker;
+        let checker_size = ((CHECKER_SIZE * self.dpi_scale_factor).round() as u32).max(1);
+        for (x, y, pixel) in self.diffuse_image_temp.enumerate_pixels_mut() {
+            let in_image = x >= pad_left && x < image_right && y >= pad_top && y < image_bottom;
+            *pixel = match letterbox_color {
+                Some(color) if !in_image => color,
+                _ if checker => {
+                    CHECKER_COLORS[(((x / checker_size) + (y / checker_size)) % 2) as usize]
+                }
+                _ => self.bg_color,
+            };
+        }
+
+        // Write the frame band around the image
+        if border > 0 && src_width > 0 && src_height > 0 {
+            let frame_left = pad_left.saturating_sub(border);
+            let frame_top = pad_top.saturating_sub(border);
+            let frame_right = (image_right + border).min(dst_width);
+            let frame_bottom = (image_bottom + border).min(dst_height);
+            for y in frame_top..frame_bottom {
+                for x in frame_left..frame_right {
+                    let in_image =
+                        x >= pad_left && x < image_right && y >= pad_top && y < image_bottom;
+                    if !in_image {
+                        self.diffuse_image_temp.put_pixel(x, y, self.border_color);
+                    }
+                }
+            }
+        }
+
+        // Write image pixels
+        for (src_x, src_y, pixel) in src_image.enumerate_pixels() {
+            if src_x < crop_left || src_y < crop_top {
+                continue;
+            }
+            let dst_x = pad_left + src_x - crop_left;
+            let dst_y = pad_top + src_y - crop_top;
+            if dst_x < dst_width && dst_y < dst_height {
+                if checker {
+                    // Blend the transparent pixels over the checker pattern
+                    self.diffuse_image_temp
+                        .get_pixel_mut(dst_x, dst_y)
+                        .blend(pixel);
+                } else {
+                    self.diffuse_image_temp.put_pixel(dst_x, dst_y, *pixel);
+                }
+            }
+        }
+    }
+
+    /// Write the images into the cells of the contact sheet, each one centered in its cell,
+    /// the empty cells of the last page and the cells still preloading show the background
+    pub fn compose_sheet(&mut self, columns: u32, rows: u32, images: &[Option<&image::RgbaImage>]) {
+        for pixel in self.diffuse_image_temp.pixels_mut() {
+            *pixel = self.bg_color;
+        }
+
+        let (width, height) = self.diffuse_image_temp.dimensions();
+        let cell_width = width / columns.max(1);
+        let cell_height = height / rows.max(1);
+        let padding = (SHEET_CELL_PADDING * self.dpi_scale_factor).round() as u32;
+        let inner_width = cell_width.saturating_sub(padding * 2).max(1);
+        let inner_height = cell_height.saturating_sub(padding * 2).max(1);
+
+        for (i, src_image) in images.iter().take((columns * rows) as usize).enumerate() {
+            // Left empty until it's preloaded
+            let src_image = match src_image {
+                Some(v) => *v,
+                None => continue,
+            };
+            let (src_width, src_height) = src_image.dimensions();
+            if src_width == 0 || src_height == 0 {
+                continue;
+            }
+            let ratio = (inner_width as f64 / src_width as f64)
+                .min(inner_height as f64 / src_height as f64);
+            let cell_image = image::imageops::resize(
+                src_image,
+                ((src_width as f64 * ratio).round() as u32).clamp(1, inner_width),
+                ((src_height as f64 * ratio).round() as u32).clamp(1, inner_height),
+                image::imageops::FilterType::Triangle,
+            );
+            let cell_x = (i as u32 % columns) * cell_width;
+            let cell_y = (i as u32 / columns) * cell_height;
+            image::imageops::overlay(
+                &mut self.diffuse_image_temp,
+                &cell_image,
+                cell_x + cell_width.saturating_sub(cell_image.width()) / 2,
+                cell_y + cell_height.saturating_sub(cell_image.height()) / 2,
+            );
+        }
+    }
+
+    /// Shrink the image to leave room for the frame on both sides,
+    /// only the width counts in the fit width mode
+    fn inset_image<'a>(
+        src_image: &'a image::RgbaImage,
+        texture_size: &PhysicalSize<u32>,
+        border: u32,
+        fit_width: bool,
+    ) -> Cow<'a, image::RgbaImage> {
+        let (width, height) = src_image.dimensions();
+        if border == 0 || width == 0 || height == 0 {
+            return Cow::Borrowed(src_image);
+        }
+        let inner_width = texture_size.width.saturating_sub(border * 2).max(1);
+        let inner_height = texture_size.height.saturating_sub(border * 2).max(1);
+        let ratio_x = inner_width as f64 / width as f64;
+        let ratio_y = inner_height as f64 / height as f64;
+        let ratio = if fit_width {
+            ratio_x
+        } else {
+            ratio_x.min(ratio_y)
+        };
+        if ratio >= 1.0 {
+            return Cow::Borrowed(src_image);
+        }
+        Cow::Owned(image::imageops::resize(
+            src_image,
+            ((width as f64 * ratio).round() as u32).max(1),
+            ((height as f64 * ratio).round() as u32).max(1),
+            image::imageops::FilterType::Triangle,
+        ))
+    }
+
+    /// Write the texture buffer into the currently displayed texture
+    pub fn redraw_current_image(&mut self) {
+        let current_texture_index = 1 - self.main_texture_index;
+        self.diffuse_textures[current_texture_index]
+            .write_queue(&self.queue, &self.diffuse_image_temp);
+    }
+
+    pub fn redraw_image(&mut self) {
+        self.diffuse_textures[self.main_texture_index]
+            .write_queue(&self.queue, &self.diffuse_image_temp);
+    }
+
+    pub fn write_uniforms(&self) {
+        self.queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[self.uniforms]),
+        );
+    }
+
+    /// Zoom the image around the given window position, return the new zoom value
+    pub fn zoom_at(&mut self, factor: f32, position: PhysicalPosition<f64>) -> f32 {
+        let old_zoom = self.uniforms.zoom;
+        let new_zoom = (old_zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        let window_size = [self.inner_size.width, self.inner_size.height];
+        let position = [position.x as f32, position.y as f32];
+
+        // Keep the texture coordinates under the cursor
+        for i in 0..2 {
+            let screen_uv = (position[i] / window_size[i].max(1) as f32) - 0.5;
+            let uv = screen_uv * self.uniforms.resized_window_scale[i];
+            self.uniforms.pan[i] += uv * ((1.0 / old_zoom) - (1.0 / new_zoom));
+        }
+        self.uniforms.zoom = new_zoom;
+        self.clamp_pan();
+        self.write_uniforms();
+
+        new_zoom
+    }
+
+    /// Pan the image by the given window pixels
+    pub fn pan_by(&mut self, dx: f64, dy: f64) {
+        let window_size = [self.inner_size.width, self.inner_size.height];
+        // Move the mirrored image in the drag direction
+        let delta = [
+            (if self.uniforms.flip_h > 0.5 { -dx } else { dx }) as f32,
+            (if self.uniforms.flip_v > 0.5 { -dy } else { dy }) as f32,
+        ];
+        for i in 0..2 {
+            let uv =
+                (delta[i] / window_size[i].max(1) as f32) * self.uniforms.resized_window_scale[i];
+            self.uniforms.pan[i] -= uv / self.uniforms.zoom;
+        }
+        self.clamp_pan();
+        self.write_uniforms();
+    }
+
+    /// Move the split of the compare mode to the window x in 0.0-1.0
+    pub fn set_compare_split(&mut self, x: f32) {
+        self.uniforms.split_x = x.clamp(0.0, 1.0);
+        self.write_uniforms();
+    }
+
+    /// Reset zoom and pan
+    pub fn reset_view(&mut self) {
+        self.uniforms.zoom = 1.0;
+        self.uniforms.pan = [0.0, 0.0];
+        self.write_uniforms();
+    }
+
+    /// Toggle the horizontal or vertical mirroring of the view, return true if mirrored
+    pub fn toggle_flip(&mut self, horizontal: bool) -> bool {
+        let flip = if horizontal {
+            &mut self.uniforms.flip_h
+        } else {
+            &mut self.uniforms.flip_v
+        };
+        *flip = 1.0 - *flip;
+        let flipped = *flip > 0.5;
+        self.write_uniforms();
+
+        flipped
+    }
+
+    pub fn reset_flip(&mut self) {
+        self.uniforms.flip_h = 0.0;
+        self.uniforms.flip_v = 0.0;
+        self.write_uniforms();
+    }
+
+    /// Rotate the view clockwise by the degrees, return the new angle in -180-180
+    pub fn rotate_by(&mut self, deg: f32) -> f32 {
+        let angle = (self.uniforms.rotation_deg + deg + 180.0).rem_euclid(360.0) - 180.0;
+        self.uniforms.rotation_deg = angle;
+        self.write_uniforms();
+        angle
+    }
+
+    pub fn reset_rotation(&mut self) {
+        self.uniforms.rotation_deg = 0.0;
+        self.write_uniforms();
+    }
+
+    /// Add to the brightness, contrast and gamma adjustments
+    pub fn adjust_image(&mut self, brightness: f32, contrast: f32, gamma: f32) {
+        let uniforms = &mut self.uniforms;
+        uniforms.brightness =
+            (uniforms.brightness + brightness).clamp(BRIGHTNESS_RANGE.0, BRIGHTNESS_RANGE.1);
+        uniforms.contrast =
+            (uniforms.contrast + contrast).clamp(CONTRAST_RANGE.0, CONTRAST_RANGE.1);
+        uniforms.gamma = (uniforms.gamma + gamma).clamp(GAMMA_RANGE.0, GAMMA_RANGE.1);
+        self.write_uniforms();
+    }
+
+    pub fn reset_adjustments(&mut self) {
+        self.uniforms.brightness = 0.0;
+        self.uniforms.contrast = 1.0;
+        self.uniforms.gamma = 1.0;
+        self.write_uniforms();
+    }
+
+    /// Set the brightness multiplier of the dim schedule
+    pub fn set_dim(&mut self, factor: f32) {
+        if self.uniforms.dim != factor {
+            self.uniforms.dim = factor;
+            self.write_uniforms();
+        }
+    }
+
+    pub fn adjustments_message(&self) -> String {
+        format!(
+            "Brightness: {:+.2}\nContrast: {:.2}\nGamma: {:.2}",
+            self.uniforms.brightness, self.uniforms.contrast, self.uniforms.gamma
+        )
+    }
+
+    fn clamp_pan(&mut self) {
+        for v in self.uniforms.pan.iter_mut() {
+            *v = v.clamp(-0.5, 0.5);
+        }
+    }
+
+    fn load_font(font_name: Option<&str>) -> Result<ab_glyph::FontArc> {
+        let source = SystemSource::new();
+        let mut handle: Option<Handle> = None;
+
+        if let Some(font_name) = font_name {
+            handle = Self::find_font(&source, font_name);
+
+            if handle.is_none() {
+                log::info!("Font '{}' not found!", font_name);
+            }
+        };
+
+        if handle.is_none() {
+            // default font
+            handle = source
+                .select_best_match(&[FamilyName::SansSerif], &Properties::new())
+                .ok();
+        }
+
+        Self::load_font_handle(&handle.unwrap())
+    }
+
+    /// Load the fallback fonts for the glyphs missing in the first font, the configured fonts
+    /// and then a CJK and an emoji font of the system if any, the missing fonts are skipped
+    fn load_fallback_fonts(font_names: &[String]) -> Vec<ab_glyph::FontArc> {
+        let source = SystemSource::new();
+        let mut handles: Vec<Handle> = font_names
+            .iter()
+            .filter_map(|font_name| {
+                let handle = Self::find_font(&source, font_name);
+                if handle.is_none() {
+                    log::info!("Font '{}' not found!", font_name);
+                }
+                handle
+            })
+            .collect();
+        for family_names in [FALLBACK_CJK_FONTS, FALLBACK_EMOJI_FONTS] {
+            let family_names: Vec<FamilyName> = family_names
+                .iter()
+                .map(|v| FamilyName::Title(v.to_string()))
+                .collect();
+            if let Ok(handle) = source.select_best_match(&family_names, &Properties::new()) {
+                handles.push(handle);
+            }
+        }
+
+        handles
+            .iter()
+            .filter_map(|handle| {
+                Self::load_font_handle(handle)
+                    .map_err(|err| log::warn!("Failed to load a fallback font: {}", err))
+                    .ok()
+            })
+            .collect()
+    }
+
+    fn find_font(source: &SystemSource, font_name: &str) -> Option<Handle> {
+        let family = source.select_family_by_name(font_name).ok()?;
+        family.fonts().first().cloned()
+    }
+
+    fn load_font_handle(handle: &Handle) -> Result<ab_glyph::FontArc> {
+        let font_data = handle
             .load()?
             .copy_font_data()
             .ok_or_else(|| anyhow!("faild to load a font."))?;
@@ -575,27 +1893,139 @@ impl GraphicsState {
         Ok(font)
     }
 
+    /// Split the text into the runs of the first font in the chain that has each glyph,
+    /// the glyphs missing in all the fonts stay in the run of the preceding glyph
+    fn text_runs<'a>(&self, text: &'a str, font_chain: &[FontId], scale: f32) -> Vec<Text<'a>> {
+        use ab_glyph::Font;
+
+        let fonts = self.glyph_brush.fonts();
+        let font_of = |c: char| {
+            if c.is_whitespace() || c.is_control() {
+                return None;
+            }
+            font_chain.iter().copied().find(|id| {
+                fonts
+                    .get(id.0)
+                    .map_or(false, |font| font.glyph_id(c).0 != 0)
+            })
+        };
+        let new_text = |run: &'a str, font_id: FontId| {
+            Text::new(run)
+                .with_color(self.text_color)
+                .with_scale(scale)
+                .with_font_id(font_id)
+        };
+
+        let mut runs = Vec::new();
+        let mut start = 0;
+        let mut current = font_chain.first().copied().unwrap_or_default();
+        for (i, c) in text.char_indices() {
+            if let Some(font_id) = font_of(c).filter(|v| *v != current) {
+                if i > start {
+                    runs.push(new_text(&text[start..i], current));
+                }
+                start = i;
+                current = font_id;
+            }
+        }
+        runs.push(new_text(&text[start..], current));
+        runs
+    }
+
     pub fn update_message(&mut self, message: &str) {
         self.message = Some(message.to_string());
         self.tx_osd_message_timer.send(()).log_err();
     }
 }
 
+pub struct AnimationPlayback {
+    pub animation: Arc<AnimatedImage>,
+    pub frame_index: usize,
+    pub played_once: bool,
+    pub loops_played: u32,
+    pub advance_pending: bool,
+}
+
+/// Slow zoom and pan of the displayed image
+pub struct KenBurns {
+    pub from: (f32, [f32; 2]),
+    pub to: (f32, [f32; 2]),
+    pub duration: Duration,
+    pub elapsed: Duration,
+    pub last_time: Instant,
+}
+
+/// Thumbnail grid view state
+pub struct GridView {
+    pub selected: usize,
+    pub scroll_row: usize,
+}
+
 pub struct State {
     pub graphics: GraphicsState,
     pub transition: TransitionState,
     pub image_loader: Arc<Mutex<ImageLoader>>,
     pub default_timer_secs: u32,
     pub current_timer_secs: u32,
+    /// Display time of the current image from the playlist
+    pub image_timer_secs: Option<u32>,
+    /// Transition effect into the current image from the playlist
+    pub image_transition: Option<i32>,
     pub paused: bool,
+    /// Never advance by the timer
+    pub manual: bool,
     pub pause_at_last: bool,
+    /// Wait longer for the next image to be decoded
+    pub wait_for_load: bool,
+    pub pause_on_activity: bool,
+    /// Paused while the mouse is moving, separate from the user's pause state
+    pub activity_paused: bool,
+    pub reverse: bool,
+    pub zoom_mode: bool,
+    /// Keep the brightness, contrast and gamma when the image changes
+    pub lock_adjustments: bool,
     pub fullscreen_ctrl: FullscreenController,
+    pub animation: Option<AnimationPlayback>,
+    pub grid: Option<GridView>,
+    pub ken_burns_enabled: bool,
+    pub ken_burns_intensity: f32,
+    pub ken_burns: Option<KenBurns>,
+    /// Background shown between the images by the slideshow
+    pub gap_time: Duration,
+    pub gap_active: bool,
+    /// Time of the last `next_image` to detect fast scrubbing
+    pub last_navigation: Instant,
+    /// Skip the transition of the next image
+    pub snap_next: bool,
+    /// Amount of the navigation waiting for the preload and the start time of the wait
+    next_image_wait: Option<(i32, Instant)>,
+    /// Scroll position of the current image in the fit width mode
+    pub scroll_offset: u32,
+    /// The slideshow was paused by showing the help
+    pub help_paused: bool,
+    pub show_filmstrip: bool,
+    /// Columns and rows of the contact sheet, the consecutive images are shown as a page
+    pub contact_sheet: Option<(u32, u32)>,
+    /// Show the folder name when the folder of the image changes
+    pub announce_folders: bool,
+    /// Parent folder of the current image
+    pub current_folder: Option<PathBuf>,
+    /// Blank the paused image briefly once in a while with the pixel shift
+    pub pixel_shift_blank: bool,
+    pub pixel_shift_step: u32,
+    pixel_shift_blanked: bool,
+    /// Played when the slideshow advances by the timer, not by the navigation
+    pub advance_sound: Option<AdvanceSound>,
     pub tx_slideshow_timer: mpsc::Sender<TimerState>,
+    pub tx_animation_timer: mpsc::Sender<Option<Duration>>,
+    pub tx_gap_timer: mpsc::Sender<Option<Duration>>,
+    pub tx_ken_burns_timer: mpsc::Sender<bool>,
     pub event_proxy: EventLoopProxy<CustomEvent>,
     pub rng: rand::rngs::ThreadRng,
 }
 
 impl State {
+    #[allow(clippy::too_many_arguments)]
     pub async fn new(
         window: &Window,
         image_loader: Arc<Mutex<ImageLoader>>,
@@ -603,16 +2033,52 @@ impl State {
         fullscreen_ctrl: FullscreenController,
         tx_slideshow_timer: mpsc::Sender<TimerState>,
         tx_osd_message_timer: mpsc::Sender<()>,
+        tx_animation_timer: mpsc::Sender<Option<Duration>>,
+        tx_gap_timer: mpsc::Sender<Option<Duration>>,
+        tx_ken_burns_timer: mpsc::Sender<bool>,
         event_proxy: EventLoopProxy<CustomEvent>,
     ) -> Result<Self> {
-        let graphics = GraphicsState::new(window, &conf, tx_osd_message_timer).await?;
+        let texture_size = image_loader.lock().unwrap().texture_size.into();
+        let graphics =
+            GraphicsState::new(window, &conf, texture_size, tx_osd_message_timer).await?;
+
+        if conf.viewer.gpu_resize {
+            image_loader.lock().unwrap().gpu_resizer = Some(Arc::new(GpuResizer::new(
+                graphics.device.clone(),
+                graphics.queue.clone(),
+            )));
+        }
+
+        // No sound without the audio device
+        let advance_sound = conf.viewer.advance_sound.as_deref().and_then(|path| {
+            AdvanceSound::new(path, conf.viewer.advance_sound_volume)
+                .map_err(|err| log::warn!("advance_sound {}: {}", path.display(), err))
+                .ok()
+        });
 
+        let effect = &conf.transition.effect;
+        let mode = transition::effect_mode(effect).unwrap_or_else(|| {
+            if !effect.eq_ignore_ascii_case(transition::RANDOM_EFFECT_NAME) {
+                log::warn!("Unknown transition effect: {}", effect);
+            }
+            0
+        });
         let transition = TransitionState {
             active: false,
             direction: 0.0,
             last_time: Instant::now(),
             time: conf.transition.time,
-            random: conf.transition.random,
+            max_frame_time: if conf.transition.min_fps > 0.0 {
+                Some(1.0 / conf.transition.min_fps)
+            } else {
+                None
+            },
+            slow_frame: conf.transition.slow_frame,
+            enabled: conf.transition.enabled,
+            random: conf.transition.random
+                || effect.eq_ignore_ascii_case(transition::RANDOM_EFFECT_NAME),
+            mode,
+            slide_dir: 1.0,
         };
 
         let rng = rand::thread_rng();
@@ -623,12 +2089,44 @@ impl State {
             image_loader,
             default_timer_secs: conf.viewer.timer,
             current_timer_secs: conf.viewer.timer,
+            image_timer_secs: None,
+            image_transition: None,
             paused: conf.viewer.timer == 0,
+            manual: conf.viewer.manual,
             pause_at_last: conf.viewer.pause_at_last,
+            wait_for_load: conf.viewer.wait_for_load,
+            pause_on_activity: conf.viewer.pause_on_activity,
+            activity_paused: false,
+            reverse: false,
+            zoom_mode: false,
+            lock_adjustments: false,
+            show_filmstrip: false,
+            contact_sheet: conf.viewer.grid.map(|[columns, rows]| (columns, rows)),
+            announce_folders: conf.viewer.announce_folders,
+            current_folder: None,
             fullscreen_ctrl,
+            animation: None,
+            grid: None,
+            ken_burns_enabled: conf.viewer.ken_burns,
+            ken_burns_intensity: conf.viewer.ken_burns_intensity.max(0.0),
+            ken_burns: None,
+            gap_time: Duration::from_millis(conf.transition.gap_ms as u64),
+            gap_active: false,
+            last_navigation: Instant::now(),
+            snap_next: false,
+            next_image_wait: None,
+            scroll_offset: 0,
+            help_paused: false,
+            pixel_shift_blank: conf.viewer.pixel_shift_blank,
+            pixel_shift_step: 0,
+            pixel_shift_blanked: false,
             tx_slideshow_timer,
+            tx_animation_timer,
+            tx_gap_timer,
+            tx_ken_burns_timer,
             event_proxy,
             rng,
+            advance_sound,
         };
 
         instance.draw_current_image().log_err();
@@ -636,6 +2134,35 @@ impl State {
         Ok(instance)
     }
 
+    /// Capture the runtime state to write to the resume file
+    pub fn resume_state(&self, config_hash: &str) -> ResumeState {
+        let loader = self.image_loader.lock().unwrap();
+        ResumeState {
+            config_hash: config_hash.to_owned(),
+            paths_hash: resume::hash_paths(&loader.scanned_paths),
+            current_index: loader.current_index,
+            current_path: loader.current_path.clone(),
+            current_timer_secs: self.current_timer_secs,
+            paused: self.paused,
+            pause_at_last: self.pause_at_last,
+        }
+    }
+
+    /// Switch to the next transition effect, returns the name of the new effect
+    pub fn next_transition_effect(&mut self) -> &'static str {
+        let trans = &mut self.transition;
+        if trans.random {
+            trans.random = false;
+            trans.mode = 0;
+        } else if trans.mode >= transition::MAX_MODE_IDX {
+            trans.random = true;
+            return transition::RANDOM_EFFECT_NAME;
+        } else {
+            trans.mode += 1;
+        }
+        transition::effect_name(trans.mode)
+    }
+
     pub fn update_transition(&mut self) -> IsTransitionEnd {
         let trans = &mut self.transition;
         let gfx = &mut self.graphics;
@@ -643,11 +2170,24 @@ impl State {
         if trans.active && trans.direction != 0.0 {
             let mut is_end = true;
 
-            let delta_time = trans.last_time.elapsed().as_micros() as f32 / 1_000_000.0;
+            let mut delta_time = trans.last_time.elapsed().as_micros() as f32 / 1_000_000.0;
             trans.last_time = Instant::now();
 
+            // Slow frames either end the transition or slow it down instead of stuttering
+            let mut skip_to_end = false;
+            if let Some(max_frame_time) = trans.max_frame_time {
+                if delta_time > max_frame_time {
+                    match trans.slow_frame {
+                        config::SlowFrame::Finish => skip_to_end = true,
+                        config::SlowFrame::Stretch => delta_time = max_frame_time,
+                    }
+                }
+            }
+
             {
-                let amount = if trans.time > 0.0 {
+                let amount = if skip_to_end {
+                    1.0
+                } else if trans.time > 0.0 {
                     let amount = (1.0 / trans.time) * delta_time;
                     if amount > 0.0 {
                         amount
@@ -679,11 +2219,7 @@ impl State {
                 gfx.uniforms.blend = b;
             }
 
-            gfx.queue.write_buffer(
-                &gfx.uniform_buffer,
-                0,
-                bytemuck::cast_slice(&[gfx.uniforms]),
-            );
+            gfx.write_uniforms();
 
             return is_end;
         }
@@ -691,50 +2227,686 @@ impl State {
         true
     }
 
-    pub fn next_image(&mut self, amount: i32) -> Result<()> {
-        {
-            let mut loader = self.image_loader.lock().unwrap();
-            loader.next_index(amount);
-        }
+    /// Show the next frame of the animated image
+    pub fn next_frame(&mut self) -> Result<()> {
+        let mut advance = false;
+
+        if let Some(playback) = &mut self.animation {
+            let animation = &playback.animation;
+            let next_index = (playback.frame_index + 1) % animation.frames.len();
+            if next_index == 0 {
+                playback.played_once = true;
+                playback.loops_played += 1;
+                advance = playback.advance_pending && !self.paused;
+            }
 
-        self.draw_current_image()
-    }
+            // Stay on the last frame after the play count of the file
+            if animation.play_count > 0 && playback.loops_played >= animation.play_count {
+                self.tx_animation_timer.send(None)?;
+            } else {
+                playback.frame_index = next_index;
 
-    pub fn first_image(&mut self) -> Result<()> {
-        {
-            let mut loader = self.image_loader.lock().unwrap();
-            loader.current_index = 0;
+                let gfx = &mut self.graphics;
+                gfx.compose_image(&animation.frames[next_index]);
+                gfx.redraw_current_image();
+
+                self.tx_animation_timer
+                    .send(Some(animation.delays[next_index]))?;
+            }
         }
 
-        self.draw_current_image()
+        // The slideshow timer has expired while playing the first loop
+        if advance {
+            self.advance_slideshow()?;
+        }
+
+        Ok(())
     }
 
-    pub fn last_image(&mut self) -> Result<()> {
-        {
-            let mut loader = self.image_loader.lock().unwrap();
-            loader.current_index = loader.scanned_paths.len() - 1;
+    /// Change the display time and keep the pause state
+    pub fn change_timer(&mut self, secs: u32) {
+        self.current_timer_secs = secs;
+        self.tx_slideshow_timer
+            .send(TimerState::Change(self.slideshow_timer_secs()))
+            .log_err();
+        if self.paused {
+            self.tx_slideshow_timer.send(TimerState::Pause).log_err();
         }
-
-        self.draw_current_image()
     }
 
-    pub fn draw_current_image(&mut self) -> Result<()> {
-        let trans = &mut self.transition;
-        let gfx = &mut self.graphics;
+    /// Display time of the current image
+    pub fn slideshow_timer_secs(&self) -> u32 {
+        if self.manual {
+            return 0;
+        }
+        scale_image_timer(
+            self.image_timer_secs,
+            self.default_timer_secs,
+            self.current_timer_secs,
+        )
+    }
 
-        if !self.paused {
-            self.tx_slideshow_timer.send(TimerState::Play)?;
+    /// Advance the slideshow by one image in the playback direction
+    pub fn advance_slideshow(&mut self) -> Result<()> {
+        if let Some(sound) = &self.advance_sound {
+            sound.play().log_err();
         }
 
-        {
-            // Write background pixels
-            for (_, _, pixel) in gfx.diffuse_image_temp.enumerate_pixels_mut() {
-                *pixel = gfx.bg_color;
+        let amount = if self.reverse { -1 } else { 1 };
+        if !self.gap_time.is_zero() && !self.gap_active {
+            // No gap at the end of the list without wrapping
+            let has_next = {
+                let loader = self.image_loader.lock().unwrap();
+                loader.get_next_index(amount).is_some()
+            };
+            if has_next {
+                return self.start_gap();
             }
-
+        }
+        self.next_image(amount)
+    }
+
+    /// Resize the textures to the monitor of the window and decode the images again,
+    /// nothing is done if the size is the same
+    pub fn fit_texture_to_monitor(&mut self) -> Result<()> {
+        let size = match self.fullscreen_ctrl.window.current_monitor() {
+            Some(monitor) => monitor.size(),
+            None => return Ok(()),
+        };
+        self.resize_texture(size)
+    }
+
+    /// Resize the texture to the window size if it changed significantly
+    pub fn fit_texture_to_window(&mut self) -> Result<()> {
+        let size = self.graphics.inner_size;
+        let texture_size = self.graphics.texture_size;
+        let changed = |window: u32, texture: u32| {
+            (window as f32 - texture as f32).abs() >= texture as f32 * TEXTURE_RESIZE_MIN_RATIO
+        };
+        if !changed(size.width, texture_size.width) && !changed(size.height, texture_size.height) {
+            return Ok(());
+        }
+        self.resize_texture(size)
+    }
+
+    /// Resize the texture and decode the cached images again at the size
+    fn resize_texture(&mut self, size: PhysicalSize<u32>) -> Result<()> {
+        if size == self.graphics.texture_size || size.width == 0 || size.height == 0 {
+            return Ok(());
+        }
+        log::info!("texture size: {}x{}", size.width, size.height);
+
+        self.graphics.set_texture_size(size)?;
+        {
+            let mut loader = self.image_loader.lock().unwrap();
+            loader.texture_size.width = size.width;
+            loader.texture_size.height = size.height;
+            loader.clear_cache();
+        }
+        self.draw_current_image()
+    }
+
+    /// Return true while the background scan is finding more images
+    pub fn is_scanning(&self) -> bool {
+        self.image_loader.lock().unwrap().scanning
+    }
+
+    /// Return true if the slideshow stops at the end without wrapping
+    pub fn is_slideshow_stopped(&self) -> bool {
+        let wrap = self.image_loader.lock().unwrap().wrap;
+        !wrap && self.is_slideshow_end()
+    }
+
+    /// Return true if the slideshow reached the end in the playback direction
+    pub fn is_slideshow_end(&self) -> bool {
+        let loader = self.image_loader.lock().unwrap();
+        if self.reverse {
+            loader.is_first()
+        } else {
+            loader.is_last()
+        }
+    }
+
+    pub fn next_image(&mut self, amount: i32) -> Result<()> {
+        if self.wait_for_next_image(amount) {
+            return Ok(());
+        }
+
+        let moved = {
+            let mut loader = self.image_loader.lock().unwrap();
+            loader.next_index(amount)
+                || (!loader.scanning && (loader.scanned_paths.len() <= 1 || loader.wrap))
+        };
+        if !moved {
+            let message = if self.is_scanning() {
+                "Scanning…"
+            } else if amount < 0 {
+                "Start of list"
+            } else {
+                "End of list"
+            };
+            self.graphics.update_message(message);
+            // Show the current image again after the gap
+            if !self.gap_active {
+                return Ok(());
+            }
+        }
+        self.transition.slide_dir = if amount < 0 { -1.0 } else { 1.0 };
+
+        // Snap to the image while scrubbing not to pile up the transitions
+        self.snap_next = self.last_navigation.elapsed() < SCRUB_INTERVAL;
+        self.last_navigation = Instant::now();
+        let result = self.draw_current_image();
+        self.snap_next = false;
+        result
+    }
+
+    /// Return true while the image moved by the amount is decoded by the preload workers,
+    /// it's moved to the front of the queue and `RetryNextImage` retries the navigation
+    fn wait_for_next_image(&mut self, amount: i32) -> bool {
+        let waiting = matches!(self.next_image_wait, Some((v, _)) if v == amount);
+        let start = match self.next_image_wait {
+            Some((_, start)) if waiting => start,
+            _ => Instant::now(),
+        };
+        let timeout = if self.wait_for_load {
+            LOAD_WAIT_TIMEOUT
+        } else {
+            NEXT_IMAGE_WAIT_TIMEOUT
+        };
+        let ready = {
+            let mut loader = self.image_loader.lock().unwrap();
+            let ready = loader.is_preloaded(amount);
+            if !ready {
+                loader.prioritize_preload(amount);
+            }
+            ready
+        };
+        if ready || start.elapsed() >= timeout {
+            if !ready {
+                log::warn!("next image not preloaded in {:?}", timeout);
+            }
+            if self.next_image_wait.take().is_some()
+                && start.elapsed() >= NEXT_IMAGE_INDICATOR_DELAY
+            {
+                self.graphics.update_message("");
+            }
+            return false;
+        }
+
+        if start.elapsed() >= NEXT_IMAGE_INDICATOR_DELAY {
+            self.graphics.update_message("Loading…");
+        }
+        self.next_image_wait = Some((amount, start));
+        if !waiting {
+            // Notify when the image is ready, and once when the indicator is due
+            let image_loader = self.image_loader.clone();
+            let proxy = self.event_proxy.clone();
+            std::thread::spawn(move || {
+                let mut indicator_notified = false;
+                loop {
+                    std::thread::sleep(NEXT_IMAGE_POLL_INTERVAL);
+                    let ready = image_loader.lock().unwrap().is_preloaded(amount);
+                    let elapsed = start.elapsed();
+                    if ready || elapsed >= timeout {
+                        proxy.send_event(CustomEvent::RetryNextImage).log_err();
+                        return;
+                    }
+                    if !indicator_notified && elapsed >= NEXT_IMAGE_INDICATOR_DELAY {
+                        indicator_notified = true;
+                        proxy.send_event(CustomEvent::RetryNextImage).log_err();
+                    }
+                }
+            });
+        }
+        true
+    }
+
+    /// Retry the navigation waiting for the preload
+    pub fn retry_next_image(&mut self) -> Result<()> {
+        match self.next_image_wait {
+            Some((amount, _)) => self.next_image(amount),
+            None => Ok(()),
+        }
+    }
+
+    /// Compose the contact sheet again with the cells decoded after it was shown
+    pub fn redraw_contact_sheet(&mut self) {
+        let (columns, rows) = match self.contact_sheet {
+            Some(v) if self.grid.is_none() => v,
+            _ => return,
+        };
+        let loader = self.image_loader.lock().unwrap();
+        let images: Vec<_> = loader
+            .get_page()
+            .into_iter()
+            .map(|v| v.map(|image_cache| &image_cache.image))
+            .collect();
+        self.graphics.compose_sheet(columns, rows, &images);
+        self.graphics.redraw_current_image();
+    }
+
+    /// Scroll the tall image in the fit width mode, move to the next image past the end
+    pub fn scroll_or_next_image(&mut self, amount: i32) -> Result<()> {
+        let max_offset = self.max_scroll_offset();
+        let at_end = if amount < 0 {
+            self.scroll_offset == 0
+        } else {
+            self.scroll_offset >= max_offset
+        };
+        if max_offset == 0 || at_end {
+            return self.next_image(amount);
+        }
+
+        let step = ((self.graphics.texture_size.height as f32 * SCROLL_STEP_RATIO) as u32).max(1);
+        self.scroll_offset = if amount < 0 {
+            self.scroll_offset.saturating_sub(step)
+        } else {
+            (self.scroll_offset + step).min(max_offset)
+        };
+
+        // Compose the visible area again without the transition
+        let loader = self.image_loader.lock().unwrap();
+        if let Some(image_cache) = loader.cache.get(&loader.current_index) {
+            self.graphics.scroll_offset = Some(self.scroll_offset);
+            self.graphics.compose_image(&image_cache.image);
+            self.graphics.redraw_current_image();
+        }
+        Ok(())
+    }
+
+    /// Scroll range of the current image, 0 if it fits in the window or not in the fit width mode
+    fn max_scroll_offset(&self) -> u32 {
+        let loader = self.image_loader.lock().unwrap();
+        if loader.fit_mode != config::FitMode::Width {
+            return 0;
+        }
+        loader
+            .cache
+            .get(&loader.current_index)
+            .map_or(0, |image_cache| {
+                image_cache
+                    .image
+                    .height()
+                    .saturating_sub(self.graphics.texture_size.height)
+            })
+    }
+
+    pub fn first_image(&mut self) -> Result<()> {
+        self.next_image_wait = None;
+        {
+            let mut loader = self.image_loader.lock().unwrap();
+            loader.current_index = 0;
+        }
+        self.transition.slide_dir = -1.0;
+
+        self.draw_current_image()
+    }
+
+    pub fn last_image(&mut self) -> Result<()> {
+        self.next_image_wait = None;
+        {
+            let mut loader = self.image_loader.lock().unwrap();
+            loader.current_index = loader.scanned_paths.len().saturating_sub(1);
+        }
+        self.transition.slide_dir = 1.0;
+
+        self.draw_current_image()
+    }
+
+    /// Jump to the 0-based index
+    pub fn goto_image(&mut self, index: usize) -> Result<()> {
+        self.next_image_wait = None;
+        {
+            let mut loader = self.image_loader.lock().unwrap();
+            if index >= loader.scanned_paths.len() {
+                return Err(anyhow!(
+                    "index {} is out of range ({} files)",
+                    index + 1,
+                    loader.scanned_paths.len()
+                ));
+            }
+            self.transition.slide_dir = slide_direction(loader.current_index, index);
+            loader.current_index = index;
+        }
+
+        self.draw_current_image()
+    }
+
+    /// Show the keybinding help, the slideshow pauses while it's shown
+    pub fn show_help(&mut self, lines: Vec<String>) -> Result<()> {
+        self.graphics.help = Some(lines);
+        if !self.paused && !self.manual {
+            self.paused = true;
+            self.help_paused = true;
+            self.tx_slideshow_timer.send(TimerState::Pause)?;
+        }
+        Ok(())
+    }
+
+    /// Hide the keybinding help and resume the slideshow paused by it
+    pub fn hide_help(&mut self) -> Result<()> {
+        self.graphics.help = None;
+        if self.help_paused {
+            self.help_paused = false;
+            self.paused = false;
+            if !self.activity_paused {
+                self.tx_slideshow_timer.send(TimerState::Play)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Move the paused image by a pixel or two in a slow Lissajous pattern against the burn-in,
+    /// return true if the screen is blanked for a moment
+    pub fn shift_pixels(&mut self) -> bool {
+        let gfx = &mut self.graphics;
+        let trans = &self.transition;
+        let transitioning = (trans.direction > 0.0 && gfx.uniforms.blend < 1.0)
+            || (trans.direction < 0.0 && gfx.uniforms.blend > 0.0);
+        // The navigation resets the pan
+        let still = (self.paused || self.manual)
+            && !transitioning
+            && !self.zoom_mode
+            && self.ken_burns.is_none()
+            && self.grid.is_none()
+            && self.last_navigation.elapsed() >= PIXEL_SHIFT_INTERVAL;
+        if !still {
+            self.pixel_shift_step = 0;
+            return false;
+        }
+
+        self.pixel_shift_step += 1;
+        let t = self.pixel_shift_step as f32 * std::f32::consts::TAU / PIXEL_SHIFT_CYCLE as f32;
+        let shift = [
+            ((3.0 * t).sin() * PIXEL_SHIFT_AMPLITUDE).round(),
+            ((2.0 * t).sin() * PIXEL_SHIFT_AMPLITUDE).round(),
+        ];
+        let window_size = [gfx.inner_size.width, gfx.inner_size.height];
+        for i in 0..2 {
+            gfx.uniforms.pan[i] =
+                shift[i] * gfx.uniforms.resized_window_scale[i] / window_size[i].max(1) as f32;
+        }
+        gfx.write_uniforms();
+
+        if self.pixel_shift_blank
+            && gfx.blanked.is_none()
+            && self.pixel_shift_step % PIXEL_SHIFT_BLANK_STEPS == 0
+        {
+            gfx.blanked = Some(gfx.bg_color);
+            self.pixel_shift_blanked = true;
+            return true;
+        }
+        false
+    }
+
+    /// Show the image again after the blank of the pixel shift
+    pub fn end_pixel_shift_blank(&mut self) {
+        if self.pixel_shift_blanked {
+            self.pixel_shift_blanked = false;
+            self.graphics.blanked = None;
+        }
+    }
+
+    /// Hold the timer while the mouse is active with `pause_on_activity`
+    pub fn set_activity_paused(&mut self, active: bool) -> Result<()> {
+        if !self.pause_on_activity || self.activity_paused == active {
+            return Ok(());
+        }
+        self.activity_paused = active;
+
+        if !self.paused && !self.manual {
+            self.tx_slideshow_timer.send(if active {
+                TimerState::Pause
+            } else {
+                TimerState::Play
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Move the view of Ken Burns effect, the effect stops while paused
+    pub fn update_ken_burns(&mut self) {
+        let ken_burns = match &mut self.ken_burns {
+            Some(v) => v,
+            None => return,
+        };
+
+        if !self.paused {
+            ken_burns.elapsed += ken_burns.last_time.elapsed();
+        }
+        ken_burns.last_time = Instant::now();
+
+        let t = if ken_burns.duration.is_zero() {
+            1.0
+        } else {
+            (ken_burns.elapsed.as_secs_f32() / ken_burns.duration.as_secs_f32()).min(1.0)
+        };
+        let lerp = |a: f32, b: f32| a + ((b - a) * t);
+        let (from, to) = (ken_burns.from, ken_burns.to);
+
+        let gfx = &mut self.graphics;
+        gfx.uniforms.zoom = lerp(from.0, to.0);
+        gfx.uniforms.pan = [lerp(from.1[0], to.1[0]), lerp(from.1[1], to.1[1])];
+        gfx.write_uniforms();
+    }
+
+    /// Stop Ken Burns effect and keep the current view
+    pub fn stop_ken_burns(&mut self) {
+        self.ken_burns = None;
+        self.tx_ken_burns_timer.send(false).log_err();
+    }
+
+    /// Save the displayed (oriented and resized) image as PNG next to the source file
+    pub fn save_displayed_image(&self) -> Result<PathBuf> {
+        let source_path = self
+            .image_loader
+            .lock()
+            .unwrap()
+            .current_path
+            .clone()
+            .ok_or_else(|| anyhow!("no image to save"))?;
+        if is_remote_path(&source_path) {
+            return Err(anyhow!("cannot save next to a remote image"));
+        }
+
+        let dir = source_path.parent().unwrap_or_else(|| Path::new("."));
+        let stem = source_path
+            .file_stem()
+            .map(|v| v.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let path = unique_path(dir, &format!("{}{}", stem, SAVED_IMAGE_SUFFIX), "png");
+
+        let image = &self.graphics.diffuse_image_temp;
+        image::save_buffer(
+            &path,
+            image,
+            image.width(),
+            image.height(),
+            image::ColorType::Rgba8,
+        )?;
+
+        Ok(path)
+    }
+
+    /// Copy the current image to the clipboard, decode the original size if possible
+    pub fn copy_image_to_clipboard(&self) -> Result<()> {
+        let (index, path, options) = {
+            let loader = self.image_loader.lock().unwrap();
+            let index = loader.current_index;
+            let path = loader
+                .current_path
+                .clone()
+                .ok_or_else(|| anyhow!("no image to copy"))?;
+            let mut options = loader.decode_options(&index);
+            options.fit_mode = config::FitMode::Actual;
+            (index, path, options)
+        };
+
+        let image = ImageLoader::open_and_resize_image(&index, &path, &options, None, None)
+            .or_else(|err| {
+                log::warn!("failed to decode the original image: {}", err);
+                self.image_loader
+                    .lock()
+                    .unwrap()
+                    .cache
+                    .get(&index)
+                    .filter(|v| v.emsg.is_none())
+                    .map(|v| v.image.clone())
+                    .ok_or(err)
+            })?;
+
+        image_copy_to_clipboard(&image)
+    }
+
+    /// Open or close the thumbnail grid view
+    pub fn toggle_grid(&mut self) -> Result<()> {
+        if self.grid.take().is_some() {
+            return self.draw_current_image();
+        }
+        self.stop_compare();
+        self.graphics.filmstrip = None;
+
+        let selected = self.image_loader.lock().unwrap().current_index;
+        self.grid = Some(GridView {
+            selected,
+            scroll_row: 0,
+        });
+
+        // Stop the animation not to overwrite the grid
+        self.animation = None;
+        self.tx_animation_timer.send(None)?;
+        self.stop_ken_burns();
+
+        self.draw_grid()
+    }
+
+    /// Move the grid selection, `rows` is multiplied by the column count
+    pub fn move_grid_selection(&mut self, amount: i32, rows: i32) -> Result<()> {
+        let (columns, _) = self.grid_layout();
+        let count = self.image_loader.lock().unwrap().scanned_paths.len();
+        if let Some(grid) = &mut self.grid {
+            let amount = amount as i64 + (rows as i64 * columns as i64);
+            let selected = (grid.selected as i64 + amount).clamp(0, count.max(1) as i64 - 1);
+            grid.selected = selected as usize;
+        }
+        self.draw_grid()
+    }
+
+    /// Close the grid view and show the selected image
+    pub fn select_grid_item(&mut self) -> Result<()> {
+        if let Some(grid) = self.grid.take() {
             let mut loader = self.image_loader.lock().unwrap();
+            if grid.selected < loader.scanned_paths.len() {
+                self.transition.slide_dir = slide_direction(loader.current_index, grid.selected);
+                loader.current_index = grid.selected;
+            }
+        }
+        self.draw_current_image()
+    }
+
+    /// Get the column and row counts of the grid
+    fn grid_layout(&self) -> (usize, usize) {
+        let cell_size = THUMBNAIL_SIZE + GRID_CELL_PADDING * 2;
+        let size = self.graphics.texture_size;
+        let columns = (size.width / cell_size).max(1) as usize;
+        let rows = (size.height / cell_size).max(1) as usize;
+        (columns, rows)
+    }
+
+    /// Compose the thumbnails of the visible rows into the texture
+    pub fn draw_grid(&mut self) -> Result<()> {
+        let (columns, rows) = self.grid_layout();
+        let grid = match &mut self.grid {
+            Some(grid) => grid,
+            None => return Ok(()),
+        };
+
+        // Scroll to the selection
+        let selected_row = grid.selected / columns;
+        if selected_row < grid.scroll_row {
+            grid.scroll_row = selected_row;
+        } else if selected_row >= grid.scroll_row + rows {
+            grid.scroll_row = selected_row + 1 - rows;
+        }
+
+        let gfx = &mut self.graphics;
+        let cell_size = THUMBNAIL_SIZE + GRID_CELL_PADDING * 2;
+        let (width, height) = gfx.diffuse_image_temp.dimensions();
+        let margin_left = width.saturating_sub(cell_size * columns as u32) / 2;
+        let margin_top = height.saturating_sub(cell_size * rows as u32) / 2;
+        let text_color = image::Rgba(gfx.text_color.map(|v| (v * 255.0) as u8));
+
+        for pixel in gfx.diffuse_image_temp.pixels_mut() {
+            *pixel = gfx.bg_color;
+        }
+
+        let first = grid.scroll_row * columns;
+        let visible = first..(first + (columns * rows));
+        let mut loader = self.image_loader.lock().unwrap();
+        loader.request_thumbnails(visible.clone());
+        for index in visible.take_while(|i| *i < loader.scanned_paths.len()) {
+            let cell_x = margin_left + ((index - first) % columns) as u32 * cell_size;
+            let cell_y = margin_top + ((index - first) / columns) as u32 * cell_size;
+
+            if index == grid.selected {
+                for y in cell_y..(cell_y + cell_size).min(height) {
+                    for x in cell_x..(cell_x + cell_size).min(width) {
+                        let is_border = x < cell_x + GRID_SELECTION_BORDER
+                            || x >= cell_x + cell_size - GRID_SELECTION_BORDER
+                            || y < cell_y + GRID_SELECTION_BORDER
+                            || y >= cell_y + cell_size - GRID_SELECTION_BORDER;
+                        if is_border {
+                            gfx.diffuse_image_temp.put_pixel(x, y, text_color);
+                        }
+                    }
+                }
+            }
+
+            if let Some(thumbnail) = loader.thumbnails.get(&index) {
+                let x = cell_x + (cell_size - thumbnail.width().min(THUMBNAIL_SIZE)) / 2;
+                let y = cell_y + (cell_size - thumbnail.height().min(THUMBNAIL_SIZE)) / 2;
+                image::imageops::overlay(&mut gfx.diffuse_image_temp, thumbnail, x, y);
+            }
+        }
+        gfx.progress = Some((grid.selected, loader.scanned_paths.len()));
+        drop(loader);
+
+        gfx.redraw_current_image();
+        gfx.reset_view();
+
+        Ok(())
+    }
+
+    pub fn draw_current_image(&mut self) -> Result<()> {
+        // Navigation skips the rest of the gap
+        if self.gap_active {
+            self.gap_active = false;
+            self.tx_gap_timer.send(None)?;
+        }
+
+        if self.grid.is_some() {
+            return self.draw_grid();
+        }
+
+        let trans = &mut self.transition;
+        let gfx = &mut self.graphics;
+
+        let (animation, image_timer_secs, image_transition, image_changed, folder) = {
+            let mut loader = self.image_loader.lock().unwrap();
+            let fit_width = loader.fit_mode == config::FitMode::Width;
+            let previous_path = loader.current_path.clone();
             let image_cache = loader.get_current()?;
-            let src_image = &image_cache.image;
+
+            // Start from the top of the new image
+            if image_cache.path != previous_path {
+                self.scroll_offset = 0;
+            }
+            gfx.scroll_offset = if fit_width {
+                Some(self.scroll_offset)
+            } else {
+                None
+            };
 
             if let Some(emsg) = &image_cache.emsg {
                 if let Some(path) = &image_cache.path {
@@ -744,34 +2916,105 @@ impl State {
                 }
             }
 
-            // Write image pixels
-            let src_height = src_image.height();
-            let src_width = src_image.width();
-            let dst_width = gfx.texture_size.width;
-            let dst_height = gfx.texture_size.height;
-            let pad_left = dst_width.saturating_sub(src_width) / 2;
-            let pad_top = dst_height.saturating_sub(src_height) / 2;
-            for (src_x, src_y, pixel) in src_image.enumerate_pixels() {
-                let dst_x = pad_left + src_x;
-                let dst_y = pad_top + src_y;
-                if dst_x < dst_width && dst_y < dst_height {
-                    gfx.diffuse_image_temp.put_pixel(dst_x, dst_y, *pixel);
-                }
+            // The contact sheet is composed after reading the current image
+            if self.contact_sheet.is_none() {
+                gfx.compose_image(&image_cache.image);
             }
 
-            loader.current_path = image_cache.path.clone();
-        }
+            gfx.image_details = format_image_details(image_cache);
+            gfx.info = image_cache
+                .path
+                .as_ref()
+                .map(|path| format_image_info(path, image_cache));
+            gfx.caption = image_cache.caption.clone();
+            let (width, height) = image_cache.image.dimensions();
+            gfx.image_aspect = if width > 0 && height > 0 {
+                Some(width as f32 / height as f32)
+            } else {
+                None
+            };
 
-        gfx.redraw_image();
+            // The contact sheet shows the first frames only
+            let animation = image_cache
+                .animation
+                .clone()
+                .filter(|_| self.contact_sheet.is_none());
+            let path = image_cache.path.clone();
+            gfx.progress = path
+                .as_ref()
+                .map(|_| (loader.current_index, loader.scanned_paths.len()));
+            gfx.favorite = loader.favorites.contains(&loader.current_index);
+            let image_timer_secs = path.as_ref().and_then(|p| loader.durations.get(p).copied());
+            let image_transition = path
+                .as_ref()
+                .and_then(|p| loader.transitions.get(p).copied());
+            let image_changed = loader.current_path != path;
+            let folder = path
+                .as_deref()
+                .and_then(|p| multi_image::file_path(p).parent().map(Path::to_path_buf));
+            loader.current_path = path;
+
+            if let Some((columns, rows)) = self.contact_sheet {
+                let images: Vec<_> = loader
+                    .get_page()
+                    .into_iter()
+                    .map(|v| v.map(|image_cache| &image_cache.image))
+                    .collect();
+                gfx.compose_sheet(columns, rows, &images);
+            }
+            (
+                animation,
+                image_timer_secs,
+                image_transition,
+                image_changed,
+                folder,
+            )
+        };
+        self.image_transition = image_transition;
+
+        // Announce the folder boundary, not the folder of the first image
+        if folder.is_some() && folder != self.current_folder {
+            if self.announce_folders && self.current_folder.is_some() {
+                if let Some(folder) = &folder {
+                    let name = folder.file_name().unwrap_or_else(|| folder.as_os_str());
+                    gfx.update_message(&format!("Folder: {}", name.to_string_lossy()));
+                }
+            }
+            self.current_folder = folder;
+        }
 
-        let is_primary = gfx.main_texture_index == 0;
-        gfx.uniforms.blend = if is_primary { 1.0 } else { 0.0 };
-        gfx.uniforms.flip = if is_primary { 0.0 } else { 1.0 };
+        if image_changed {
+            gfx.reset_flip();
+            gfx.reset_rotation();
+            if !self.lock_adjustments {
+                gfx.reset_adjustments();
+            }
+        }
 
-        if trans.random {
-            gfx.uniforms.mode = self.rng.gen_range(0..=TRANSITION_MAX_MODE_IDX);
+        // Restore the global timer for the images without the custom display time
+        self.image_timer_secs = image_timer_secs;
+        let timer_secs = scale_image_timer(
+            image_timer_secs,
+            self.default_timer_secs,
+            self.current_timer_secs,
+        );
+        self.tx_slideshow_timer
+            .send(TimerState::Change(if self.manual { 0 } else { timer_secs }))?;
+        if !self.paused && !self.activity_paused {
+            self.tx_slideshow_timer.send(TimerState::Play)?;
         }
 
+        // Start animation
+        self.animation = animation.map(|animation| AnimationPlayback {
+            animation,
+            frame_index: 0,
+            played_once: false,
+            loops_played: 0,
+            advance_pending: false,
+        });
+        let delay = self.animation.as_ref().map(|v| v.animation.delays[0]);
+        self.tx_animation_timer.send(delay)?;
+
         {
             let screen_size = if self.fullscreen_ctrl.active {
                 self.fullscreen_ctrl.size.unwrap_or(gfx.inner_size)
@@ -792,18 +3035,276 @@ impl State {
             };
         }
 
-        gfx.queue.write_buffer(
-            &gfx.uniform_buffer,
-            0,
-            bytemuck::cast_slice(&[gfx.uniforms]),
-        );
+        // Start Ken Burns effect on the still images
+        self.ken_burns = None;
+        if self.ken_burns_enabled && !self.zoom_mode && self.animation.is_none() {
+            let intensity = self.ken_burns_intensity;
+            let rng = &mut self.rng;
+            let zoom_in = rng.gen::<bool>();
+            let mut random_view = |zoom: f32| {
+                let max_pan = 0.5 - (0.5 / zoom);
+                let mut pan = || rng.gen_range(-max_pan..=max_pan);
+                (zoom, [pan(), pan()])
+            };
+            let (from, to) = if zoom_in {
+                (random_view(1.0), random_view(1.0 + intensity))
+            } else {
+                (random_view(1.0 + intensity), random_view(1.0))
+            };
+            self.ken_burns = Some(KenBurns {
+                from,
+                to,
+                duration: Duration::from_secs_f32(timer_secs as f32 + trans.time),
+                elapsed: Duration::ZERO,
+                last_time: Instant::now(),
+            });
+        }
+        self.tx_ken_burns_timer.send(self.ken_burns.is_some())?;
 
-        // Start transition
-        trans.direction = if is_primary { -1.0 } else { 1.0 };
-        self.event_proxy.send_event(CustomEvent::TransitionStart)?;
+        let (zoom, pan) = self
+            .ken_burns
+            .as_ref()
+            .map_or((1.0, [0.0, 0.0]), |v| v.from);
+        gfx.uniforms.zoom = zoom;
+        gfx.uniforms.pan = pan;
 
-        gfx.main_texture_index = if is_primary { 1 } else { 0 };
+        self.start_transition()?;
+        self.update_histogram();
+        self.update_filmstrip();
 
         Ok(())
     }
+
+    /// Decode the current image (the page of the contact sheet) again from the file,
+    /// only the modified files with `modified_only`, return true if any was reloaded
+    pub fn reload_current_image(&mut self, modified_only: bool) -> Result<bool> {
+        let reloaded = {
+            let mut loader = self.image_loader.lock().unwrap();
+            let start = loader.current_index;
+            let end = (start + loader.page_size.max(1)).min(loader.scanned_paths.len());
+            let indices: Vec<usize> = (start..end)
+                .filter(|i| !modified_only || loader.is_modified(i))
+                .collect();
+            for index in &indices {
+                loader.thumbnails.remove(index);
+                loader.force_reload_cache(index)?;
+            }
+            !indices.is_empty()
+        };
+        if reloaded {
+            self.draw_current_image()?;
+        }
+        Ok(reloaded)
+    }
+
+    /// Show or hide the filmstrip of the neighbor thumbnails
+    pub fn toggle_filmstrip(&mut self) -> bool {
+        self.show_filmstrip = !self.show_filmstrip;
+        if self.show_filmstrip {
+            self.graphics.filmstrip_opacity = 1.0;
+            self.update_filmstrip();
+        } else {
+            self.graphics.filmstrip = None;
+        }
+        self.show_filmstrip
+    }
+
+    /// Center the filmstrip on the current image and request the missing thumbnails,
+    /// hidden in the grid view
+    pub fn update_filmstrip(&mut self) {
+        if !self.show_filmstrip || self.grid.is_some() {
+            self.graphics.filmstrip = None;
+            return;
+        }
+
+        let half = (self.graphics.filmstrip_slot_count() / 2) as i64;
+        let mut loader = self.image_loader.lock().unwrap();
+        let len = loader.scanned_paths.len() as i64;
+        let current = loader.current_index as i64;
+        // No wrap around, a short list would repeat the images
+        let indices: Vec<Option<usize>> = (-half..=half)
+            .map(|amount| current + amount)
+            .map(|index| (0..len).contains(&index).then(|| index as usize))
+            .collect();
+        loader.request_thumbnails(indices.iter().flatten().copied());
+        self.graphics.set_filmstrip(indices, &loader.thumbnails);
+    }
+
+    /// Pin the displayed image as the reference of the compare mode,
+    /// the next images are shown beside it
+    pub fn pin_compare(&mut self) -> Result<()> {
+        if self.grid.is_some() {
+            return Ok(());
+        }
+        let gfx = &mut self.graphics;
+        let pinned = 1 - gfx.main_texture_index;
+        gfx.compare = Some(pinned);
+        gfx.uniforms.split_x = 0.5;
+        self.draw_current_image()
+    }
+
+    /// Leave the compare mode and show only the current image
+    pub fn stop_compare(&mut self) {
+        let gfx = &mut self.graphics;
+        let pinned = match gfx.compare.take() {
+            Some(v) => v,
+            None => return,
+        };
+
+        // The current image is in the other texture, show it as after a transition
+        let current = (1 - pinned) as f32;
+        gfx.uniforms.mode = self.transition.mode;
+        gfx.uniforms.flip = current;
+        gfx.uniforms.blend = current;
+        gfx.write_uniforms();
+    }
+
+    /// Blend the displayed image into the composed image
+    fn start_transition(&mut self) -> Result<()> {
+        let trans = &mut self.transition;
+        let gfx = &mut self.graphics;
+
+        // Keep the pinned image and replace the other texture without a transition
+        if let Some(pinned) = gfx.compare {
+            trans.direction = 0.0;
+            gfx.main_texture_index = pinned;
+            gfx.redraw_current_image();
+            gfx.uniforms.mode = transition::COMPARE_MODE;
+            gfx.uniforms.flip = pinned as f32;
+            gfx.write_uniforms();
+            return Ok(());
+        }
+
+        gfx.redraw_image();
+
+        let is_primary = gfx.main_texture_index == 0;
+        gfx.uniforms.blend = if is_primary { 1.0 } else { 0.0 };
+        gfx.uniforms.flip = if is_primary { 0.0 } else { 1.0 };
+
+        // The transition of the image from the playlist takes priority
+        gfx.uniforms.mode = match self.image_transition {
+            Some(mode) => mode,
+            None if trans.random => self.rng.gen_range(0..=transition::MAX_MODE_IDX),
+            None => trans.mode,
+        };
+        gfx.uniforms.slide_dir = trans.slide_dir;
+
+        if trans.enabled && !self.snap_next {
+            gfx.write_uniforms();
+
+            // Start transition
+            trans.direction = if is_primary { -1.0 } else { 1.0 };
+            self.event_proxy.send_event(CustomEvent::TransitionStart)?;
+
+            gfx.main_texture_index = if is_primary { 1 } else { 0 };
+        } else {
+            // Show the new image immediately
+            trans.direction = 0.0;
+            gfx.uniforms.blend = if is_primary { 0.0 } else { 1.0 };
+            gfx.write_uniforms();
+
+            gfx.main_texture_index = if is_primary { 1 } else { 0 };
+            gfx.redraw_image();
+        }
+
+        Ok(())
+    }
+
+    /// Blend into the background and show the next image after the gap
+    fn start_gap(&mut self) -> Result<()> {
+        self.gap_active = true;
+        self.animation = None;
+        self.tx_animation_timer.send(None)?;
+        self.stop_ken_burns();
+
+        self.graphics.compose_image(&image::RgbaImage::new(0, 0));
+        self.transition.slide_dir = if self.reverse { -1.0 } else { 1.0 };
+        self.start_transition()?;
+
+        let transition_time = if self.transition.enabled {
+            Duration::from_secs_f32(self.transition.time.max(0.0))
+        } else {
+            Duration::ZERO
+        };
+        self.tx_gap_timer
+            .send(Some(transition_time + self.gap_time))?;
+        Ok(())
+    }
+
+    /// Show the next image when the gap has not been skipped by the navigation
+    pub fn finish_gap(&mut self) -> Result<()> {
+        if !self.gap_active {
+            return Ok(());
+        }
+        self.next_image(if self.reverse { -1 } else { 1 })
+    }
+
+    /// Compute the histogram of the current image once and keep it in the image cache
+    pub fn update_histogram(&mut self) {
+        if !self.graphics.show_histogram {
+            return;
+        }
+
+        let mut loader = self.image_loader.lock().unwrap();
+        let index = loader.current_index;
+        self.graphics.histogram = loader.cache.get_mut(&index).map(|image_cache| {
+            if image_cache.histogram.is_none() {
+                // The cached image is resized to the window
+                let (width, height) = image_cache.image.dimensions();
+                let approximate = image_cache.dimensions.map_or(true, |(w, h)| {
+                    w as u64 * h as u64 != width as u64 * height as u64
+                });
+                image_cache.histogram =
+                    Some(Arc::new(Histogram::new(&image_cache.image, approximate)));
+            }
+            image_cache.histogram.clone().unwrap()
+        });
+    }
+}
+
+/// Slide from the right when moving forward
+fn slide_direction(from: usize, to: usize) -> f32 {
+    if to < from {
+        -1.0
+    } else {
+        1.0
+    }
+}
+
+/// Scale the custom display time by the runtime timer changes
+fn scale_image_timer(image_secs: Option<u32>, default_secs: u32, current_secs: u32) -> u32 {
+    match image_secs {
+        Some(_) if current_secs == 0 => 0,
+        Some(secs) if default_secs > 0 => {
+            ((secs as u64 * current_secs as u64) / default_secs as u64).max(1) as u32
+        }
+        Some(secs) => secs,
+        None => current_secs,
+    }
+}
+
+/// Multi-line text of the file name, details and EXIF information
+fn format_image_info(path: &Path, image_cache: &ImageCache) -> String {
+    let file_name = path
+        .file_name()
+        .map(|v| v.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string());
+
+    let mut lines = vec![file_name];
+    if let Some(details) = format_image_details(image_cache) {
+        lines.push(details);
+    }
+    if let Some(exif) = &image_cache.exif {
+        lines.extend(exif.lines());
+    }
+    lines.join("\n")
+}
+
+/// Original dimensions and file size text, e.g. "4032×3024, 2.1 MB"
+fn format_image_details(image_cache: &ImageCache) -> Option<String> {
+    let (width, height) = image_cache.dimensions?;
+    match image_cache.file_size {
+        Some(size) => Some(format!("{}×{}, {}", width, height, format_file_size(size))),
+        None => Some(format!("{}×{}", width, height)),
+    }
 }
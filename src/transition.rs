@@ -0,0 +1,105 @@
+use anyhow::{anyhow, Result};
+use std::borrow::Cow;
+use std::fs;
+use std::path::Path;
+
+/// Built-in transition shader
+pub const EMBEDDED_SHADER: &str = include_str!("transition.wgsl");
+
+/// Transition effect names, the index is the mode number in the transition shader
+pub const EFFECT_NAMES: [&str; 23] = [
+    "fade",
+    "smooth_fade",
+    "wipe_left",
+    "wipe_up",
+    "wipe_right",
+    "wipe_down",
+    "wipe_up_left",
+    "wipe_down_left",
+    "wipe_up_right",
+    "wipe_down_right",
+    "door_open_horizontal",
+    "door_open_vertical",
+    "door_close_horizontal",
+    "door_close_vertical",
+    "blinds_left",
+    "blinds_up",
+    "blinds_right",
+    "blinds_down",
+    "box_out",
+    "box_in",
+    "random_squares",
+    "angular",
+    "slide",
+];
+
+pub const MAX_MODE_IDX: i32 = EFFECT_NAMES.len() as i32 - 1;
+
+/// Shader mode of the split view comparing the pinned image, not a transition effect
+pub const COMPARE_MODE: i32 = EFFECT_NAMES.len() as i32;
+
+pub const RANDOM_EFFECT_NAME: &str = "random";
+
+/// Get the shader mode number of the effect name
+pub fn effect_mode(name: &str) -> Option<i32> {
+    EFFECT_NAMES
+        .iter()
+        .position(|v| v.eq_ignore_ascii_case(name))
+        .map(|i| i as i32)
+}
+
+/// Get the effect name of the shader mode number
+pub fn effect_name(mode: i32) -> &'static str {
+    EFFECT_NAMES
+        .get(mode as usize)
+        .copied()
+        .unwrap_or("unknown")
+}
+
+/// Read the external transition shader, fall back to the embedded shader on errors
+pub fn load_shader_source(path: Option<&Path>) -> Cow<'static, str> {
+    let path = match path {
+        Some(v) => v,
+        None => return Cow::Borrowed(EMBEDDED_SHADER),
+    };
+
+    match read_shader(path) {
+        Ok(source) => {
+            log::info!("transition shader: {}", path.display());
+            Cow::Owned(source)
+        }
+        Err(err) => {
+            log::error!(
+                "failed to load the transition shader {}, use the built-in shader: {}",
+                path.display(),
+                err
+            );
+            Cow::Borrowed(EMBEDDED_SHADER)
+        }
+    }
+}
+
+/// Compile the shader before wgpu to avoid the panic on invalid shaders,
+/// the bindings are checked in an error scope when the pipeline is created
+fn read_shader(path: &Path) -> Result<String> {
+    let source = fs::read_to_string(path)?;
+    let module = naga::front::wgsl::parse_str(&source).map_err(|err| anyhow!("{:?}", err))?;
+    naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::empty(),
+    )
+    .validate(&module)
+    .map_err(|err| anyhow!("{:?}", err))?;
+
+    for stage in [naga::ShaderStage::Vertex, naga::ShaderStage::Fragment].iter() {
+        let found = module
+            .entry_points
+            .iter()
+            .any(|v| v.stage == *stage && v.name == "main");
+        if !found {
+            return Err(anyhow!("missing the {:?} entry point 'main'", stage));
+        }
+    }
+
+    Ok(source)
+}
@@ -38,6 +38,22 @@ struct Uniforms {
     bg_g: f32;
     bg_b: f32;
     bg_a: f32;
+    zoom: f32;
+    //pan: vec2<f32>;
+    pan_x: f32;
+    pan_y: f32;
+    brightness: f32;
+    contrast: f32;
+    gamma: f32;
+    flip_h: f32;
+    flip_v: f32;
+    slide_dir: f32;
+    split_x: f32;
+    rotation_deg: f32;
+    window_aspect: f32;
+    dim: f32;
+    padding_0: f32;
+    padding_1: f32;
 };
 
 [[group(0), binding(0)]]
@@ -135,20 +151,91 @@ fn ts_angular(a: v4f, b: v4f, t: f32, uv: v2f) -> v4f {
     return mix(a, b, v4f(step(normalizedAngle, t)));
 }
 
-[[stage(fragment)]]
-fn main(in: VertexOutput) -> [[location(0)]] v4f {
-    let uv = v2f(
-        (0.5 + ((in.tex_coords.x - 0.5) * uniforms.resized_window_scale_x)),
-        (0.5 + ((in.tex_coords.y - 0.5) * uniforms.resized_window_scale_y))
+// view
+
+// screen coordinates to the image coordinates
+fn to_image_uv(screen_uv: v2f) -> v2f {
+    return v2f(
+        (0.5 + ((screen_uv.x - 0.5) * uniforms.resized_window_scale_x)),
+        (0.5 + ((screen_uv.y - 0.5) * uniforms.resized_window_scale_y))
+    );
+}
+
+// rotate the screen coordinates clockwise around the center,
+// enlarged so the rotated view covers the window without the corners
+fn rotate_screen_uv(screen_uv: v2f) -> v2f {
+    let a = uniforms.window_aspect;
+    let rad = radians(uniforms.rotation_deg);
+    let c = cos(rad);
+    let s = sin(rad);
+    let cover = max(abs(c) + (abs(s) / a), abs(c) + (abs(s) * a));
+    let p = v2f((screen_uv.x - 0.5) * a, screen_uv.y - 0.5) / cover;
+    return v2f(
+        (((p.x * c) + (p.y * s)) / a) + 0.5,
+        ((p.y * c) - (p.x * s)) + 0.5
+    );
+}
+
+// image coordinates to the texture coordinates with the zoom, pan and mirroring
+fn to_view_uv(uv: v2f) -> v2f {
+    let zoomed = v2f(
+        (0.5 + uniforms.pan_x + ((uv.x - 0.5) / uniforms.zoom)),
+        (0.5 + uniforms.pan_y + ((uv.y - 0.5) / uniforms.zoom))
+    );
+
+    // mirror the view, both textures are sampled with the same coordinates
+    return v2f(
+        mix(zoomed.x, 1.0 - zoomed.x, uniforms.flip_h),
+        mix(zoomed.y, 1.0 - zoomed.y, uniforms.flip_v)
     );
+}
 
+fn sample_view(tex_index: i32, view_uv: v2f) -> v4f {
     let out_of_bounds = v4f(clamp01(
-        step(uv.x, 0.0) + step(1.0 - uv.x, 0.0) +
-        step(uv.y, 0.0) + step(1.0 - uv.y, 0.0)
+        step(view_uv.x, 0.0) + step(1.0 - view_uv.x, 0.0) +
+        step(view_uv.y, 0.0) + step(1.0 - view_uv.y, 0.0)
     ));
     let bg = v4f(uniforms.bg_r, uniforms.bg_g, uniforms.bg_b, uniforms.bg_a);
-    let src_a = mix(textureSample(t_diffuse_a, s_diffuse, uv), bg, out_of_bounds);
-    let src_b = mix(textureSample(t_diffuse_b, s_diffuse, uv), bg, out_of_bounds);
+    let color_a = textureSample(t_diffuse_a, s_diffuse, view_uv);
+    let color_b = textureSample(t_diffuse_b, s_diffuse, view_uv);
+    let color = mix(color_a, color_b, v4f(f32(tex_index)));
+    return mix(color, bg, out_of_bounds);
+}
+
+// slide, the new image pushes the current image out of the window
+// dir: 1.0 = the new image comes from the right, -1.0 = from the left
+fn ts_slide(screen_uv: v2f, t: f32, dir: f32) -> v4f {
+    // progress of the slide, the blend goes from the current image `b` to the new image `a`
+    let p = 1.0 - t;
+    let old_screen_uv = v2f(screen_uv.x + (p * dir), screen_uv.y);
+    let new_screen_uv = v2f(screen_uv.x + ((p - 1.0) * dir), screen_uv.y);
+    let old_uv = to_view_uv(to_image_uv(old_screen_uv));
+    let new_uv = to_view_uv(to_image_uv(new_screen_uv));
+
+    // the textures of the images are swapped by the flip
+    let new_index = i32(uniforms.flip);
+    let old_color = sample_view(1 - new_index, old_uv);
+    let new_color = sample_view(new_index, new_uv);
+    let is_old = step(0.0, old_screen_uv.x) * step(old_screen_uv.x, 1.0);
+    return mix(new_color, old_color, v4f(is_old));
+}
+
+// compare, the pinned image on the left of the split and the current image on the right
+// the texture index of the pinned image is in the flip
+fn compare_split(screen_uv: v2f, view_uv: v2f) -> v4f {
+    let pinned_index = i32(uniforms.flip);
+    let pinned_color = sample_view(pinned_index, view_uv);
+    let current_color = sample_view(1 - pinned_index, view_uv);
+    return mix(pinned_color, current_color, v4f(step(uniforms.split_x, screen_uv.x)));
+}
+
+[[stage(fragment)]]
+fn main(in: VertexOutput) -> [[location(0)]] v4f {
+    let screen_uv = rotate_screen_uv(in.tex_coords);
+    let uv = to_image_uv(screen_uv);
+    let view_uv = to_view_uv(uv);
+    let src_a = sample_view(0, view_uv);
+    let src_b = sample_view(1, view_uv);
 
     let a = mix(src_a, src_b, v4f(uniforms.flip));
     let b = mix(src_b, src_a, v4f(uniforms.flip));
@@ -178,7 +265,16 @@ fn main(in: VertexOutput) -> [[location(0)]] v4f {
         case 19: { ret = ts_box_in(a, b, t, uv); }
         case 20: { ret = ts_randomsquares(a, b, t, uv); }
         case 21: { ret = ts_angular(a, b, t, uv); }
+        case 22: { ret = ts_slide(screen_uv, t, uniforms.slide_dir); }
+        case 23: { ret = compare_split(in.tex_coords, view_uv); }
         default: { ret = ts_crossfading(a, b, t); }
     }
-    return ret;
+
+    // brightness, contrast and gamma
+    let rgb = ((ret.rgb - 0.5) * uniforms.contrast) + 0.5 + uniforms.brightness;
+    let rgb = clamp(rgb, vec3<f32>(0.0, 0.0, 0.0), vec3<f32>(1.0, 1.0, 1.0));
+    let rgb = pow(rgb, vec3<f32>(1.0 / uniforms.gamma, 1.0 / uniforms.gamma, 1.0 / uniforms.gamma));
+    // dim schedule
+    let rgb = rgb * uniforms.dim;
+    return v4f(rgb, ret.a);
 }
@@ -2,8 +2,11 @@
 use crate::common_win32;
 
 use crate::config::{ResizeFilterType, CONF_FILE_EXTENSION};
+use anyhow::Result;
+use chrono::Local;
 use copypasta::{ClipboardContext, ClipboardProvider};
 use std::{
+    borrow::Cow,
     path::{Path, PathBuf},
     time::Duration,
 };
@@ -19,6 +22,28 @@ pub const fn convert_filter_type(src: &ResizeFilterType) -> image::imageops::Fil
     }
 }
 
+/// Get the file path next to the config file ('<config file>.<extension>'),
+/// or in the home folder without the config file ('~/.sldshow.<extension>')
+pub fn config_sidecar_path(conf_path: Option<&Path>, extension: &str) -> Option<PathBuf> {
+    let mut path = match conf_path {
+        Some(conf_path) => std::fs::canonicalize(conf_path)
+            .unwrap_or_else(|_| conf_path.to_path_buf())
+            .into_os_string(),
+        None => dirs::home_dir()?.join(".sldshow").into_os_string(),
+    };
+    path.push(".");
+    path.push(extension);
+    Some(PathBuf::from(path))
+}
+
+/// Get the minute of the day (0-1439) in the local time zone
+pub fn local_minute_of_day() -> u32 {
+    use chrono::Timelike;
+
+    let now = chrono::Local::now();
+    now.hour() * 60 + now.minute()
+}
+
 pub fn rgba_u8_to_f32(input: [u8; 4]) -> [f32; 4] {
     let mut output = [0.0; 4];
     for i in 0..4 {
@@ -82,6 +107,71 @@ pub fn get_config_file_path() -> Option<PathBuf> {
     None
 }
 
+/// Get the shuffle seed from args ('--shuffle-seed <u64>' or '--shuffle-seed=<u64>')
+pub fn get_arg_shuffle_seed() -> Option<u64> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    for (i, arg) in args.iter().enumerate() {
+        let value = if arg == "--shuffle-seed" {
+            args.get(i + 1).map(|v| v.as_str())
+        } else {
+            arg.strip_prefix("--shuffle-seed=")
+        };
+
+        if let Some(value) = value {
+            match value.parse() {
+                Ok(seed) => return Some(seed),
+                Err(err) => log::error!("invalid shuffle seed '{}': {}", value, err),
+            }
+        }
+    }
+
+    None
+}
+
+/// Format the byte size in 1024-based units, e.g. "2.1 MB"
+pub fn format_file_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[unit])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Get a path which does not exist yet by appending a counter, e.g. "name_1.png"
+pub fn unique_path(dir: &Path, stem: &str, ext: &str) -> PathBuf {
+    let mut path = dir.join(format!("{}.{}", stem, ext));
+    let mut counter = 1;
+    while path.exists() {
+        path = dir.join(format!("{}_{}.{}", stem, counter, ext));
+        counter += 1;
+    }
+    path
+}
+
+/// Save the rendered window to 'screenshot_<local time>.png' in the directory
+pub fn save_screenshot(dir: &Path, image: &image::RgbaImage) -> Result<PathBuf> {
+    let stem = format!("screenshot_{}", Local::now().format("%Y%m%d_%H%M%S"));
+    let path = unique_path(dir, &stem, "png");
+    image::save_buffer(
+        &path,
+        image,
+        image.width(),
+        image.height(),
+        image::ColorType::Rgba8,
+    )?;
+
+    Ok(path)
+}
+
 pub fn path_copy_to_clipboard(path: &Path) -> bool {
     match ClipboardContext::new() {
         Ok(mut ctx) => {
@@ -98,6 +188,56 @@ pub fn path_copy_to_clipboard(path: &Path) -> bool {
     false
 }
 
+/// Open the folder of the file in the file manager, select the file if the platform supports it
+pub fn reveal_in_file_manager(path: &Path) -> Result<()> {
+    if !path.exists() {
+        return Err(anyhow::anyhow!("File not found: {}", path.display()));
+    }
+    // Not canonicalized, Explorer does not accept the verbatim "\\?\" paths
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()?.join(path)
+    };
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        // Explorer expects the path right after the comma, quoted as a whole
+        std::process::Command::new("explorer")
+            .raw_arg(format!("/select,\"{}\"", path.display()))
+            .spawn()?;
+    }
+    #[cfg(target_os = "macos")]
+    {
+        std::process::Command::new("open")
+            .arg("-R")
+            .arg(&path)
+            .spawn()?;
+    }
+    #[cfg(not(any(windows, target_os = "macos")))]
+    {
+        let folder = path
+            .parent()
+            .ok_or_else(|| anyhow::anyhow!("No parent folder: {}", path.display()))?;
+        std::process::Command::new("xdg-open").arg(folder).spawn()?;
+    }
+
+    Ok(())
+}
+
+/// Copy the RGBA image to the clipboard
+pub fn image_copy_to_clipboard(image: &image::RgbaImage) -> anyhow::Result<()> {
+    let mut clipboard = arboard::Clipboard::new()?;
+    clipboard.set_image(arboard::ImageData {
+        width: image.width() as usize,
+        height: image.height() as usize,
+        bytes: Cow::Borrowed(image.as_raw()),
+    })?;
+
+    Ok(())
+}
+
 pub fn modulo<T>(a: T, b: T) -> T
 where
     T: std::ops::Add<Output = T> + std::ops::Rem<Output = T> + Copy,
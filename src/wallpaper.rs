@@ -0,0 +1,59 @@
+#[cfg(feature = "wallpaper")]
+use crate::image_loader::{is_remote_path, ImageLoader};
+#[cfg(feature = "wallpaper")]
+use crate::multi_image;
+use anyhow::{anyhow, Result};
+use std::path::Path;
+#[cfg(feature = "wallpaper")]
+use std::path::PathBuf;
+
+/// Set the image as the desktop wallpaper, the remote images and the TIFF pages are
+/// written to a file in the cache folder first, it has to stay while it's the wallpaper
+#[cfg(feature = "wallpaper")]
+pub fn set_wallpaper(path: &Path) -> Result<()> {
+    let file_path = if is_remote_path(path) {
+        let bytes = ImageLoader::download(path)?;
+        let format = image::guess_format(&bytes)?;
+        let extension = format.extensions_str().first().copied().unwrap_or("img");
+        let file_path = wallpaper_file(extension)?;
+        std::fs::write(&file_path, &bytes)?;
+        file_path
+    } else if let Some((tiff_path, page)) = multi_image::split_page_path(path) {
+        let file_path = wallpaper_file("png")?;
+        multi_image::open_tiff_page(&tiff_path, page)?
+            .save_with_format(&file_path, image::ImageFormat::Png)?;
+        file_path
+    } else {
+        path.to_path_buf()
+    };
+
+    // Not canonicalized, Windows does not accept the verbatim "\\?\" paths
+    let file_path = if file_path.is_absolute() {
+        file_path
+    } else {
+        std::env::current_dir()?.join(file_path)
+    };
+    let file_path = file_path
+        .to_str()
+        .ok_or_else(|| anyhow!("invalid path: {}", file_path.display()))?;
+    // The error of the crate is not Send
+    wallpaper::set_from_path(file_path).map_err(|err| anyhow!("{}", err))
+}
+
+#[cfg(not(feature = "wallpaper"))]
+pub fn set_wallpaper(_path: &Path) -> Result<()> {
+    Err(anyhow!(
+        "setting the wallpaper is not supported in this build."
+    ))
+}
+
+/// Get the wallpaper file ('<cache dir>/sldshow/wallpaper.<extension>'),
+/// the previous one is replaced
+#[cfg(feature = "wallpaper")]
+fn wallpaper_file(extension: &str) -> Result<PathBuf> {
+    let dir = dirs::cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("sldshow");
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir.join(format!("wallpaper.{}", extension)))
+}
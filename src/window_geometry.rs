@@ -0,0 +1,64 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::monitor::MonitorHandle;
+
+pub const GEOMETRY_FILE_EXTENSION: &str = "geometry";
+
+/// Window position and size saved to restore on the next launch
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl WindowGeometry {
+    pub fn new(position: PhysicalPosition<i32>, size: PhysicalSize<u32>) -> Self {
+        Self {
+            x: position.x,
+            y: position.y,
+            width: size.width,
+            height: size.height,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = &fs::read_to_string(path)?;
+        let geometry: Self = toml::from_str(data)?;
+
+        Ok(geometry)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, toml::to_string(self)?)?;
+
+        Ok(())
+    }
+
+    pub fn position(&self) -> PhysicalPosition<i32> {
+        PhysicalPosition::new(self.x, self.y)
+    }
+
+    pub fn size(&self) -> PhysicalSize<u32> {
+        PhysicalSize::new(self.width, self.height)
+    }
+
+    /// Return true if the top-left corner is on one of the connected monitors
+    pub fn is_on_monitors(&self, mut monitors: impl Iterator<Item = MonitorHandle>) -> bool {
+        if self.width == 0 || self.height == 0 {
+            return false;
+        }
+        monitors.any(|monitor| {
+            let position = monitor.position();
+            let size = monitor.size();
+            self.x >= position.x
+                && self.y >= position.y
+                && self.x < position.x + size.width as i32
+                && self.y < position.y + size.height as i32
+        })
+    }
+}